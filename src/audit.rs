@@ -0,0 +1,96 @@
+use anyhow::Result;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::Mutex;
+
+use crate::TalosctlError;
+
+// Argument names whose values are never written to the audit log.
+const SECRET_KEY_PARTS: &[&str] = &[
+    "secret",
+    "token",
+    "password",
+    "passwd",
+    "key",
+    "cert",
+    "credential",
+    "private",
+    // Inline machine config content carries the cluster PKI
+    "content",
+    // Config patches can carry registry credentials, tokens and certificates
+    "patch",
+];
+
+// Append a JSON line describing a tool invocation to TALOS_AUDIT_LOG, if set.
+// Each entry is flushed and synced so the log survives a crash.
+pub fn record(tool: &str, params_map: &HashMap<String, Value>, result: &Result<Value>) {
+    let Ok(path) = env::var("TALOS_AUDIT_LOG") else {
+        return;
+    };
+    if path.is_empty() {
+        return;
+    }
+    let entry = entry(tool, params_map, result);
+
+    // Serialize writers so concurrent streaming calls never interleave lines
+    static WRITE_LOCK: Mutex<()> = Mutex::new(());
+    let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let written = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| {
+            writeln!(file, "{}", entry)?;
+            file.flush()?;
+            file.sync_data()
+        });
+    if let Err(e) = written {
+        tracing::error!(path = %path, error = %e, "Failed to write audit log");
+    }
+}
+
+// The audit log entry for a tool invocation, with secret-looking arguments redacted.
+pub fn entry(tool: &str, params_map: &HashMap<String, Value>, result: &Result<Value>) -> Value {
+    let args: Map<String, Value> = params_map
+        .iter()
+        .map(|(k, v)| (k.clone(), redact(k, v)))
+        .collect();
+    let exit_code = match result {
+        Ok(_) => Some(0),
+        Err(e) => e
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<TalosctlError>())
+            .and_then(|err| err.exit_code),
+    };
+    json!({
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "tool": tool,
+        "node": params_map.get("node"),
+        "args": args,
+        "result": if result.is_ok() { "ok" } else { "error" },
+        "exit_code": exit_code
+    })
+}
+
+// Request params with secret-looking values replaced, for logging.
+pub fn redact_params(params: &Value) -> Value {
+    redact("", params)
+}
+
+// Replace values of secret-looking arguments, recursing into nested objects.
+fn redact(key: &str, value: &Value) -> Value {
+    let key = key.to_ascii_lowercase();
+    if SECRET_KEY_PARTS.iter().any(|part| key.contains(part)) {
+        return json!("[REDACTED]");
+    }
+    match value {
+        Value::Object(obj) => {
+            Value::Object(obj.iter().map(|(k, v)| (k.clone(), redact(k, v))).collect())
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|v| redact("", v)).collect()),
+        other => other.clone(),
+    }
+}
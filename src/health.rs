@@ -0,0 +1,99 @@
+use serde_json::{json, Map, Value};
+
+// Break `talosctl health` output down per node. Each "waiting for <check>: <status>"
+// line is reported against the nodes its status mentions; a check that passed counts
+// for every node, and failures naming no known node are listed as unattributed.
+pub fn parse_report(output: &str, nodes: &[&str], succeeded: bool) -> Value {
+    let checks = final_check_statuses(output);
+
+    let mut per_node = Map::new();
+    let mut unattributed = Vec::new();
+    for node in nodes {
+        per_node.insert(node.to_string(), json!({"checks": [], "healthy": true}));
+    }
+
+    for (name, status) in &checks {
+        let passed = status == "OK";
+        let failing_nodes: Vec<&str> = if passed {
+            Vec::new()
+        } else {
+            nodes
+                .iter()
+                .copied()
+                .filter(|node| mentions_node(status, node))
+                .collect()
+        };
+        if !passed && failing_nodes.is_empty() {
+            unattributed.push(json!({"check": name, "message": status}));
+            continue;
+        }
+
+        for node in nodes {
+            let failed = failing_nodes.contains(node);
+            let entry = &mut per_node[*node];
+            if let Some(list) = entry["checks"].as_array_mut() {
+                list.push(json!({
+                    "check": name,
+                    "status": if failed { "failed" } else { "ok" },
+                    "message": if failed { Some(status) } else { None }
+                }));
+            }
+            if failed {
+                entry["healthy"] = json!(false);
+            }
+        }
+    }
+
+    let all_nodes_healthy = per_node.values().all(|n| n["healthy"] == json!(true));
+    json!({
+        "per_node": per_node,
+        "unattributed_failures": unattributed,
+        "overall_healthy": succeeded && all_nodes_healthy && unattributed.is_empty()
+    })
+}
+
+// The last reported status of each check, in first-seen order. talosctl reprints a
+// check as it progresses, and indented lines continue the previous check's message.
+fn final_check_statuses(output: &str) -> Vec<(String, String)> {
+    let mut checks: Vec<(String, String)> = Vec::new();
+    let mut current: Option<usize> = None;
+
+    for line in output.lines() {
+        if let Some((name, status)) = line
+            .strip_prefix("waiting for ")
+            .and_then(|rest| rest.split_once(": "))
+        {
+            let idx = match checks.iter().position(|(n, _)| n == name) {
+                Some(idx) => idx,
+                None => {
+                    checks.push((name.to_string(), String::new()));
+                    checks.len() - 1
+                }
+            };
+            checks[idx].1 = status.trim().to_string();
+            current = Some(idx);
+        } else if line.starts_with(char::is_whitespace) && !line.trim().is_empty() {
+            if let Some(idx) = current {
+                let status = &mut checks[idx].1;
+                status.push('\n');
+                status.push_str(line.trim());
+            }
+        } else {
+            current = None;
+        }
+    }
+
+    // Drop checks that never reported a status
+    checks.retain(|(_, status)| !status.is_empty());
+    checks
+}
+
+// Whether text mentions a node address as a whole token, so 10.0.0.1 doesn't match 10.0.0.10.
+fn mentions_node(text: &str, node: &str) -> bool {
+    let is_addr_char = |c: char| c.is_ascii_alphanumeric() || c == '.' || c == '-';
+    text.match_indices(node).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + node.len()..].chars().next();
+        !before.is_some_and(is_addr_char) && !after.is_some_and(is_addr_char)
+    })
+}
@@ -1,13 +1,138 @@
 use anyhow::{anyhow, Context, Result};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::env;
-use std::process::{Command, Stdio};
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use std::fmt;
+use std::process::{Output, Stdio};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::Command;
 use tokio::runtime::Runtime;
+use tokio::sync::{Mutex, Semaphore};
+use tokio::task::JoinSet;
+use tracing::Instrument;
 
+// How long a talosctl command may run unless TALOSCTL_TIMEOUT or a per-call timeout says otherwise.
+const DEFAULT_TALOSCTL_TIMEOUT: Duration = Duration::from_secs(120);
+
+// How many talosctl commands may run at once unless TALOSCTL_MAX_CONCURRENCY says otherwise.
+const DEFAULT_TALOSCTL_MAX_CONCURRENCY: usize = 8;
+
+// First delay between retries of a transient failure, doubled on each further attempt,
+// unless TALOSCTL_RETRY_BACKOFF says otherwise.
+const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+// How long in-flight requests get to finish after a shutdown signal, unless
+// TALOS_SHUTDOWN_GRACE says otherwise.
+const DEFAULT_SHUTDOWN_GRACE: Duration = Duration::from_secs(30);
+
+// Size cap for captures returned inline (base64) rather than written to output_file.
+const PCAP_INLINE_MAX_BYTES: usize = 64 * 1024;
+
+// How long the startup check gives `talosctl version --client` to answer.
+const PREFLIGHT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Slack given to talosctl commands that wait (get_health, upgrade_node with wait,
+// capture_packets) beyond their own timeout.
+const WAIT_TIMEOUT_MARGIN: Duration = Duration::from_secs(30);
+
+// How long upgrade_node with wait blocks for the node to come back, matching talosctl.
+const UPGRADE_WAIT_TIMEOUT: &str = "30m";
+
+tokio::task_local! {
+    // Per-call talosctl timeout from a tool's `timeout` argument
+    static CALL_TIMEOUT: Duration;
+
+    // Per-call API endpoints from a tool's `endpoint`/`endpoints` argument
+    static CALL_ENDPOINTS: String;
+
+    // Per-call talosconfig context from a tool's `context` argument
+    static CALL_CONTEXT: String;
+
+    // Whether the current call's tool is read-only, so its commands may be retried
+    static CALL_READ_ONLY: bool;
+
+    // Set while a call runs with `explain: true`
+    static CALL_EXPLAIN: bool;
+}
+
+// Whether the current call only explains its commands. Safety pre-flights are skipped
+// and no local files are written, since nothing is actually run.
+fn explaining() -> bool {
+    CALL_EXPLAIN.try_with(|explain| *explain).unwrap_or(false)
+}
+
+// How long a one-shot get_events call captures the (never-ending) event stream.
+const EVENTS_CAPTURE_WINDOW: Duration = Duration::from_secs(3);
+
+// Number of dmesg lines and events collect_diagnostics includes.
+const DIAGNOSTICS_TAIL: u64 = 50;
+
+// How often service wait polls the service status.
+const SERVICE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// MCP protocol versions the server speaks, preferred (newest) first.
+const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+// How often wait_node_ready probes the node, and how long each probe may take.
+const NODE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const NODE_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// How many of the largest entries a multi-path get_usage summary lists.
+const USAGE_SUMMARY_LIMIT: usize = 20;
+
+// Longest an `explain` call may take, so tools that poll for a state report the commands
+// recorded so far rather than polling for their whole timeout.
+const EXPLAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+// How many tool schemas a single tools/list page carries.
+const TOOLS_PAGE_SIZE: usize = 25;
+
+// Tools that only make sense against control plane nodes.
+const ETCD_METHODS: &[&str] = &[
+    "get_etcd_status",
+    "get_etcd_members",
+    "defrag_etcd",
+    "bootstrap_etcd",
+    "etcd_snapshot",
+    "etcd_restore",
+    "etcd_remove_member",
+    "etcd_forfeit_leadership",
+];
+
+// Like anyhow!, for a failure caused by the request's params (JSON-RPC -32602).
+macro_rules! invalid_params {
+    ($($arg:tt)*) => {
+        anyhow::Error::new($crate::RpcFailure::InvalidParams(format!($($arg)*)))
+    };
+}
+
+// Like anyhow!, for a request naming a method the server doesn't have (JSON-RPC -32601).
+macro_rules! method_not_found {
+    ($($arg:tt)*) => {
+        anyhow::Error::new($crate::RpcFailure::MethodNotFound(format!($($arg)*)))
+    };
+}
+
+mod audit;
+mod health;
+mod metrics;
+mod nodes;
+mod output;
+mod redact;
+mod resources;
+mod runner;
+mod streaming;
 mod tools;
+mod transport;
+mod workdir;
+
+use runner::{CommandRunner, ExplainRunner, TalosctlRunner, UnavailableRunner};
+use streaming::{InFlight, SharedStdout};
 
 // Custom error type for production-ready error handling.
 #[derive(Debug, Serialize)]
@@ -17,6 +142,47 @@ struct RpcError {
     data: Option<Value>,
 }
 
+// JSON-RPC error codes.
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+const INTERNAL_ERROR: i32 = -32603;
+
+// A request failure the client caused, reported with its specific JSON-RPC code.
+// Anything else, including talosctl failures, is an internal error.
+#[derive(Debug)]
+enum RpcFailure {
+    ParseError(String),
+    InvalidRequest(String),
+    MethodNotFound(String),
+    InvalidParams(String),
+}
+
+impl RpcFailure {
+    fn code(&self) -> i32 {
+        match self {
+            RpcFailure::ParseError(_) => PARSE_ERROR,
+            RpcFailure::InvalidRequest(_) => INVALID_REQUEST,
+            RpcFailure::MethodNotFound(_) => METHOD_NOT_FOUND,
+            RpcFailure::InvalidParams(_) => INVALID_PARAMS,
+        }
+    }
+}
+
+impl fmt::Display for RpcFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RpcFailure::ParseError(msg)
+            | RpcFailure::InvalidRequest(msg)
+            | RpcFailure::MethodNotFound(msg)
+            | RpcFailure::InvalidParams(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for RpcFailure {}
+
 // JSON-RPC Success Response structure.
 #[derive(Serialize)]
 struct RpcSuccessResponse {
@@ -43,1157 +209,7263 @@ struct RpcRequest {
     id: Option<Value>,
 }
 
-// Helper to run talosctl command and capture output.
-fn run_talosctl(args: &[&str]) -> Result<String> {
-    let talosconfig = env::var("TALOSCONFIG").context("TALOSCONFIG env var not set")?;
-    let mut cmd = Command::new("talosctl");
-    cmd.arg("--talosconfig").arg(&talosconfig);
-    cmd.args(args);
-    cmd.stderr(Stdio::piped());
-    let output = cmd.output().context("Failed to execute talosctl")?;
-    if !output.status.success() {
-        let err = String::from_utf8_lossy(&output.stderr).to_string();
-        return Err(anyhow!("talosctl failed: {}", err));
-    }
-    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+// stderr patterns that mean the client certificate is expired or otherwise rejected.
+const CERT_ERROR_PATTERNS: &[&str] = &[
+    "certificate has expired",
+    "expired certificate",
+    "certificate is not yet valid",
+    "x509:",
+];
+
+// stderr patterns of failures that tend to clear up on their own, such as a node that
+// is briefly unreachable during a reboot or an etcd leader election.
+const TRANSIENT_ERROR_PATTERNS: &[&str] = &[
+    "connection refused",
+    "connection reset",
+    "deadline exceeded",
+    "no route to host",
+    "error reading from server: eof",
+    "transport is closing",
+    "code = unavailable",
+    "leader changed",
+];
+
+// Error from a talosctl invocation that exited unsuccessfully.
+#[derive(Debug)]
+struct TalosctlError {
+    exit_code: Option<i32>,
+    stderr: String,
+    // Broad failure class (e.g. "auth") when the stderr matches a known pattern
+    category: Option<&'static str>,
+    // The talosctl arguments that failed, without the talosconfig path
+    args: Vec<String>,
 }
 
-// Helper to run talosctl command and capture stderr output (for health checks).
-fn run_talosctl_with_stderr(args: &[&str]) -> Result<String> {
-    let talosconfig = env::var("TALOSCONFIG").context("TALOSCONFIG env var not set")?;
-    let mut cmd = Command::new("talosctl");
-    cmd.arg("--talosconfig").arg(&talosconfig);
-    cmd.args(args);
-    cmd.stderr(Stdio::piped());
-    let output = cmd.output().context("Failed to execute talosctl")?;
-    if !output.status.success() {
-        let err = String::from_utf8_lossy(&output.stderr).to_string();
-        return Err(anyhow!("talosctl failed: {}", err));
+impl TalosctlError {
+    fn timed_out(args: &[&str], after: Duration) -> Self {
+        TalosctlError {
+            exit_code: None,
+            stderr: format!("timed out after {}s", after.as_secs()),
+            category: Some("timeout"),
+            args: reported_args(args),
+        }
     }
-    // For health checks, the useful output is in stderr, not stdout
-    Ok(String::from_utf8_lossy(&output.stderr).to_string())
-}
 
-// Capabilities advertised by the server with full MCP tool schemas.
-fn get_capabilities() -> Value {
-    tools::get_all_tool_schemas()
+    fn new(args: &[&str], exit_code: Option<i32>, stderr: String) -> Self {
+        let lowered = stderr.to_lowercase();
+        let matches = |patterns: &[&str]| patterns.iter().any(|p| lowered.contains(p));
+        let category = if matches(CERT_ERROR_PATTERNS) {
+            Some("auth")
+        } else if matches(TRANSIENT_ERROR_PATTERNS) {
+            Some("transient")
+        } else {
+            None
+        };
+        TalosctlError {
+            exit_code,
+            stderr,
+            category,
+            args: reported_args(args),
+        }
+    }
+
+    // Actionable advice for categorized failures.
+    fn hint(&self) -> Option<&'static str> {
+        match self.category {
+            Some("auth") => Some(
+                "the Talos client certificate appears expired or invalid; refresh the talosconfig \
+                 (e.g. regenerate it with `talosctl config new` or re-export it from the cluster \
+                 secrets) and check that the clocks on this host and the node are in sync",
+            ),
+            Some("timeout") => Some(
+                "the node may be unreachable or the operation slow; pass a larger `timeout` \
+                 argument or raise TALOSCTL_TIMEOUT",
+            ),
+            Some("transient") => Some(
+                "the node or Talos API was briefly unavailable (e.g. rebooting or electing an \
+                 etcd leader); try again shortly, or set TALOSCTL_RETRIES to retry read-only \
+                 tools automatically",
+            ),
+            _ => None,
+        }
+    }
+
+    // Structured JSON-RPC error data, so clients can tell failures apart without
+    // parsing the message.
+    fn error_data(&self) -> Value {
+        json!({
+            "exit_code": self.exit_code,
+            "stderr": self.stderr,
+            "args": self.args,
+            "category": self.category,
+            "hint": self.hint()
+        })
+    }
 }
 
-// Extract parameters from JSON value into HashMap
-fn extract_params(params: Option<&Value>) -> HashMap<String, Value> {
-    params
-        .and_then(|p| {
-            p.as_object()
-                .map(|o| o.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+// Arguments as reported in errors, with any talosconfig path redacted.
+fn reported_args(args: &[&str]) -> Vec<String> {
+    let mut redact_next = false;
+    args.iter()
+        .map(|arg| {
+            let value = if redact_next { "[REDACTED]" } else { arg };
+            redact_next = *arg == "--talosconfig";
+            value.to_string()
         })
-        .unwrap_or_default()
+        .collect()
 }
 
-// Handle system inspection and monitoring methods
-fn handle_system_inspection_methods(
-    method: &str,
-    params_map: &HashMap<String, Value>,
-) -> Option<Result<Value>> {
-    match method {
-        "containers" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            let kubernetes = params_map
-                .get("kubernetes")
-                .and_then(|v| v.as_bool())
-                .unwrap_or(false);
-            match node {
-                Ok(node) => {
-                    let mut args = vec!["--nodes", node, "containers"];
-                    if kubernetes {
-                        args.push("--kubernetes");
-                    }
-                    let output = run_talosctl(&args);
-                    Some(output.map(|out| json!({"containers": out, "namespace": if kubernetes { "k8s.io" } else { "system" }})))
-                }
-                Err(e) => Some(Err(e)),
-            }
+impl fmt::Display for TalosctlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.category == Some("timeout") {
+            return write!(f, "talosctl {}", self.stderr);
         }
-        "stats" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            let kubernetes = params_map
-                .get("kubernetes")
-                .and_then(|v| v.as_bool())
-                .unwrap_or(false);
-            match node {
-                Ok(node) => {
-                    let mut args = vec!["--nodes", node, "stats"];
-                    if kubernetes {
-                        args.push("--kubernetes");
-                    }
-                    let output = run_talosctl(&args);
-                    Some(output.map(|out| json!({"stats": out, "namespace": if kubernetes { "k8s.io" } else { "system" }})))
-                }
-                Err(e) => Some(Err(e)),
-            }
+        write!(f, "talosctl failed: {}", self.stderr.trim_end())?;
+        if let Some(hint) = self.hint() {
+            write!(f, " (hint: {})", hint)?;
         }
-        "memory_verbose" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            match node {
-                Ok(node) => {
-                    let output = run_talosctl(&["--nodes", node, "memory", "--verbose"]);
-                    Some(output.map(|out| json!({"memory_verbose": out})))
-                }
-                Err(e) => Some(Err(e)),
+        Ok(())
+    }
+}
+
+impl std::error::Error for TalosctlError {}
+
+// Failure of a tool that gathers several sections, carrying the sections that succeeded.
+#[derive(Debug)]
+struct PartialFailure {
+    error: anyhow::Error,
+    partial: Value,
+}
+
+impl PartialFailure {
+    // The failed command's error data, plus the partial result.
+    fn error_data(&self) -> Value {
+        let mut data = self
+            .error
+            .downcast_ref::<TalosctlError>()
+            .map_or_else(|| json!({}), TalosctlError::error_data);
+        data["partial_result"] = self.partial.clone();
+        data
+    }
+}
+
+impl fmt::Display for PartialFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl std::error::Error for PartialFailure {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.error.as_ref())
+    }
+}
+
+// Combine independently fetched sections into one object. If any failed, the first
+// failure is returned with the sections that did succeed as its partial result.
+fn combine_sections(sections: Vec<(&str, Result<String>)>) -> Result<Value> {
+    let mut result = serde_json::Map::new();
+    let mut first_error = None;
+    for (key, section) in sections {
+        match section {
+            Ok(out) => {
+                result.insert(key.to_string(), json!(out));
             }
-        }
-        "get_cpu_memory_usage" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            match node {
-                Ok(node) => {
-                    let mem = run_talosctl(&["--nodes", node, "memory"]);
-                    let cgroups = run_talosctl(&["--nodes", node, "cgroups", "--preset", "cpu"]);
-                    match (mem, cgroups) {
-                        (Ok(mem), Ok(cgroups)) => Some(Ok(json!({"memory": mem, "cpu": cgroups}))),
-                        (Err(e), _) | (_, Err(e)) => Some(Err(e)),
-                    }
-                }
-                Err(e) => Some(Err(e)),
+            Err(e) => {
+                first_error.get_or_insert(e);
             }
         }
-        "get_processes" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            let sort = params_map
-                .get("sort")
-                .and_then(|v| v.as_str())
-                .unwrap_or("rss");
-            match node {
-                Ok(node) => {
-                    let args = vec!["--nodes", node, "processes", "--sort", sort];
-                    let output = run_talosctl(&args);
-                    Some(output.map(|out| json!({"processes": out, "sort_by": sort})))
-                }
-                Err(e) => Some(Err(e)),
-            }
+    }
+    match first_error {
+        None => Ok(Value::Object(result)),
+        Some(error) => Err(PartialFailure {
+            error,
+            partial: Value::Object(result),
         }
-        _ => None,
+        .into()),
     }
 }
 
-// Handle file system operations
-fn handle_file_operations_methods(
-    method: &str,
-    params_map: &HashMap<String, Value>,
-) -> Option<Result<Value>> {
-    match method {
-        "list" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            let path = params_map
-                .get("path")
-                .and_then(|v| v.as_str())
-                .unwrap_or("/");
-            let long = params_map
-                .get("long")
-                .and_then(|v| v.as_bool())
-                .unwrap_or(false);
-            let humanize = params_map
-                .get("humanize")
-                .and_then(|v| v.as_bool())
-                .unwrap_or(false);
-            let recurse = params_map
-                .get("recurse")
-                .and_then(|v| v.as_bool())
-                .unwrap_or(false);
-            let depth = params_map
-                .get("depth")
-                .and_then(|v| v.as_i64())
-                .unwrap_or(1);
-            let file_types = params_map
-                .get("type")
-                .and_then(|v| v.as_array())
-                .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>());
+// A talosctl command pointed at $TALOSCONFIG, ready for args.
+fn talosctl_command() -> Result<Command> {
+    let talosconfig = env::var("TALOSCONFIG").context("TALOSCONFIG env var not set")?;
+    let mut cmd = Command::new("talosctl");
+    cmd.arg("--talosconfig").arg(talosconfig);
+    cmd.args(call_global_args());
+    cmd.kill_on_drop(true);
+    Ok(cmd)
+}
 
-            match node {
-                Ok(node) => {
-                    let mut args = vec!["--nodes", node, "list", path];
-                    let depth_str = depth.to_string();
+// Global talosctl flags set by the current call's `context` and `endpoint` arguments.
+fn call_global_args() -> Vec<String> {
+    let mut args = Vec::new();
+    if let Ok(context) = CALL_CONTEXT.try_with(|c| c.clone()) {
+        args.extend(["--context".to_string(), context]);
+    }
+    if let Ok(endpoints) = CALL_ENDPOINTS.try_with(|e| e.clone()) {
+        args.extend(["--endpoints".to_string(), endpoints]);
+    }
+    args
+}
 
-                    if long {
-                        args.push("--long");
-                    }
+// Time limit for the current talosctl command: the call's `timeout` argument, then
+// TALOSCTL_TIMEOUT, then the default.
+fn talosctl_timeout() -> Duration {
+    CALL_TIMEOUT.try_with(|t| *t).unwrap_or_else(|_| {
+        env::var("TALOSCTL_TIMEOUT")
+            .ok()
+            .and_then(|v| parse_duration(&v).ok())
+            .unwrap_or(DEFAULT_TALOSCTL_TIMEOUT)
+    })
+}
 
-                    if humanize {
-                        args.push("--humanize");
-                    }
+// Run a tool call with its `timeout`, `endpoint`/`endpoints` and `context` arguments,
+// if any, applied to every talosctl command it runs. Tools that give `timeout` their own
+// meaning are left alone.
+async fn with_call_options(
+    tool: &str,
+    params_map: &HashMap<String, Value>,
+    call: impl std::future::Future<Output = Result<Value>>,
+) -> Result<Value> {
+    let timeout = match params_map.get("timeout") {
+        Some(_) if tools::TOOLS_WITH_OWN_TIMEOUT.contains(&tool) => None,
+        Some(Value::Number(secs)) => secs.as_u64().map(Duration::from_secs),
+        Some(Value::String(value)) => Some(parse_duration(value)?),
+        _ => None,
+    };
+    let call = async {
+        match timeout {
+            Some(timeout) => CALL_TIMEOUT.scope(timeout, call).await,
+            None => call.await,
+        }
+    };
+    let call = CALL_READ_ONLY.scope(tools::is_read_only(tool), call);
+    let endpoints = call_endpoints(params_map);
+    if endpoints.is_some() && tools::TOOLS_WITH_OWN_ENDPOINTS.contains(&tool) {
+        return Err(invalid_params!(
+            "{} chooses its own endpoints; endpoint and endpoints are not supported",
+            tool
+        ));
+    }
+    let call = async {
+        match endpoints {
+            Some(endpoints) => CALL_ENDPOINTS.scope(endpoints, call).await,
+            None => call.await,
+        }
+    };
+    match call_context(params_map)? {
+        Some(context) => CALL_CONTEXT.scope(context, call).await,
+        None => call.await,
+    }
+}
 
-                    // --recurse and --depth are mutually exclusive
-                    if recurse {
-                        args.push("--recurse");
-                    } else if depth != 1 {
-                        args.extend(&["--depth", &depth_str]);
-                    }
+// talosctl --context value from the `context` argument. None leaves the talosconfig's
+// current context in effect.
+fn call_context(params_map: &HashMap<String, Value>) -> Result<Option<String>> {
+    match params_map.get("context") {
+        None => Ok(None),
+        Some(value) => match value.as_str().map(str::trim) {
+            Some(context) if !context.is_empty() => Ok(Some(context.to_string())),
+            _ => Err(invalid_params!(
+                "Invalid context {}: expected a talosconfig context name",
+                value
+            )),
+        },
+    }
+}
 
-                    if let Some(types) = &file_types {
-                        for file_type in types {
-                            args.extend(&["--type", file_type]);
-                        }
-                    }
+// Comma-separated talosctl --endpoints value from the `endpoints` array, or the
+// single `endpoint` string. None leaves talosconfig's endpoints in effect.
+fn call_endpoints(params_map: &HashMap<String, Value>) -> Option<String> {
+    let endpoints: Vec<&str> = match params_map.get("endpoints") {
+        Some(Value::Array(arr)) => arr.iter().filter_map(|v| v.as_str()).collect(),
+        _ => params_map
+            .get("endpoint")
+            .and_then(|v| v.as_str())
+            .into_iter()
+            .collect(),
+    };
+    let endpoints: Vec<&str> = endpoints
+        .into_iter()
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+        .collect();
+    (!endpoints.is_empty()).then(|| endpoints.join(","))
+}
 
-                    let output = run_talosctl(&args);
-                    Some(output.map(|out| {
-                        json!({
-                            "list": out,
-                            "path": path,
-                            "long": long,
-                            "humanize": humanize,
-                            "recurse": recurse,
-                            "depth": depth,
-                            "types": file_types
-                        })
-                    }))
+// Run a future that spawns talosctl while holding one of TALOSCTL_MAX_CONCURRENCY
+// slots, so a burst of calls queues rather than spawning a process each. Streaming
+// tools run for minutes and don't take a slot, so they can't starve other calls.
+async fn with_talosctl_slot<T>(run: impl std::future::Future<Output = T>) -> T {
+    static SLOTS: OnceLock<Semaphore> = OnceLock::new();
+    let slots = SLOTS.get_or_init(|| {
+        let max = env::var("TALOSCTL_MAX_CONCURRENCY")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .filter(|max| *max > 0)
+            .unwrap_or(DEFAULT_TALOSCTL_MAX_CONCURRENCY);
+        Semaphore::new(max)
+    });
+    let _slot = slots
+        .acquire()
+        .await
+        .expect("talosctl slots are never closed");
+    run.await
+}
+
+// Run a future in a talosctl slot, bounded by `timeout` counted from when it gets the
+// slot, so time spent queued behind other calls doesn't count against it.
+async fn with_talosctl_slot_timeout<F: std::future::Future>(
+    timeout: Duration,
+    run: F,
+) -> Result<F::Output, tokio::time::error::Elapsed> {
+    with_talosctl_slot(async { tokio::time::timeout(timeout, run).await }).await
+}
+
+// Await several futures concurrently within the current task, returning their
+// outputs in order. Unlike spawning, the futures may borrow the runner and keep the
+// call's task-local options.
+async fn join_all<F: std::future::Future>(futures: Vec<F>) -> Vec<F::Output> {
+    let mut futures: Vec<_> = futures.into_iter().map(Box::pin).collect();
+    let mut outputs: Vec<Option<F::Output>> = futures.iter().map(|_| None).collect();
+    std::future::poll_fn(|cx| {
+        let mut pending = false;
+        for (future, output) in futures.iter_mut().zip(outputs.iter_mut()) {
+            if output.is_none() {
+                match future.as_mut().poll(cx) {
+                    std::task::Poll::Ready(value) => *output = Some(value),
+                    std::task::Poll::Pending => pending = true,
                 }
-                Err(e) => Some(Err(e)),
             }
         }
-        "read" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            let path = params_map
-                .get("path")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing path param"));
-            match (node, path) {
-                (Ok(node), Ok(path)) => {
-                    let output = run_talosctl(&["--nodes", node, "read", path]);
-                    Some(output.map(|out| json!({"content": out})))
-                }
-                (Err(e), _) | (_, Err(e)) => Some(Err(e)),
-            }
+        if pending {
+            std::task::Poll::Pending
+        } else {
+            std::task::Poll::Ready(())
         }
-        "copy" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            let source = params_map
-                .get("source")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing source param"));
-            let destination = params_map
-                .get("destination")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing destination param"));
-            match (node, source, destination) {
-                (Ok(node), Ok(source), Ok(destination)) => {
-                    let output = run_talosctl(&["--nodes", node, "copy", source, destination]);
-                    Some(output.map(|out| json!({"copy": out})))
-                }
-                (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => Some(Err(e)),
-            }
+    })
+    .await;
+    outputs
+        .into_iter()
+        .map(|output| output.expect("every future completed"))
+        .collect()
+}
+
+// Run talosctl to completion and return its output, or a TalosctlError on failure.
+// With TALOS_AUTO_REFRESH set, an auth failure runs the configured refresh command
+// once and retries.
+async fn exec_talosctl(args: &[&str]) -> Result<Output> {
+    let timeout = talosctl_timeout();
+    let run = || async {
+        // Dropping the timed-out future kills the child (kill_on_drop)
+        let output: Output = with_talosctl_slot_timeout(
+            timeout,
+            talosctl_command()?.args(args).stdin(Stdio::null()).output(),
+        )
+        .await
+        .map_err(|_| TalosctlError::timed_out(args, timeout))?
+        .context("Failed to execute talosctl")?;
+        if !output.status.success() {
+            let err = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(TalosctlError::new(args, output.status.code(), err).into());
         }
-        "get_usage" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            let path = params_map
-                .get("path")
-                .and_then(|v| v.as_str())
-                .unwrap_or("/");
-            match node {
-                Ok(node) => {
-                    let output = run_talosctl(&["--nodes", node, "usage", path]);
-                    Some(output.map(|out| json!({"usage": out})))
-                }
-                Err(e) => Some(Err(e)),
+        Ok::<_, anyhow::Error>(output)
+    };
+
+    let category = |e: &anyhow::Error| {
+        e.downcast_ref::<TalosctlError>()
+            .and_then(|err| err.category)
+    };
+    let (retries, backoff) = retry_policy();
+    let mut attempt = 0;
+    loop {
+        let result = match run().await {
+            Err(e) if category(&e) == Some("auth") && refresh_talosconfig().await => run().await,
+            result => result,
+        };
+        match result {
+            Err(e) if category(&e) == Some("transient") && attempt < retries => {
+                let delay = backoff * 2u32.saturating_pow(attempt);
+                tracing::warn!(
+                    command = %args.join(" "),
+                    delay = ?delay,
+                    error = %e,
+                    "talosctl failed transiently, retrying"
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
             }
+            result => return result,
         }
-        "get_mounts" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            match node {
-                Ok(node) => {
-                    let output = run_talosctl(&["--nodes", node, "mounts"]);
-                    Some(output.map(|out| json!({"mounts": out})))
-                }
-                Err(e) => Some(Err(e)),
-            }
+    }
+}
+
+// How many times a transient failure is retried, and the first backoff delay. Retries
+// are opt-in through TALOSCTL_RETRIES and only apply to read-only tools, so nothing
+// that changes node state is ever run twice.
+fn retry_policy() -> (u32, Duration) {
+    if !CALL_READ_ONLY
+        .try_with(|read_only| *read_only)
+        .unwrap_or(false)
+    {
+        return (0, Duration::ZERO);
+    }
+    let retries = env::var("TALOSCTL_RETRIES")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(0);
+    let backoff = env::var("TALOSCTL_RETRY_BACKOFF")
+        .ok()
+        .and_then(|v| parse_duration(&v).ok())
+        .unwrap_or(DEFAULT_RETRY_BACKOFF);
+    (retries, backoff)
+}
+
+// Run TALOS_REFRESH_COMMAND once if TALOS_AUTO_REFRESH is enabled, returning whether
+// it succeeded. Its stdout is discarded so it can't corrupt the JSON-RPC stream.
+async fn refresh_talosconfig() -> bool {
+    let enabled = env::var("TALOS_AUTO_REFRESH").is_ok_and(|v| !v.is_empty() && v != "0");
+    let Ok(command) = env::var("TALOS_REFRESH_COMMAND") else {
+        return false;
+    };
+    if !enabled || command.trim().is_empty() {
+        return false;
+    }
+
+    match Command::new("sh")
+        .arg("-c")
+        .arg(&command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()
+        .await
+    {
+        Ok(status) => status.success(),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to run TALOS_REFRESH_COMMAND");
+            false
         }
-        _ => None,
     }
 }
 
-// Handle network operations
-fn handle_network_operations_methods(
-    method: &str,
-    params_map: &HashMap<String, Value>,
-) -> Option<Result<Value>> {
-    match method {
-        "interfaces" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            let namespace = params_map.get("namespace").and_then(|v| v.as_str());
-            let output_format = params_map
-                .get("output")
-                .and_then(|v| v.as_str())
-                .unwrap_or("table");
+// Helper to run talosctl command and capture output.
+async fn run_talosctl(args: &[&str]) -> Result<String> {
+    let output = exec_talosctl(args).await?;
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
 
-            match node {
-                Ok(node) => {
-                    let mut args = vec!["--nodes", node, "get", "addresses"];
+// Run talosctl for at most `window`, killing it at the deadline. Returns the output
+// and whether the command finished on its own.
+async fn run_talosctl_bounded(args: &[&str], window: Duration) -> Result<(String, bool)> {
+    with_talosctl_slot(talosctl_bounded(args, window)).await
+}
 
-                    if let Some(ns) = namespace {
-                        args.extend(&["--namespace", ns]);
-                    }
+async fn talosctl_bounded(args: &[&str], window: Duration) -> Result<(String, bool)> {
+    let mut child = talosctl_command()?
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to execute talosctl")?;
+    let mut stdout = child
+        .stdout
+        .take()
+        .ok_or(anyhow!("Failed to capture talosctl output"))?;
+    let mut stderr = child
+        .stderr
+        .take()
+        .ok_or(anyhow!("Failed to capture talosctl errors"))?;
 
-                    args.extend(&["--output", output_format]);
+    // Keep reading while waiting so a full pipe can't stall the child
+    let mut out = Vec::new();
+    let mut err = Vec::new();
+    let finished = tokio::time::timeout(window, async {
+        let (read_out, read_err, status) = tokio::join!(
+            stdout.read_to_end(&mut out),
+            stderr.read_to_end(&mut err),
+            child.wait()
+        );
+        read_out.and(read_err).and(status)
+    })
+    .await;
 
-                    let output = run_talosctl(&args);
-                    Some(output.map(|out| {
-                        json!({
-                            "interfaces": out,
-                            "namespace": namespace,
-                            "output_format": output_format
-                        })
-                    }))
-                }
-                Err(e) => Some(Err(e)),
-            }
+    let status = match finished {
+        Ok(status) => Some(status.context("Failed to wait for talosctl")?),
+        Err(_) => {
+            let _ = child.kill().await;
+            None
         }
-        "routes" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            let namespace = params_map.get("namespace").and_then(|v| v.as_str());
-            let output_format = params_map
-                .get("output")
-                .and_then(|v| v.as_str())
-                .unwrap_or("table");
+    };
+    if let Some(status) = status {
+        if !status.success() {
+            let err = String::from_utf8_lossy(&err).to_string();
+            return Err(TalosctlError::new(args, status.code(), err).into());
+        }
+    }
+    Ok((String::from_utf8_lossy(&out).to_string(), status.is_some()))
+}
 
-            match node {
-                Ok(node) => {
-                    let mut args = vec!["--nodes", node, "get", "routes"];
+// Run talosctl and stream its stdout into a file without buffering it in memory,
+// returning the number of bytes written.
+async fn run_talosctl_to_file(args: &[&str], path: &std::path::Path) -> Result<u64> {
+    with_talosctl_slot(talosctl_to_file(args, path)).await
+}
 
-                    if let Some(ns) = namespace {
-                        args.extend(&["--namespace", ns]);
-                    }
+async fn talosctl_to_file(args: &[&str], path: &std::path::Path) -> Result<u64> {
+    let mut child = talosctl_command()?
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to execute talosctl")?;
+    let mut stdout = child
+        .stdout
+        .take()
+        .ok_or(anyhow!("Failed to capture talosctl output"))?;
+    let mut stderr = child
+        .stderr
+        .take()
+        .ok_or(anyhow!("Failed to capture talosctl errors"))?;
 
-                    args.extend(&["--output", output_format]);
+    let file = tokio::fs::File::create(path)
+        .await
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = tokio::io::BufWriter::new(file);
+    // Drain stderr alongside so a chatty talosctl can't block on a full pipe
+    let mut err = Vec::new();
+    let timeout = talosctl_timeout();
+    let transfer = tokio::time::timeout(timeout, async {
+        tokio::join!(
+            async {
+                let bytes = tokio::io::copy(&mut stdout, &mut writer).await?;
+                writer.flush().await?;
+                Ok::<_, std::io::Error>(bytes)
+            },
+            stderr.read_to_end(&mut err)
+        )
+    })
+    .await;
+    let Ok((copied, _)) = transfer else {
+        let _ = child.kill().await;
+        return Err(TalosctlError::timed_out(args, timeout).into());
+    };
+    let status = child.wait().await.context("Failed to wait for talosctl")?;
 
-                    let output = run_talosctl(&args);
-                    Some(output.map(|out| {
-                        json!({
-                            "routes": out,
-                            "namespace": namespace,
-                            "output_format": output_format
-                        })
-                    }))
-                }
-                Err(e) => Some(Err(e)),
+    if !status.success() {
+        let err = String::from_utf8_lossy(&err).to_string();
+        return Err(TalosctlError::new(args, status.code(), err).into());
+    }
+    copied.with_context(|| format!("Failed to write {}", path.display()))
+}
+
+// Helper to run talosctl command and capture stderr output (for health checks).
+async fn run_talosctl_with_stderr(args: &[&str]) -> Result<String> {
+    let output = exec_talosctl(args).await?;
+    // For health checks, the useful output is in stderr, not stdout
+    Ok(String::from_utf8_lossy(&output.stderr).to_string())
+}
+
+// Run a tool against each target node concurrently, so one unreachable node can't fail
+// the others. A single target returns its result unchanged; several return `results`
+// and `errors` keyed by node, and fail only if every node failed.
+async fn for_each_node<'a, F, Fut>(targets: &'a [String], call: F) -> Result<Value>
+where
+    F: Fn(&'a str) -> Fut,
+    Fut: std::future::Future<Output = Result<Value>>,
+{
+    if let [node] = targets {
+        return call(node).await;
+    }
+    let outcomes = join_all(targets.iter().map(|node| call(node)).collect()).await;
+    let mut results = serde_json::Map::new();
+    let mut errors = serde_json::Map::new();
+    let mut first_error = None;
+    for (node, outcome) in targets.iter().zip(outcomes) {
+        match outcome {
+            Ok(result) => {
+                results.insert(node.clone(), result);
+            }
+            Err(e) => {
+                errors.insert(node.clone(), json!(e.to_string()));
+                first_error.get_or_insert(e);
             }
         }
-        "get_netstat" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            match node {
-                Ok(node) => {
-                    let output = run_talosctl(&["--nodes", node, "netstat"]);
-                    Some(output.map(|out| json!({"netstat": out})))
+    }
+    match first_error {
+        Some(e) if results.is_empty() => Err(e),
+        _ => Ok(json!({"results": results, "errors": errors})),
+    }
+}
+
+// Parse a duration such as "10s", "15m", "1h30m" or a plain number of seconds.
+fn parse_duration(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+
+    let mut total = Duration::ZERO;
+    let mut number = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let amount: u64 = number
+            .parse()
+            .map_err(|_| invalid_params!("Invalid duration: {}", value))?;
+        total += match c {
+            's' => Duration::from_secs(amount),
+            'm' => Duration::from_secs(amount * 60),
+            'h' => Duration::from_secs(amount * 3600),
+            _ => {
+                return Err(invalid_params!(
+                    "Invalid duration unit '{}' in {}",
+                    c,
+                    value
+                ))
+            }
+        };
+        number.clear();
+    }
+    if !number.is_empty() || total.is_zero() {
+        return Err(invalid_params!("Invalid duration: {}", value));
+    }
+    Ok(total)
+}
+
+// Parse a time bound given as RFC3339 or as a duration ago from now (e.g. "15m").
+fn parse_time_bound(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    let ago = parse_duration(value).map_err(|_| {
+        invalid_params!(
+            "Invalid time '{}': expected RFC3339 or a duration like 15m",
+            value
+        )
+    })?;
+    Ok(now - chrono::Duration::from_std(ago)?)
+}
+
+// Extract the timestamp from a Talos service log line, which is either a JSON
+// record with a ts/time field or text prefixed with an RFC3339 or Go log timestamp.
+fn parse_log_timestamp(line: &str) -> Option<DateTime<Utc>> {
+    // Drop the "<node>: " prefix talosctl adds when --nodes is used
+    let line = match line.split_once(": ") {
+        Some((prefix, rest)) if !prefix.contains(' ') => rest,
+        _ => line,
+    };
+
+    if line.starts_with('{') {
+        let record: Value = serde_json::from_str(line).ok()?;
+        let ts = ["ts", "time", "timestamp"]
+            .iter()
+            .find_map(|key| record.get(*key))?;
+        return match ts {
+            Value::String(s) => DateTime::parse_from_rfc3339(s)
+                .ok()
+                .map(|t| t.with_timezone(&Utc)),
+            Value::Number(n) => {
+                let secs = n.as_f64()?;
+                DateTime::from_timestamp(secs.trunc() as i64, (secs.fract() * 1e9) as u32)
+            }
+            _ => None,
+        };
+    }
+
+    let (fields, _) = split_columns(line, 2);
+    let first = fields.first()?;
+    if let Ok(time) = DateTime::parse_from_rfc3339(first) {
+        return Some(time.with_timezone(&Utc));
+    }
+    let joined = fields.join(" ");
+    NaiveDateTime::parse_from_str(&joined, "%Y/%m/%d %H:%M:%S%.f")
+        .ok()
+        .map(|t| t.and_utc())
+}
+
+// Keep log lines inside [since, until]. Lines without a timestamp follow the
+// preceding line, so multi-line records stay together. Tail applies afterwards.
+fn filter_log_window(
+    logs: &str,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    tail: Option<i64>,
+) -> String {
+    let mut keep = false;
+    let mut lines: Vec<&str> = logs
+        .lines()
+        .filter(|line| {
+            if let Some(ts) = parse_log_timestamp(line) {
+                keep = since.is_none_or(|s| ts >= s) && until.is_none_or(|u| ts <= u);
+            }
+            keep
+        })
+        .collect();
+
+    if let Some(tail) = tail.filter(|t| *t >= 0) {
+        let skip = lines.len().saturating_sub(tail as usize);
+        lines.drain(..skip);
+    }
+
+    join_lines(&lines)
+}
+
+// Keep only the last `count` lines of command output.
+fn tail_lines(output: &str, count: usize) -> String {
+    let lines: Vec<&str> = output.lines().collect();
+    join_lines(&lines[lines.len().saturating_sub(count)..])
+}
+
+// Join output lines back together with a trailing newline.
+fn join_lines(lines: &[&str]) -> String {
+    let mut joined = lines.join("\n");
+    if !joined.is_empty() {
+        joined.push('\n');
+    }
+    joined
+}
+
+// Split a whitespace-aligned table row into its first `count` columns and the remainder.
+fn split_columns(line: &str, count: usize) -> (Vec<&str>, &str) {
+    let mut fields = Vec::with_capacity(count);
+    let mut rest = line.trim_start();
+    while fields.len() < count && !rest.is_empty() {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    (fields, rest.trim_end())
+}
+
+// Parse `talosctl services` table output into {node, name, state, health, last_change,
+// last_event} objects. None if the header isn't recognized.
+fn parse_services_table(output: &str) -> Option<Vec<Value>> {
+    let columns = [
+        ("node", "NODE"),
+        ("name", "SERVICE"),
+        ("state", "STATE"),
+        ("health", "HEALTH"),
+        ("last_change", "LAST CHANGE"),
+        ("last_event", "LAST EVENT"),
+    ];
+    let rows = parse_aligned_table(output, &columns, &["name", "state"])?;
+    Some(rows.into_iter().map(Value::Object).collect())
+}
+
+// Parse a talosctl table whose columns are aligned under a header line, mapping each
+// header title to a key. Rows are sliced at the header's offsets, as values (such as
+// LAST CHANGE) may contain spaces. None unless the `required` keys' titles are present.
+fn parse_aligned_table(
+    output: &str,
+    columns: &[(&str, &str)],
+    required: &[&str],
+) -> Option<Vec<serde_json::Map<String, Value>>> {
+    let mut lines = output.lines().filter(|line| !line.trim().is_empty());
+    let header = lines.next()?;
+    let mut offsets: Vec<(&str, usize)> = columns
+        .iter()
+        .filter_map(|(key, title)| header.find(title).map(|offset| (*key, offset)))
+        .collect();
+    if !required
+        .iter()
+        .all(|key| offsets.iter().any(|(k, _)| k == key))
+    {
+        return None;
+    }
+    offsets.sort_by_key(|(_, offset)| *offset);
+
+    let rows = lines
+        .map(|line| {
+            let mut row = serde_json::Map::new();
+            for (i, (key, start)) in offsets.iter().enumerate() {
+                let end = offsets.get(i + 1).map_or(line.len(), |(_, end)| *end);
+                let value = line.get(*start..end.min(line.len())).unwrap_or_default();
+                row.insert(key.to_string(), json!(value.trim()));
+            }
+            row
+        })
+        .collect();
+    Some(rows)
+}
+
+// Page through `talosctl list` output client-side, as talosctl can't: keep the header
+// and the entries in [offset, offset + limit), parsed into objects where the columns
+// are recognized, with the total entry count and whether more follow.
+fn page_list_output(output: &str, offset: u64, limit: Option<u64>) -> Value {
+    let mut lines: Vec<&str> = output.lines().filter(|l| !l.trim().is_empty()).collect();
+    let header = match lines.first() {
+        Some(first) if first.trim_start().starts_with("NODE") => Some(lines.remove(0)),
+        _ => None,
+    };
+    let total = lines.len();
+    let start = (offset as usize).min(total);
+    let end = limit.map_or(total, |limit| {
+        start.saturating_add(limit as usize).min(total)
+    });
+
+    let page: Vec<&str> = header
+        .into_iter()
+        .chain(lines[start..end].iter().copied())
+        .collect();
+    let list = join_lines(&page);
+    let columns = [
+        ("node", "NODE"),
+        ("mode", "MODE"),
+        ("uid", "UID"),
+        ("gid", "GID"),
+        ("size", "SIZE"),
+        ("modified", "LASTMOD"),
+        ("name", "NAME"),
+    ];
+    let entries = parse_aligned_table(&list, &columns, &["name"]);
+    json!({
+        "list": list,
+        "entries": entries,
+        "total": total,
+        "offset": start,
+        "limit": limit,
+        "has_more": end < total
+    })
+}
+
+// Parse `talosctl mounts` table output into {node, filesystem, size, used, available,
+// percent, mountpoint} objects, with sizes in GB and percent as a number. None if the
+// header isn't recognized.
+fn parse_mounts_table(output: &str) -> Option<Vec<Value>> {
+    let columns = [
+        ("node", "NODE"),
+        ("filesystem", "FILESYSTEM"),
+        ("size", "SIZE(GB)"),
+        ("used", "USED(GB)"),
+        ("available", "AVAILABLE(GB)"),
+        ("percent", "PERCENT USED"),
+        ("mountpoint", "MOUNTED ON"),
+    ];
+    let rows = parse_aligned_table(output, &columns, &["filesystem", "mountpoint"])?;
+    let mounts = rows
+        .into_iter()
+        .map(|mut row| {
+            for key in ["size", "used", "available", "percent"] {
+                let number = row
+                    .get(key)
+                    .and_then(|v| v.as_str())
+                    .and_then(|v| v.trim_end_matches('%').parse::<f64>().ok());
+                if let Some(number) = number {
+                    row.insert(key.to_string(), json!(number));
                 }
-                Err(e) => Some(Err(e)),
             }
-        }
-        "capture_packets" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            let interface = params_map
-                .get("interface")
-                .and_then(|v| v.as_str())
-                .unwrap_or("eth0");
-            let duration = params_map
-                .get("duration")
-                .and_then(|v| v.as_str())
-                .unwrap_or("10s");
-            match node {
-                Ok(node) => {
-                    let output = run_talosctl(&[
-                        "--nodes",
-                        node,
-                        "pcap",
-                        "--interface",
-                        interface,
-                        "--duration",
-                        duration,
-                    ]);
-                    Some(output.map(|out| json!({"packets": out})))
+            Value::Object(row)
+        })
+        .collect();
+    Some(mounts)
+}
+
+// Parse `talosctl events` table output into {timestamp, node, id, type, actor, source,
+// message} objects. Event IDs are xids, whose leading bits are the event's creation time
+// in Unix seconds, so that is the timestamp. None if the header isn't recognized.
+fn parse_events_table(output: &str) -> Option<Vec<Value>> {
+    let columns = [
+        ("node", "NODE"),
+        ("id", "ID"),
+        ("type", "EVENT"),
+        ("actor", "ACTOR"),
+        ("source", "SOURCE"),
+        ("message", "MESSAGE"),
+    ];
+    let rows = parse_aligned_table(output, &columns, &["id", "type"])?;
+    let events = rows
+        .into_iter()
+        .map(|mut row| {
+            let timestamp = row["id"].as_str().and_then(xid_timestamp);
+            row.insert(
+                "timestamp".to_string(),
+                json!(timestamp.map(|t| t.to_rfc3339())),
+            );
+            Value::Object(row)
+        })
+        .collect();
+    Some(events)
+}
+
+// Creation time of an xid: its first 32 bits, from the leading 7 base32hex characters.
+fn xid_timestamp(id: &str) -> Option<DateTime<Utc>> {
+    if id.len() != 20 {
+        return None;
+    }
+    let bits = id.chars().take(7).try_fold(0u64, |bits, c| {
+        c.to_digit(32).map(|digit| bits << 5 | u64::from(digit))
+    })?;
+    DateTime::from_timestamp((bits >> 3) as i64, 0)
+}
+
+// Whether an event's type matches a `type` filter: the full type, such as
+// runtime.ServiceStateEvent, or just its name, compared case-insensitively.
+fn event_type_matches(event_type: &str, filter: &str) -> bool {
+    let event_type = event_type.to_lowercase();
+    let filter = filter.to_lowercase();
+    event_type == filter || event_type.rsplit('.').next() == Some(filter.as_str())
+}
+
+// The device name talosctl wipe disk takes, from a name or /dev path of a disk or
+// partition (e.g. /dev/sdb, nvme0n1p2). Anything else is rejected before it can reach
+// a destructive command.
+fn block_device_name(device: &str) -> Result<&str> {
+    static BLOCK_DEVICE: OnceLock<regex::Regex> = OnceLock::new();
+    let pattern = BLOCK_DEVICE.get_or_init(|| {
+        regex::Regex::new(
+            r"^(?:/dev/)?((?:sd|vd|xvd|hd)[a-z]+[0-9]*|nvme[0-9]+n[0-9]+(?:p[0-9]+)?|mmcblk[0-9]+(?:p[0-9]+)?)$",
+        )
+        .expect("block device pattern is valid")
+    });
+    pattern
+        .captures(device)
+        .and_then(|captures| captures.get(1))
+        .map(|name| name.as_str())
+        .ok_or_else(|| {
+            invalid_params!(
+                "Invalid device '{}': expected a block device such as /dev/sdb or nvme0n1",
+                device
+            )
+        })
+}
+
+// Parse `talosctl usage` table output into {path, size_bytes} objects, with the size as
+// a number. None if the header isn't recognized.
+fn parse_usage_table(output: &str) -> Option<Vec<Value>> {
+    let columns = [("node", "NODE"), ("path", "NAME"), ("size_bytes", "SIZE")];
+    let rows = parse_aligned_table(output, &columns, &["path", "size_bytes"])?;
+    let entries = rows
+        .into_iter()
+        .map(|row| {
+            let size = &row["size_bytes"];
+            json!({
+                "path": row["path"],
+                "size_bytes": size.as_str().and_then(|s| s.parse::<u64>().ok()).map_or(size.clone(), |s| json!(s))
+            })
+        })
+        .collect();
+    Some(entries)
+}
+
+// Parse `talosctl processes` table output into {node, pid, state, threads, cpu_time,
+// virt_mem, res_mem, label, command} objects, with pid, threads and cpu_time (seconds)
+// as numbers. None if the header isn't recognized.
+fn parse_processes_table(output: &str) -> Option<Vec<Value>> {
+    let columns = [
+        ("node", "NODE"),
+        ("pid", "PID"),
+        ("state", "STATE"),
+        ("threads", "THREADS"),
+        ("cpu_time", "CPU-TIME"),
+        ("virt_mem", "VIRTMEM"),
+        ("res_mem", "RESMEM"),
+        ("label", "LABEL"),
+        ("command", "COMMAND"),
+    ];
+    let rows = parse_aligned_table(output, &columns, &["pid", "command"])?;
+    let processes = rows
+        .into_iter()
+        .map(|mut row| {
+            for key in ["pid", "threads"] {
+                let number = row
+                    .get(key)
+                    .and_then(|v| v.as_str())
+                    .and_then(|v| v.parse::<u64>().ok());
+                if let Some(number) = number {
+                    row.insert(key.to_string(), json!(number));
                 }
-                Err(e) => Some(Err(e)),
             }
-        }
-        "get_network_io_cgroups" => {
-            let node = params_map
-                .get("node")
+            let cpu_time = row
+                .get("cpu_time")
                 .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            match node {
-                Ok(node) => {
-                    let output = run_talosctl(&["--nodes", node, "cgroups", "--preset", "io"]);
-                    Some(output.map(|out| json!({"network_io": out})))
+                .and_then(|v| v.parse::<f64>().ok());
+            if let Some(cpu_time) = cpu_time {
+                row.insert("cpu_time".to_string(), json!(cpu_time));
+            }
+            Value::Object(row)
+        })
+        .collect();
+    Some(processes)
+}
+
+// Parse `talosctl memory` output into per-node {node, total_bytes, used_bytes,
+// free_bytes, shared_bytes, buffers_bytes, cached_bytes, available_bytes}. Handles
+// both the default table (values in MiB) and --verbose's /proc/meminfo listing
+// (values in kB, one `NODE:` block per node). None if neither format is recognized.
+fn parse_memory_output(output: &str) -> Option<Vec<Value>> {
+    const MIB: u64 = 1024 * 1024;
+    let columns = [
+        ("node", "NODE"),
+        ("total", "TOTAL"),
+        ("used", "USED"),
+        ("free", "FREE"),
+        ("shared", "SHARED"),
+        ("buffers", "BUFFERS"),
+        ("cached", "CACHE"),
+        ("available", "AVAILABLE"),
+    ];
+    if let Some(rows) = parse_aligned_table(output, &columns, &["total", "free"]) {
+        let nodes = rows
+            .into_iter()
+            .map(|row| {
+                let mut stats = json!({"node": row.get("node")});
+                for (key, _) in &columns[1..] {
+                    let mib = row
+                        .get(*key)
+                        .and_then(|v| v.as_str())
+                        .and_then(|v| v.parse::<u64>().ok());
+                    if let Some(mib) = mib {
+                        stats[format!("{}_bytes", key)] = json!(mib * MIB);
+                    }
                 }
-                Err(e) => Some(Err(e)),
+                stats
+            })
+            .collect();
+        return Some(nodes);
+    }
+
+    // /proc/meminfo fields, e.g. `MemTotal:  2016644 kB`
+    let fields = [
+        ("total", "MemTotal"),
+        ("free", "MemFree"),
+        ("shared", "Shmem"),
+        ("buffers", "Buffers"),
+        ("cached", "Cached"),
+        ("available", "MemAvailable"),
+    ];
+    let mut nodes: Vec<(Option<&str>, HashMap<&str, u64>)> = Vec::new();
+    for line in output.lines() {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        if name.trim() == "NODE" {
+            nodes.push((Some(value.trim()), HashMap::new()));
+            continue;
+        }
+        let Some((key, _)) = fields.iter().find(|(_, field)| *field == name.trim()) else {
+            continue;
+        };
+        let mut parts = value.split_whitespace();
+        let Some(amount) = parts.next().and_then(|v| v.parse::<u64>().ok()) else {
+            continue;
+        };
+        let bytes = match parts.next() {
+            Some("kB") => amount * 1024,
+            _ => amount,
+        };
+        if nodes.is_empty() {
+            nodes.push((None, HashMap::new()));
+        }
+        if let Some((_, values)) = nodes.last_mut() {
+            values.insert(key, bytes);
+        }
+    }
+    if !nodes.iter().any(|(_, values)| values.contains_key("total")) {
+        return None;
+    }
+    let nodes = nodes
+        .into_iter()
+        .map(|(node, values)| {
+            let mut stats = json!({"node": node});
+            for (key, bytes) in &values {
+                stats[format!("{}_bytes", key)] = json!(bytes);
+            }
+            // As free(1) counts it: neither free nor reclaimable buffers and cache
+            if let (Some(total), Some(free)) = (values.get("total"), values.get("free")) {
+                let reclaimable =
+                    values.get("buffers").unwrap_or(&0) + values.get("cached").unwrap_or(&0);
+                stats["used_bytes"] = json!(total.saturating_sub(free + reclaimable));
             }
+            stats
+        })
+        .collect();
+    Some(nodes)
+}
+
+// Add the memory stats parsed from a result's raw `talosctl memory` output under
+// `memory_stats`, or a parse_error when the output isn't recognized.
+fn with_memory_stats(result: &mut Value, field: &str) {
+    let Some(out) = result[field].as_str() else {
+        return;
+    };
+    match parse_memory_output(out) {
+        Some(stats) => result["memory_stats"] = json!(stats),
+        None => result["parse_error"] = json!("Unrecognized talosctl memory output"),
+    }
+}
+
+// Walk a classic pcap capture, returning the length of its global header plus the
+// whole packet records that fit in `max` bytes, and how many records that is. None
+// if it isn't a pcap capture.
+fn pcap_records(pcap: &[u8], max: usize) -> Option<(usize, u64)> {
+    const GLOBAL_HEADER: usize = 24;
+    const RECORD_HEADER: usize = 16;
+    let magic: [u8; 4] = pcap.get(..4)?.try_into().ok()?;
+    // Microsecond and nanosecond magics, written in the capturing host's byte order
+    let little_endian = match magic {
+        [0xd4, 0xc3, 0xb2, 0xa1] | [0x4d, 0x3c, 0xb2, 0xa1] => true,
+        [0xa1, 0xb2, 0xc3, 0xd4] | [0xa1, 0xb2, 0x3c, 0x4d] => false,
+        _ => return None,
+    };
+    if pcap.len() < GLOBAL_HEADER {
+        return None;
+    }
+
+    let mut end = GLOBAL_HEADER.min(max);
+    let mut packets = 0;
+    while let Some(header) = pcap.get(end..end + RECORD_HEADER) {
+        let captured: [u8; 4] = header[8..12].try_into().ok()?;
+        let captured = if little_endian {
+            u32::from_le_bytes(captured)
+        } else {
+            u32::from_be_bytes(captured)
+        } as usize;
+        let next = end + RECORD_HEADER + captured;
+        if next > pcap.len() || next > max {
+            break;
         }
-        "list_network_interfaces" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            match node {
-                Ok(node) => {
-                    let output = run_talosctl(&["--nodes", node, "list", "/sys/class/net"]);
-                    Some(output.map(|out| json!({"interfaces": out})))
-                }
-                Err(e) => Some(Err(e)),
+        end = next;
+        packets += 1;
+    }
+    Some((end, packets))
+}
+
+// Cut `talosctl cgroups` tree output down to the header, the first cgroup named `id`
+// and the rows nested under it. None if no cgroup has that name.
+fn scope_cgroup_tree(output: &str, id: &str) -> Option<String> {
+    let tree_chars: &[char] = &['├', '└', '│', '─', ' '];
+    let mut lines = output.lines();
+    let header = lines.next()?;
+    let mut scoped = vec![header];
+    let mut depth = None;
+    for line in lines {
+        let name_start = line.len() - line.trim_start_matches(tree_chars).len();
+        let name = line[name_start..]
+            .split_whitespace()
+            .next()
+            .unwrap_or_default();
+        match depth {
+            None if name == id => {
+                depth = Some(name_start);
+                scoped.push(line);
             }
+            Some(depth) if name_start > depth => scoped.push(line),
+            Some(_) => break,
+            None => {}
         }
-        _ => None,
     }
+    depth.map(|_| scoped.join("\n") + "\n")
 }
 
-// Handle service and logging operations
-fn handle_service_log_methods(
+// Parse `talosctl image list` table output into {node, name, digest, size} objects.
+// None if the header isn't recognized.
+fn parse_image_list(output: &str) -> Option<Vec<Value>> {
+    let columns = [
+        ("node", "NODE"),
+        ("name", "IMAGE"),
+        ("digest", "DIGEST"),
+        ("size", "SIZE"),
+    ];
+    let rows = parse_aligned_table(output, &columns, &["name"])?;
+    Some(rows.into_iter().map(Value::Object).collect())
+}
+
+// Read the optional containerd namespace param of the image tools.
+fn image_namespace(params_map: &HashMap<String, Value>) -> Result<Option<&str>> {
+    match params_map.get("namespace").and_then(|v| v.as_str()) {
+        Some(ns) if !tools::IMAGE_NAMESPACES.contains(&ns) => Err(invalid_params!(
+            "Invalid namespace '{}': expected one of: {}",
+            ns,
+            tools::IMAGE_NAMESPACES.join(", ")
+        )),
+        namespace => Ok(namespace),
+    }
+}
+
+// Split `talosctl version` output into its Client and Server sections, and pick out
+// each node's Talos version from the server section's NODE and Tag lines (or the
+// "Talos <tag>" lines of --short output).
+fn parse_version_output(output: &str) -> Value {
+    let mut client = Vec::new();
+    let mut server = Vec::new();
+    let mut section = None;
+    for line in output.lines() {
+        match line.trim() {
+            "Client:" => section = Some(&mut client),
+            "Server:" => section = Some(&mut server),
+            _ => {
+                if let Some(lines) = section.as_mut() {
+                    lines.push(line.trim());
+                }
+            }
+        }
+    }
+
+    let mut nodes = Vec::new();
+    let mut node = None;
+    for line in &server {
+        if let Some(name) = line.strip_prefix("NODE:") {
+            node = Some(name.trim());
+        } else if let Some(tag) = line
+            .strip_prefix("Tag:")
+            .or_else(|| line.strip_prefix("Talos "))
+        {
+            nodes.push(json!({"node": node, "version": tag.trim()}));
+        }
+    }
+
+    json!({
+        "client": client.join("\n"),
+        "server": server.join("\n"),
+        "server_versions": nodes
+    })
+}
+
+// Summarize a discovered cluster member resource as {id, hostname, addresses,
+// machine_type, os}.
+fn parse_member(member: &Value) -> Value {
+    let spec = &member["spec"];
+    json!({
+        "id": member["metadata"]["id"],
+        "hostname": spec["hostname"],
+        "addresses": spec.get("addresses").cloned().unwrap_or_else(|| json!([])),
+        "machine_type": spec["machineType"],
+        "os": spec["operatingSystem"]
+    })
+}
+
+// The image tag, i.e. the Kubernetes version, of a Talos resource whose spec names a
+// component image, such as kubeletspec or apiserverconfig.
+async fn resource_image_tag(
+    node: &str,
+    resource: &str,
+    runner: &dyn CommandRunner,
+) -> Result<String> {
+    let specs = runner
+        .run(&["--nodes", node, "get", resource, "--output", "json"])
+        .await
+        .and_then(|out| resources::parse_resource_stream(&out))?;
+    specs
+        .first()
+        .and_then(|spec| spec["spec"]["image"].as_str())
+        .and_then(|image| image.rsplit_once(':'))
+        .map(|(_, tag)| tag.to_string())
+        .ok_or_else(|| anyhow!("no image in {}", resource))
+}
+
+// A node's Talos version (from `version --short`) and Kubernetes version (the tag
+// of its kubelet image), each with the error that prevented reading it, if any.
+async fn node_versions(node: &str, role: &str, runner: &dyn CommandRunner) -> Value {
+    let talos = runner
+        .run(&["--nodes", node, "version", "--short"])
+        .await
+        .and_then(|out| {
+            parse_version_output(&out)["server_versions"][0]["version"]
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("no server version in talosctl output"))
+        });
+    let kubernetes = resource_image_tag(node, "kubeletspec", runner).await;
+
+    let mut entry = json!({"node": node, "role": role});
+    for (key, version) in [("talos", talos), ("kubernetes", kubernetes)] {
+        match version {
+            Ok(version) => entry[format!("{}_version", key)] = json!(version),
+            Err(e) => entry[format!("{}_error", key)] = json!(e.to_string()),
+        }
+    }
+    entry
+}
+
+// Numeric components of a version like v1.7.6 or v1.8.0-beta.0, for ordering.
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split(['.', '-'])
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+// Group nodes by their version of one component (`talos` or `kubernetes`) and list
+// the nodes behind the newest version seen, e.g. ones missed by a partial upgrade.
+fn version_skew(entries: &[Value], component: &str) -> Value {
+    let field = format!("{}_version", component);
+    let mut versions: Vec<(&str, Vec<&str>)> = Vec::new();
+    for entry in entries {
+        let (Some(node), Some(version)) = (entry["node"].as_str(), entry[&field].as_str()) else {
+            continue;
+        };
+        match versions.iter_mut().find(|(v, _)| *v == version) {
+            Some((_, nodes)) => nodes.push(node),
+            None => versions.push((version, vec![node])),
+        }
+    }
+
+    let latest = versions
+        .iter()
+        .map(|(version, _)| *version)
+        .max_by_key(|version| version_key(version));
+    let behind: Vec<&str> = versions
+        .iter()
+        .filter(|(version, _)| Some(*version) != latest)
+        .flat_map(|(_, nodes)| nodes.iter().copied())
+        .collect();
+    json!({
+        "versions": versions
+            .into_iter()
+            .map(|(version, nodes)| (version.to_string(), json!(nodes)))
+            .collect::<serde_json::Map<_, _>>(),
+        "latest": latest,
+        "behind": behind,
+        "uniform": behind.is_empty()
+    })
+}
+
+// Parse /proc/modules lines ("name size instances used_by, state address") into
+// {name, size, instances, used_by, state} objects. None if any line doesn't fit.
+fn parse_proc_modules(output: &str) -> Option<Vec<Value>> {
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [name, size, instances, used_by, state, ..] = fields[..] else {
+                return None;
+            };
+            let used_by: Vec<&str> = used_by
+                .split(',')
+                .filter(|m| !m.is_empty() && *m != "-")
+                .collect();
+            Some(json!({
+                "name": name,
+                "size": size.parse::<u64>().ok()?,
+                "instances": instances.parse::<u64>().ok()?,
+                "used_by": used_by,
+                "state": state
+            }))
+        })
+        .collect()
+}
+
+// Parse `talosctl config contexts` into {name, current, endpoints, nodes} objects.
+fn parse_config_contexts(output: &str) -> Option<Vec<Value>> {
+    let columns = [
+        ("current", "CURRENT"),
+        ("name", "NAME"),
+        ("endpoints", "ENDPOINTS"),
+        ("nodes", "NODES"),
+    ];
+    let rows = parse_aligned_table(output, &columns, &["current", "name"])?;
+    let list = |value: Option<&Value>| -> Vec<String> {
+        value
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    Some(
+        rows.iter()
+            .map(|row| {
+                json!({
+                    "name": row["name"],
+                    "current": row["current"] == "*",
+                    "endpoints": list(row.get("endpoints")),
+                    "nodes": list(row.get("nodes"))
+                })
+            })
+            .collect(),
+    )
+}
+
+// Parse `talosctl config info`'s "Key: value" lines into an object with snake_case
+// keys, splitting the node and endpoint lists.
+fn parse_config_info(output: &str) -> serde_json::Map<String, Value> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| {
+            let key = key.trim().to_lowercase().replace(' ', "_");
+            let value = value.trim();
+            let value = match key.as_str() {
+                "nodes" | "endpoints" => json!(value
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|item| !item.is_empty())
+                    .collect::<Vec<_>>()),
+                _ => json!(value),
+            };
+            (key, value)
+        })
+        .collect()
+}
+
+// Capabilities advertised by the server with full MCP tool schemas.
+fn get_capabilities() -> Value {
+    let mut capabilities = tools::get_all_tool_schemas();
+
+    // Tools are advertised under their canonical names. Every tool can be routed through
+    // specific API endpoints, and every tool that runs talosctl to completion accepts a
+    // per-call command timeout
+    if let Some(tools) = capabilities["tools"].as_array_mut() {
+        for tool in tools {
+            let name = tool["name"].as_str().unwrap_or_default().to_string();
+            tool["name"] = json!(tools::canonical_tool_name(&name));
+            let Some(properties) = tool["inputSchema"]["properties"].as_object_mut() else {
+                continue;
+            };
+            if !tools::TOOLS_WITH_OWN_ENDPOINTS.contains(&name.as_str()) {
+                properties.insert("endpoint".to_string(), tools::endpoint_property());
+                properties.insert("endpoints".to_string(), tools::endpoints_property());
+            }
+            properties.insert("context".to_string(), tools::context_property());
+            if !streaming::is_streaming_tool(&name) {
+                properties.insert("explain".to_string(), tools::explain_property());
+            }
+            if tools::TOOLS_WITH_OWN_TIMEOUT.contains(&name.as_str())
+                || streaming::is_streaming_tool(&name)
+            {
+                continue;
+            }
+            properties.insert("timeout".to_string(), tools::command_timeout_property());
+        }
+    }
+
+    // With a default node configured, clients may omit node, so don't advertise it as required
+    if nodes::default_node().is_some() {
+        if let Some(tools) = capabilities["tools"].as_array_mut() {
+            for tool in tools {
+                if let Some(required) = tool["inputSchema"]["required"].as_array_mut() {
+                    required.retain(|field| field != "node");
+                }
+            }
+        }
+    }
+
+    capabilities
+}
+
+// Extract parameters from JSON value into HashMap, falling back to the default node
+fn extract_params(params: Option<&Value>) -> HashMap<String, Value> {
+    let mut params_map: HashMap<String, Value> = params
+        .and_then(|p| {
+            p.as_object()
+                .map(|o| o.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+        })
+        .unwrap_or_default();
+
+    // Explicitly-passed nodes always win over the configured default
+    if !params_map.contains_key("node") {
+        if let Some(node) = nodes::default_node() {
+            params_map.insert("node".to_string(), json!(node));
+        }
+    }
+
+    params_map
+}
+
+// Handle system inspection and monitoring methods
+async fn handle_system_inspection_methods(
     method: &str,
     params_map: &HashMap<String, Value>,
+    runner: &dyn CommandRunner,
 ) -> Option<Result<Value>> {
     match method {
-        "dmesg" => {
+        "containers" => {
+            let targets = nodes::node_targets(params_map);
+            let kubernetes = params_map
+                .get("kubernetes")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            match targets {
+                Ok(targets) => Some(
+                    for_each_node(&targets, |node| async move {
+                        let mut args = vec!["--nodes", node, "containers"];
+                        if kubernetes {
+                            args.push("--kubernetes");
+                        }
+                        let out = runner.run(&args).await?;
+                        Ok(json!({"containers": out, "namespace": if kubernetes { "k8s.io" } else { "system" }}))
+                    })
+                    .await,
+                ),
+                Err(e) => Some(Err(e)),
+            }
+        }
+        "stats" => {
+            let targets = nodes::node_targets(params_map);
+            let kubernetes = params_map
+                .get("kubernetes")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            match targets {
+                Ok(targets) => Some(
+                    for_each_node(&targets, |node| async move {
+                        let mut args = vec!["--nodes", node, "stats"];
+                        if kubernetes {
+                            args.push("--kubernetes");
+                        }
+                        let out = runner.run(&args).await?;
+                        Ok(json!({"stats": out, "namespace": if kubernetes { "k8s.io" } else { "system" }}))
+                    })
+                    .await,
+                ),
+                Err(e) => Some(Err(e)),
+            }
+        }
+        "memory_verbose" => {
             let node = params_map
                 .get("node")
                 .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
+                .ok_or(invalid_params!("Missing node param"));
             match node {
                 Ok(node) => {
-                    let args = vec!["--nodes", node, "dmesg"];
-                    let output = run_talosctl(&args);
+                    let output = runner.run(&["--nodes", node, "memory", "--verbose"]).await;
                     Some(output.map(|out| {
-                        json!({
-                            "dmesg": out
-                        })
+                        let mut result = json!({"memory_verbose": out});
+                        with_memory_stats(&mut result, "memory_verbose");
+                        result
                     }))
                 }
                 Err(e) => Some(Err(e)),
             }
         }
-        "service" => {
+        "get_cgroups" => {
             let node = params_map
                 .get("node")
                 .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            let service = params_map
-                .get("service")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing service param"));
-            let action = params_map
-                .get("action")
+                .ok_or(invalid_params!("Missing node param"));
+            let preset = params_map
+                .get("preset")
                 .and_then(|v| v.as_str())
-                .unwrap_or("status");
-            match (node, service) {
-                (Ok(node), Ok(service)) => {
-                    let output = run_talosctl(&["--nodes", node, "service", service, action]);
-                    Some(output.map(|out| json!({"service": out})))
+                .unwrap_or("cpu");
+            if !tools::CGROUP_PRESETS.contains(&preset) {
+                return Some(Err(invalid_params!(
+                    "Invalid preset '{}': expected one of: {}",
+                    preset,
+                    tools::CGROUP_PRESETS.join(", ")
+                )));
+            }
+            let id = params_map.get("id").and_then(|v| v.as_str());
+            match node {
+                Ok(node) => {
+                    let output = runner
+                        .run(&["--nodes", node, "cgroups", "--preset", preset])
+                        .await;
+                    Some(output.and_then(|out| {
+                        // talosctl has no per-cgroup filter, so the tree is cut down here
+                        let cgroups = match id {
+                            Some(id) if !explaining() => {
+                                scope_cgroup_tree(&out, id).ok_or_else(|| {
+                                    invalid_params!("No cgroup named '{}' on {}", id, node)
+                                })?
+                            }
+                            _ => out,
+                        };
+                        Ok(json!({
+                            "node": node,
+                            "preset": preset,
+                            "id": id,
+                            "cgroups": cgroups
+                        }))
+                    }))
                 }
-                (Err(e), _) | (_, Err(e)) => Some(Err(e)),
+                Err(e) => Some(Err(e)),
             }
         }
-        "restart" => {
+        "get_cpu_memory_usage" => {
             let node = params_map
                 .get("node")
                 .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            let service = params_map
-                .get("service")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing service param"));
-            match (node, service) {
-                (Ok(node), Ok(service)) => {
-                    let output = run_talosctl(&["--nodes", node, "service", service, "restart"]);
-                    Some(output.map(|out| json!({"restart": out})))
+                .ok_or(invalid_params!("Missing node param"));
+            match node {
+                Ok(node) => {
+                    let (mem, cgroups) = tokio::join!(
+                        async { runner.run(&["--nodes", node, "memory"]).await },
+                        async {
+                            runner
+                                .run(&["--nodes", node, "cgroups", "--preset", "cpu"])
+                                .await
+                        },
+                    );
+                    Some(
+                        combine_sections(vec![("memory", mem), ("cpu", cgroups)]).map(
+                            |mut result| {
+                                with_memory_stats(&mut result, "memory");
+                                result
+                            },
+                        ),
+                    )
                 }
-                (Err(e), _) | (_, Err(e)) => Some(Err(e)),
+                Err(e) => Some(Err(e)),
             }
         }
-        "get_events" => {
+        "dashboard" => {
             let node = params_map
                 .get("node")
                 .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
+                .ok_or(invalid_params!("Missing node param"));
             match node {
                 Ok(node) => {
-                    let output = run_talosctl(&["--nodes", node, "events"]);
-                    Some(output.map(|out| json!({"events": out})))
+                    let (mem, cgroups, processes, netstat) = tokio::join!(
+                        async { runner.run(&["--nodes", node, "memory"]).await },
+                        async {
+                            runner
+                                .run(&["--nodes", node, "cgroups", "--preset", "cpu"])
+                                .await
+                        },
+                        async {
+                            runner
+                                .run(&["--nodes", node, "processes", "--sort", "cpu"])
+                                .await
+                        },
+                        async { runner.run(&["--nodes", node, "netstat"]).await },
+                    );
+                    let result = combine_sections(vec![
+                        ("memory", mem),
+                        ("cpu", cgroups),
+                        ("processes", processes),
+                        ("netstat", netstat),
+                    ]);
+                    Some(result.map(|mut result| {
+                        result["node"] = json!(node);
+                        result
+                    }))
                 }
                 Err(e) => Some(Err(e)),
             }
         }
+        "get_processes" => {
+            let targets = nodes::node_targets(params_map);
+            let sort = params_map
+                .get("sort")
+                .and_then(|v| v.as_str())
+                .unwrap_or("rss");
+            match targets {
+                Ok(targets) => Some(
+                    for_each_node(&targets, |node| async move {
+                        let out = runner
+                            .run(&["--nodes", node, "processes", "--sort", sort])
+                            .await?;
+                        // Rows keep talosctl's order, so they stay sorted by `sort`
+                        Ok(match parse_processes_table(&out) {
+                            Some(processes) => json!({
+                                "processes": processes,
+                                "count": processes.len(),
+                                "sort_by": sort
+                            }),
+                            None => json!({
+                                "raw": out,
+                                "sort_by": sort,
+                                "parse_error": "Unrecognized talosctl processes output"
+                            }),
+                        })
+                    })
+                    .await,
+                ),
+                Err(e) => Some(Err(e)),
+            }
+        }
         _ => None,
     }
-}
+}
+
+// Handle file system operations
+async fn handle_file_operations_methods(
+    method: &str,
+    params_map: &HashMap<String, Value>,
+    runner: &dyn CommandRunner,
+) -> Option<Result<Value>> {
+    match method {
+        "list" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            let path = params_map
+                .get("path")
+                .and_then(|v| v.as_str())
+                .unwrap_or("/");
+            let long = params_map
+                .get("long")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let humanize = params_map
+                .get("humanize")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let recurse = params_map
+                .get("recurse")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let depth = params_map
+                .get("depth")
+                .and_then(|v| v.as_i64())
+                .unwrap_or(1);
+            let file_types = params_map
+                .get("type")
+                .and_then(|v| v.as_array())
+                .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>());
+            let offset = params_map.get("offset").and_then(|v| v.as_u64());
+            let limit = params_map.get("limit").and_then(|v| v.as_u64());
+            if limit == Some(0) {
+                return Some(Err(invalid_params!("limit must be at least 1")));
+            }
+
+            match node {
+                Ok(node) => {
+                    let mut args = vec!["--nodes", node, "list", path];
+                    let depth_str = depth.to_string();
+
+                    if long {
+                        args.push("--long");
+                    }
+
+                    if humanize {
+                        args.push("--humanize");
+                    }
+
+                    // --recurse and --depth are mutually exclusive
+                    if recurse {
+                        args.push("--recurse");
+                    } else if depth != 1 {
+                        args.extend(&["--depth", &depth_str]);
+                    }
+
+                    if let Some(types) = &file_types {
+                        for file_type in types {
+                            args.extend(&["--type", file_type]);
+                        }
+                    }
+
+                    let output = runner.run(&args).await;
+                    Some(output.map(|out| {
+                        let mut result = json!({
+                            "list": out,
+                            "path": path,
+                            "long": long,
+                            "humanize": humanize,
+                            "recurse": recurse,
+                            "depth": depth,
+                            "types": file_types
+                        });
+                        if offset.is_some() || limit.is_some() {
+                            let page = page_list_output(&out, offset.unwrap_or(0), limit);
+                            if let (Some(result), Value::Object(page)) =
+                                (result.as_object_mut(), page)
+                            {
+                                result.extend(page);
+                            }
+                        }
+                        result
+                    }))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        }
+        "read" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            let path = params_map
+                .get("path")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing path param"));
+            let limit = output::max_output_bytes(params_map);
+            let keep = output::keep_param(params_map);
+            let head = params_map.get("head_lines").and_then(|v| v.as_u64());
+            let tail = params_map.get("tail_lines").and_then(|v| v.as_u64());
+            if head.is_some() && tail.is_some() {
+                return Some(Err(invalid_params!(
+                    "head_lines and tail_lines cannot be combined"
+                )));
+            }
+            match (node, path, limit, keep) {
+                (Ok(node), Ok(path), Ok(limit), Ok(keep)) => {
+                    let output = runner.run(&["--nodes", node, "read", path]).await;
+                    Some(output.map(|out| {
+                        // talosctl reads whole files, so lines are sliced here, before
+                        // the byte limit applies
+                        let total_lines = out.lines().count();
+                        let content = match (head, tail) {
+                            (Some(n), _) => {
+                                join_lines(&out.lines().take(n as usize).collect::<Vec<_>>())
+                            }
+                            (_, Some(n)) => tail_lines(&out, n as usize),
+                            _ => out,
+                        };
+                        let mut result = json!({"content": content});
+                        if head.is_some() || tail.is_some() {
+                            result["total_lines"] = json!(total_lines);
+                        }
+                        output::limit_field(&mut result, "content", limit, keep);
+                        result
+                    }))
+                }
+                (Err(e), _, _, _) | (_, Err(e), _, _) | (_, _, Err(e), _) | (_, _, _, Err(e)) => {
+                    Some(Err(e))
+                }
+            }
+        }
+        "copy" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            let source = params_map
+                .get("source")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing source param"));
+            let destination = params_map
+                .get("destination")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing destination param"));
+            let direction =
+                params_map
+                    .get("direction")
+                    .and_then(|v| v.as_str())
+                    .ok_or(invalid_params!(
+                        "Missing direction param (from_node or to_node)"
+                    ));
+            match (node, source, destination, direction) {
+                (Ok(node), Ok(source), Ok(destination), Ok("from_node")) => {
+                    // Node paths are always absolute; a relative source is a local path
+                    // passed on the wrong side
+                    if !source.starts_with('/') {
+                        return Some(Err(invalid_params!(
+                            "source '{}' must be an absolute path on the node for from_node copies",
+                            source
+                        )));
+                    }
+                    let dest = match workdir::artifact_path(destination) {
+                        Ok(dest) => dest,
+                        Err(e) => return Some(Err(e)),
+                    };
+                    let output = workdir::produce(&dest, |temp| async move {
+                        runner.run_to_file(&["--nodes", node, "copy", source, "-"], &temp).await
+                    })
+                    .await;
+                    Some(output.map(|bytes| {
+                        json!({
+                            "direction": "from_node",
+                            "source": source,
+                            "destination": dest.to_string_lossy(),
+                            "format": "tar",
+                            "bytes": bytes
+                        })
+                    }))
+                }
+                (Ok(_), Ok(_), Ok(_), Ok("to_node")) => Some(Err(invalid_params!(
+                    "Copying files onto a Talos node is not supported: the Talos API only reads files from nodes; deliver files through the machine config (apply_config) instead"
+                ))),
+                (Ok(_), Ok(_), Ok(_), Ok(other)) => Some(Err(invalid_params!(
+                    "Invalid direction '{}': expected from_node or to_node",
+                    other
+                ))),
+                (Err(e), _, _, _) | (_, Err(e), _, _) | (_, _, Err(e), _) | (_, _, _, Err(e)) => {
+                    Some(Err(e))
+                }
+            }
+        }
+        "get_usage" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            let path = params_map
+                .get("path")
+                .and_then(|v| v.as_str())
+                .unwrap_or("/");
+            let paths = match params_map.get("paths") {
+                None => None,
+                Some(Value::Array(paths))
+                    if !paths.is_empty() && paths.iter().all(Value::is_string) =>
+                {
+                    Some(paths.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>())
+                }
+                Some(_) => {
+                    return Some(Err(invalid_params!(
+                        "Invalid paths: expected a non-empty array of paths"
+                    )))
+                }
+            };
+            if paths.is_some() && params_map.contains_key("path") {
+                return Some(Err(invalid_params!("Pass either path or paths, not both")));
+            }
+            let node = match node {
+                Ok(node) => node,
+                Err(e) => return Some(Err(e)),
+            };
+            let Some(paths) = paths else {
+                let output = runner.run(&["--nodes", node, "usage", path]).await;
+                return Some(output.map(|out| {
+                    let mut result = json!({"usage": out, "path": path});
+                    match parse_usage_table(&out) {
+                        Some(entries) => result["entries"] = json!(entries),
+                        None => result["parse_error"] = json!("Unrecognized talosctl usage output"),
+                    }
+                    result
+                }));
+            };
+
+            // Every path is measured at once, then their entries are ranked together
+            let calls = paths
+                .iter()
+                .map(|path| async move { runner.run(&["--nodes", node, "usage", path]).await })
+                .collect();
+            let outputs = join_all(calls).await;
+            let mut usage = serde_json::Map::new();
+            let mut errors = serde_json::Map::new();
+            let mut largest = Vec::new();
+            let mut first_error = None;
+            for (path, output) in paths.iter().zip(outputs) {
+                match output {
+                    Ok(out) => match parse_usage_table(&out) {
+                        Some(entries) => {
+                            largest.extend(entries.iter().cloned());
+                            usage.insert(path.to_string(), json!({"entries": entries}));
+                        }
+                        None => {
+                            usage.insert(
+                                path.to_string(),
+                                json!({
+                                    "raw": out,
+                                    "parse_error": "Unrecognized talosctl usage output"
+                                }),
+                            );
+                        }
+                    },
+                    Err(e) => {
+                        errors.insert(path.to_string(), json!(e.to_string()));
+                        first_error.get_or_insert(e);
+                    }
+                }
+            }
+            if let Some(e) = first_error.filter(|_| usage.is_empty()) {
+                return Some(Err(e));
+            }
+            largest.sort_by_key(|entry| std::cmp::Reverse(entry["size_bytes"].as_u64()));
+            largest.truncate(USAGE_SUMMARY_LIMIT);
+            Some(Ok(json!({
+                "node": node,
+                "paths": usage,
+                "largest": largest,
+                "errors": errors
+            })))
+        }
+        "get_mounts" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            match node {
+                Ok(node) => {
+                    let output = runner.run(&["--nodes", node, "mounts"]).await;
+                    Some(output.map(|out| match parse_mounts_table(&out) {
+                        Some(mounts) => json!({"node": node, "unit": "GB", "mounts": mounts}),
+                        None => json!({
+                            "node": node,
+                            "raw": out,
+                            "parse_error": "Unrecognized talosctl mounts output"
+                        }),
+                    }))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        }
+        "image_list" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            match (node, image_namespace(params_map)) {
+                (Ok(node), Ok(namespace)) => {
+                    let mut args = vec!["--nodes", node, "image", "list"];
+                    if let Some(ns) = namespace {
+                        args.extend(&["--namespace", ns]);
+                    }
+                    let output = runner.run(&args).await;
+                    Some(output.map(|out| match parse_image_list(&out) {
+                        Some(images) => json!({
+                            "node": node,
+                            "namespace": namespace.unwrap_or("cri"),
+                            "count": images.len(),
+                            "images": images
+                        }),
+                        None => json!({
+                            "node": node,
+                            "raw": out,
+                            "parse_error": "Unrecognized talosctl image list output"
+                        }),
+                    }))
+                }
+                (Err(e), _) | (_, Err(e)) => Some(Err(e)),
+            }
+        }
+        "image_pull" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            let image = match params_map.get("image").and_then(|v| v.as_str()) {
+                Some(image) if !image.is_empty() && !image.starts_with('-') => Ok(image),
+                Some(image) => Err(invalid_params!("Invalid image reference '{}'", image)),
+                None => Err(invalid_params!("Missing image param")),
+            };
+            match (node, image, image_namespace(params_map)) {
+                (Ok(node), Ok(image), Ok(namespace)) => {
+                    let mut args = vec!["--nodes", node, "image", "pull", image];
+                    if let Some(ns) = namespace {
+                        args.extend(&["--namespace", ns]);
+                    }
+                    let output = runner.run(&args).await;
+                    Some(output.map(|out| {
+                        json!({
+                            "node": node,
+                            "image": image,
+                            "namespace": namespace.unwrap_or("cri"),
+                            "output": out
+                        })
+                    }))
+                }
+                (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => Some(Err(e)),
+            }
+        }
+        _ => None,
+    }
+}
+
+// Handle network operations
+async fn handle_network_operations_methods(
+    method: &str,
+    params_map: &HashMap<String, Value>,
+    runner: &dyn CommandRunner,
+) -> Option<Result<Value>> {
+    match method {
+        "interfaces" => {
+            Some(resources::get_typed_resource(params_map, "addresses", "interfaces", runner).await)
+        }
+        "routes" => {
+            Some(resources::get_typed_resource(params_map, "routes", "routes", runner).await)
+        }
+        "get_netstat" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            match node {
+                Ok(node) => {
+                    let output = runner.run(&["--nodes", node, "netstat"]).await;
+                    Some(output.map(|out| json!({"netstat": out})))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        }
+        "capture_packets" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            let interface = params_map
+                .get("interface")
+                .and_then(|v| v.as_str())
+                .unwrap_or("eth0");
+            let duration = params_map
+                .get("duration")
+                .and_then(|v| v.as_str())
+                .unwrap_or("10s");
+            let filter = params_map.get("filter").and_then(|v| v.as_str());
+            if filter.is_some_and(|f| f.trim().is_empty()) {
+                return Some(Err(invalid_params!(
+                    "Invalid filter: expected a BPF expression such as 'tcp port 6443'"
+                )));
+            }
+            // talosctl stops capturing after the duration, so it bounds the command too
+            let command_timeout = match parse_duration(duration) {
+                Ok(capture) if !capture.is_zero() => {
+                    (capture + WAIT_TIMEOUT_MARGIN).max(talosctl_timeout())
+                }
+                Ok(_) => {
+                    return Some(Err(invalid_params!(
+                        "Invalid duration '{}': a capture must last longer than zero",
+                        duration
+                    )))
+                }
+                Err(e) => return Some(Err(e)),
+            };
+            let output_file = params_map.get("output_file").and_then(|v| v.as_str());
+            // Inline captures are base64 in the response, so they get a small cap of their own
+            let limit = match params_map.get("max_bytes") {
+                Some(_) => output::max_output_bytes(params_map),
+                None => Ok(Some(PCAP_INLINE_MAX_BYTES)),
+            };
+            let (node, limit) = match (node, limit) {
+                (Ok(node), Ok(limit)) => (node, limit),
+                (Err(e), _) | (_, Err(e)) => return Some(Err(e)),
+            };
+
+            // talosctl writes raw pcap with --output; "-" sends it to stdout
+            let mut args = vec!["--nodes", node, "pcap", "--interface", interface];
+            args.extend(&["--duration", duration]);
+            if let Some(filter) = filter {
+                args.extend(&["--bpf-filter", filter]);
+            }
+            args.extend(&["--output", "-"]);
+            let mut result = json!({
+                "node": node,
+                "interface": interface,
+                "duration": duration,
+                "filter": filter,
+                "format": "pcap"
+            });
+
+            match output_file {
+                Some(output_file) => {
+                    let dest = match workdir::artifact_path(output_file) {
+                        Ok(dest) => dest,
+                        Err(e) => return Some(Err(e)),
+                    };
+                    let captured = workdir::produce(&dest, |temp| async move {
+                        let capture = runner.run_to_file(&args, &temp);
+                        CALL_TIMEOUT.scope(command_timeout, capture).await
+                    })
+                    .await;
+                    Some(captured.map(|bytes| {
+                        let packets = std::fs::read(&dest)
+                            .ok()
+                            .and_then(|pcap| pcap_records(&pcap, usize::MAX))
+                            .map(|(_, packets)| packets);
+                        result["path"] = json!(dest.to_string_lossy());
+                        result["bytes"] = json!(bytes);
+                        result["packet_count"] = json!(packets);
+                        result
+                    }))
+                }
+                None => {
+                    let staged = match workdir::TempInput::write("pcap", "") {
+                        Ok(staged) => staged,
+                        Err(e) => return Some(Err(e)),
+                    };
+                    let captured = CALL_TIMEOUT
+                        .scope(command_timeout, runner.run_to_file(&args, staged.path()))
+                        .await;
+                    Some(captured.and_then(|_| {
+                        let pcap = if explaining() {
+                            Vec::new()
+                        } else {
+                            std::fs::read(staged.path()).with_context(|| {
+                                format!("Failed to read {}", staged.path().display())
+                            })?
+                        };
+                        // Cut at a packet boundary so the truncated capture still parses
+                        let max = limit.unwrap_or(usize::MAX);
+                        let (end, packets) = match pcap_records(&pcap, max) {
+                            Some(records) => (records.0, Some(records.1)),
+                            None => (pcap.len().min(max), None),
+                        };
+                        result["pcap_base64"] = json!(BASE64_STANDARD.encode(&pcap[..end]));
+                        result["encoding"] = json!("base64");
+                        result["bytes"] = json!(end);
+                        result["packet_count"] = json!(packets);
+                        if end < pcap.len() {
+                            result["truncated"] = json!(true);
+                            result["total_bytes"] = json!(pcap.len());
+                        }
+                        Ok(result)
+                    }))
+                }
+            }
+        }
+        "get_network_io_cgroups" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            match node {
+                Ok(node) => {
+                    let output = runner
+                        .run(&["--nodes", node, "cgroups", "--preset", "io"])
+                        .await;
+                    Some(output.map(|out| json!({"network_io": out})))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        }
+        "ping_node" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            let target = params_map
+                .get("target")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing target param"));
+            match (node, target) {
+                (Ok(node), Ok(target)) => {
+                    // Talos has no shell or ICMP tooling, so reachability is checked by
+                    // proxying a version request through the source node's apid to the
+                    // target, which exercises the node-to-node path on the Talos API port.
+                    // The elapsed time covers the whole talosctl run (queueing for a
+                    // slot, process start and TLS setup included), not just the network
+                    // round trip.
+                    let started = Instant::now();
+                    let output = runner
+                        .run(&["--endpoints", node, "--nodes", target, "version"])
+                        .await;
+                    let elapsed_ms = started.elapsed().as_millis() as u64;
+                    Some(Ok(match output {
+                        Ok(_) => json!({
+                            "source": node,
+                            "target": target,
+                            "reachable": true,
+                            "elapsed_ms": elapsed_ms,
+                            "method": "apid-proxy"
+                        }),
+                        Err(e) => json!({
+                            "source": node,
+                            "target": target,
+                            "reachable": false,
+                            "elapsed_ms": null,
+                            "method": "apid-proxy",
+                            "error": e.to_string()
+                        }),
+                    }))
+                }
+                (Err(e), _) | (_, Err(e)) => Some(Err(e)),
+            }
+        }
+        "list_network_interfaces" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            match node {
+                Ok(node) => {
+                    let output = runner
+                        .run(&["--nodes", node, "list", "/sys/class/net"])
+                        .await;
+                    Some(output.map(|out| json!({"interfaces": out})))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        }
+        _ => None,
+    }
+}
+
+// Handle service and logging operations
+async fn handle_service_log_methods(
+    method: &str,
+    params_map: &HashMap<String, Value>,
+    runner: &dyn CommandRunner,
+) -> Option<Result<Value>> {
+    match method {
+        "dmesg" => {
+            let targets = nodes::node_targets(params_map);
+            let tail = params_map.get("tail").and_then(|v| v.as_u64());
+            let limit = output::max_output_bytes(params_map);
+            match (targets, limit) {
+                (Ok(targets), Ok(limit)) => Some(
+                    for_each_node(&targets, |node| async move {
+                        let out = runner.run(&["--nodes", node, "dmesg"]).await?;
+                        let dmesg = match tail {
+                            Some(n) => tail_lines(&out, n as usize),
+                            None => out,
+                        };
+                        let mut result = json!({
+                            "dmesg": dmesg,
+                            "tail_lines": tail
+                        });
+                        // Oversized output keeps its most recent messages
+                        output::limit_field(&mut result, "dmesg", limit, output::Keep::Tail);
+                        Ok(result)
+                    })
+                    .await,
+                ),
+                (Err(e), _) | (_, Err(e)) => Some(Err(e)),
+            }
+        }
+        "service" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            let service = params_map
+                .get("service")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing service param"));
+            let action = params_map
+                .get("action")
+                .and_then(|v| v.as_str())
+                .unwrap_or("status");
+            let wait = params_map
+                .get("wait")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let timeout = params_map
+                .get("timeout")
+                .and_then(|v| v.as_str())
+                .unwrap_or("60s");
+            match (node, service, parse_duration(timeout)) {
+                (Ok(node), Ok(service), Ok(timeout_duration)) => {
+                    let output = runner
+                        .run(&["--nodes", node, "service", service, action])
+                        .await;
+                    if !(wait && matches!(action, "start" | "restart")) {
+                        return Some(output.map(|out| json!({"service": out})));
+                    }
+                    let out = match output {
+                        Ok(out) => out,
+                        Err(e) => return Some(Err(e)),
+                    };
+                    let waited =
+                        wait_for_service_healthy(node, service, timeout_duration, runner).await;
+                    Some(
+                        waited.map(
+                            |waited| json!({"service": out, "wait": waited, "timeout": timeout}),
+                        ),
+                    )
+                }
+                (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => Some(Err(e)),
+            }
+        }
+        "services" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            match node {
+                Ok(node) => {
+                    let output = runner.run(&["--nodes", node, "services"]).await;
+                    Some(output.map(|out| match parse_services_table(&out) {
+                        Some(services) => json!({
+                            "node": node,
+                            "count": services.len(),
+                            "services": services
+                        }),
+                        None => json!({
+                            "node": node,
+                            "services": out,
+                            "parse_error": "Unrecognized talosctl services output"
+                        }),
+                    }))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        }
+        "restart" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            let service = params_map
+                .get("service")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing service param"));
+            match (node, service) {
+                (Ok(node), Ok(service)) => {
+                    let output = runner
+                        .run(&["--nodes", node, "service", service, "restart"])
+                        .await;
+                    Some(output.map(|out| json!({"restart": out})))
+                }
+                (Err(e), _) | (_, Err(e)) => Some(Err(e)),
+            }
+        }
+        "get_events" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            let tail = params_map.get("tail").and_then(|v| v.as_i64());
+            let event_type = params_map.get("type").and_then(|v| v.as_str());
+            let now = Utc::now();
+            let since = params_map
+                .get("since")
+                .and_then(|v| v.as_str())
+                .map(|v| parse_time_bound(v, now))
+                .transpose();
+            match (node, since) {
+                (Ok(node), Ok(since)) => {
+                    let mut args = vec!["--nodes", node, "events"];
+
+                    // With a type filter, the tail applies to the matching events, so the
+                    // full history is captured and cut down afterwards
+                    let history = match (since, tail) {
+                        (Some(since), _) => {
+                            let secs = (now - since).num_seconds().max(1);
+                            Some(("--duration", format!("{}s", secs)))
+                        }
+                        (None, Some(_)) if event_type.is_some() => {
+                            Some(("--tail", "-1".to_string()))
+                        }
+                        (None, Some(tail)) => Some(("--tail", tail.to_string())),
+                        (None, None) => None,
+                    };
+                    if let Some((flag, ref value)) = history {
+                        args.extend(&[flag, value]);
+                    }
+                    // talosctl events never exits on its own, so capture a fixed window
+                    let output = runner
+                        .run_bounded(&args, EVENTS_CAPTURE_WINDOW)
+                        .await
+                        .map(|(out, _)| out);
+                    Some(output.map(|out| {
+                        let Some(mut events) = parse_events_table(&out) else {
+                            return json!({
+                                "node": node,
+                                "raw": out,
+                                "parse_error": "Unrecognized talosctl events output"
+                            });
+                        };
+                        if let Some(filter) = event_type {
+                            events.retain(|event| {
+                                event["type"]
+                                    .as_str()
+                                    .is_some_and(|t| event_type_matches(t, filter))
+                            });
+                        }
+                        if let Some(tail) = tail.filter(|t| *t >= 0) {
+                            let skip = events.len().saturating_sub(tail as usize);
+                            events.drain(..skip);
+                        }
+                        json!({
+                            "node": node,
+                            "count": events.len(),
+                            "events": events,
+                            "tail_events": tail,
+                            "type": event_type,
+                            "since": since.map(|t| t.to_rfc3339())
+                        })
+                    }))
+                }
+                (Err(e), _) | (_, Err(e)) => Some(Err(e)),
+            }
+        }
+        _ => None,
+    }
+}
+
+// Poll `talosctl service <name> status` until the service is healthy or the timeout
+// elapses. Services without a health check count as healthy once Running.
+async fn wait_for_service_healthy(
+    node: &str,
+    service: &str,
+    timeout: Duration,
+    runner: &dyn CommandRunner,
+) -> Result<Value> {
+    let started = Instant::now();
+    loop {
+        let status = runner
+            .run(&["--nodes", node, "service", service, "status"])
+            .await?;
+        let field = |name: &str| {
+            status.lines().find_map(|line| {
+                let (key, value) = line.trim().split_once(char::is_whitespace)?;
+                (key == name).then(|| value.trim().to_string())
+            })
+        };
+        let state = field("STATE");
+        let health = field("HEALTH");
+        let healthy = match health.as_deref() {
+            Some("OK") => true,
+            Some("?") | None => state.as_deref() == Some("Running"),
+            Some(_) => false,
+        };
+
+        let elapsed = started.elapsed();
+        if healthy || elapsed >= timeout {
+            return Ok(json!({
+                "healthy": healthy,
+                "state": state,
+                "health": health,
+                "elapsed_ms": elapsed.as_millis() as u64
+            }));
+        }
+        tokio::time::sleep(SERVICE_POLL_INTERVAL.min(timeout - elapsed)).await;
+    }
+}
+
+// Poll a node's API with short version requests until it answers or the timeout
+// passes. With wait_for_down, first wait for the node to stop answering so a node that
+// hasn't gone down yet after a reboot isn't mistaken for one that came back.
+async fn wait_for_node_ready(
+    node: &str,
+    timeout: Duration,
+    wait_for_down: bool,
+    runner: &dyn CommandRunner,
+) -> Result<Value> {
+    let started = Instant::now();
+    let probe = || async {
+        let (out, finished) = runner
+            .run_bounded(&["--nodes", node, "version"], NODE_PROBE_TIMEOUT)
+            .await?;
+        if !finished {
+            return Err(anyhow!(
+                "no response within {}s",
+                NODE_PROBE_TIMEOUT.as_secs()
+            ));
+        }
+        Ok::<_, anyhow::Error>(out)
+    };
+
+    let mut attempts = 0;
+    let mut went_down = !wait_for_down;
+    let mut last_error = None;
+    loop {
+        attempts += 1;
+        match probe().await {
+            Ok(out) if went_down => {
+                // The server section follows the client's, so its Tag comes last
+                let version = out
+                    .lines()
+                    .skip_while(|line| line.trim() != "Server:")
+                    .find_map(|line| line.trim().strip_prefix("Tag:"))
+                    .map(|tag| tag.trim().to_string());
+                return Ok(json!({
+                    "node": node,
+                    "ready": true,
+                    "version": version,
+                    "attempts": attempts,
+                    "elapsed_ms": started.elapsed().as_millis() as u64
+                }));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                went_down = true;
+                last_error = Some(e.to_string());
+            }
+        }
+
+        let elapsed = started.elapsed();
+        if elapsed >= timeout {
+            return Ok(json!({
+                "node": node,
+                "ready": false,
+                "went_down": went_down,
+                "attempts": attempts,
+                "elapsed_ms": elapsed.as_millis() as u64,
+                "last_error": last_error
+            }));
+        }
+        tokio::time::sleep(NODE_POLL_INTERVAL.min(timeout - elapsed)).await;
+    }
+}
+
+// Handle storage and hardware methods
+async fn handle_storage_hardware_methods(
+    method: &str,
+    params_map: &HashMap<String, Value>,
+    runner: &dyn CommandRunner,
+) -> Option<Result<Value>> {
+    match method {
+        "disks" => Some(resources::get_typed_resource(params_map, "disks", "disks", runner).await),
+        "list_disks" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            match node {
+                Ok(node) => {
+                    let output = runner.run(&["--nodes", node, "list", "/sys/block"]).await;
+                    Some(output.map(|out| json!({"disks": out})))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        }
+        "wipe_disk" => {
+            let node = match params_map.get("node").and_then(|v| v.as_str()) {
+                Some(node) => node,
+                None => return Some(Err(invalid_params!("Missing node param"))),
+            };
+            let device = match params_map
+                .get("device")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing device param"))
+                .and_then(block_device_name)
+            {
+                Ok(device) => device,
+                Err(e) => return Some(Err(e)),
+            };
+            let method = params_map.get("method").and_then(|v| v.as_str());
+            if let Some(method) = method.filter(|m| !tools::WIPE_METHODS.contains(m)) {
+                return Some(Err(invalid_params!(
+                    "Invalid method '{}': expected one of: {}",
+                    method,
+                    tools::WIPE_METHODS.join(", ")
+                )));
+            }
+            if let Err(e) = require_confirmation(
+                params_map,
+                &format!("wipe_disk erases all data on {} of {}", device, node),
+            ) {
+                return Some(Err(e));
+            }
+            let mut args = vec!["--nodes", node, "wipe", "disk", device];
+            if let Some(method) = method {
+                args.extend(&["--method", method]);
+            }
+            let output = runner.run(&args).await;
+            Some(output.map(|out| {
+                json!({
+                    "status": "wiped",
+                    "node": node,
+                    "device": device,
+                    "method": method.unwrap_or("fast"),
+                    "output": out
+                })
+            }))
+        }
+        "get_kernel_modules" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            match node {
+                Ok(node) => {
+                    let output = runner
+                        .run(&["--nodes", node, "read", "/proc/modules"])
+                        .await;
+                    Some(output.map(|out| match parse_proc_modules(&out) {
+                        Some(modules) => json!({
+                            "node": node,
+                            "count": modules.len(),
+                            "modules": modules
+                        }),
+                        None => json!({
+                            "node": node,
+                            "modules": out,
+                            "parse_error": "Unrecognized /proc/modules format"
+                        }),
+                    }))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        }
+        _ => None,
+    }
+}
+
+// Handle generic Talos resource methods
+async fn handle_resource_methods(
+    method: &str,
+    params_map: &HashMap<String, Value>,
+    runner: &dyn CommandRunner,
+) -> Option<Result<Value>> {
+    match method {
+        "get_resource" => Some(resources::get_resource(params_map, runner).await),
+        "list_resource_types" => Some(resources::list_resource_types(params_map, runner).await),
+        "compare_resource" => Some(resources::compare_resource(params_map, runner).await),
+        _ => None,
+    }
+}
+
+// Handle controller runtime inspection methods
+async fn handle_inspect_methods(
+    method: &str,
+    params_map: &HashMap<String, Value>,
+    runner: &dyn CommandRunner,
+) -> Option<Result<Value>> {
+    match method {
+        "inspect_dependencies" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            let with_resources = params_map
+                .get("with_resources")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            match node {
+                Ok(node) => {
+                    let mut args = vec!["--nodes", node, "inspect", "dependencies"];
+                    if with_resources {
+                        args.push("--with-resources");
+                    }
+                    let output = runner.run(&args).await;
+                    Some(output.map(|out| {
+                        json!({
+                            "node": node,
+                            "format": "dot",
+                            "with_resources": with_resources,
+                            "graph": out
+                        })
+                    }))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        }
+        _ => None,
+    }
+}
+
+// Handle talosconfig context introspection methods
+async fn handle_config_context_methods(
+    method: &str,
+    runner: &dyn CommandRunner,
+) -> Option<Result<Value>> {
+    match method {
+        "config_info" => {
+            let output = runner.run(&["config", "info"]).await;
+            Some(output.map(|out| {
+                let mut info = parse_config_info(&out);
+                if info.is_empty() {
+                    info.insert("raw".to_string(), json!(out));
+                }
+                Value::Object(info)
+            }))
+        }
+        "config_contexts" => {
+            let output = runner.run(&["config", "contexts"]).await;
+            Some(output.map(|out| match parse_config_contexts(&out) {
+                Some(contexts) => {
+                    let current = contexts
+                        .iter()
+                        .find(|c| c["current"] == true)
+                        .map(|c| c["name"].clone());
+                    json!({"current": current, "contexts": contexts})
+                }
+                None => json!({
+                    "contexts": out,
+                    "parse_error": "Unrecognized talosctl config contexts output"
+                }),
+            }))
+        }
+        _ => None,
+    }
+}
+
+// Files `talosctl gen config` writes into its output directory.
+const GEN_CONFIG_FILES: [&str; 3] = ["controlplane.yaml", "worker.yaml", "talosconfig"];
+
+// Handle cluster bootstrap generation methods. Their output holds the cluster's
+// secrets, so it is written under the work dir and only paths are returned unless
+// the caller asks for the content inline.
+async fn handle_gen_methods(
+    method: &str,
+    params_map: &HashMap<String, Value>,
+    runner: &dyn CommandRunner,
+) -> Option<Result<Value>> {
+    match method {
+        "gen_config" => {
+            let cluster_name = match params_map.get("cluster_name").and_then(|v| v.as_str()) {
+                Some(name) if !name.is_empty() && !name.starts_with('-') => name,
+                Some(name) => return Some(Err(invalid_params!("Invalid cluster_name '{}'", name))),
+                None => return Some(Err(invalid_params!("Missing cluster_name param"))),
+            };
+            let endpoint = match params_map.get("endpoint").and_then(|v| v.as_str()) {
+                Some(endpoint) if endpoint.starts_with("https://") => endpoint,
+                Some(endpoint) => {
+                    return Some(Err(invalid_params!(
+                        "Invalid endpoint '{}': expected a URL like https://10.0.0.1:6443",
+                        endpoint
+                    )))
+                }
+                None => return Some(Err(invalid_params!("Missing endpoint param"))),
+            };
+            let inline = params_map
+                .get("inline")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let output_dir = params_map
+                .get("output_dir")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| {
+                    format!(
+                        "{}-config-{}",
+                        cluster_name,
+                        Utc::now().format("%Y%m%dT%H%M%SZ")
+                    )
+                });
+            let dest = match workdir::artifact_path(&output_dir) {
+                Ok(dest) if dest.exists() => {
+                    return Some(Err(invalid_params!(
+                        "Output directory {} already exists",
+                        dest.display()
+                    )))
+                }
+                Ok(dest) => dest,
+                Err(e) => return Some(Err(e)),
+            };
+            let with_secrets = match params_map.get("with_secrets").and_then(|v| v.as_str()) {
+                Some(path) => match workdir::artifact_path(path) {
+                    Ok(path) => Some(path.to_string_lossy().to_string()),
+                    Err(e) => return Some(Err(e)),
+                },
+                None => None,
+            };
+            let options: Vec<(&str, &str)> = [
+                ("kubernetes_version", "--kubernetes-version"),
+                ("talos_version", "--talos-version"),
+                ("install_disk", "--install-disk"),
+            ]
+            .into_iter()
+            .filter_map(|(param, flag)| {
+                params_map
+                    .get(param)
+                    .and_then(|v| v.as_str())
+                    .map(|value| (flag, value))
+            })
+            .collect();
+
+            let result = workdir::produce(&dest, |temp| async move {
+                let temp_str = temp.to_string_lossy().to_string();
+                let mut args = vec!["gen", "config", cluster_name, endpoint];
+                args.extend(&["--output-dir", &temp_str]);
+                if let Some(ref secrets) = with_secrets {
+                    args.extend(&["--with-secrets", secrets]);
+                }
+                for (flag, value) in &options {
+                    args.extend(&[*flag, *value]);
+                }
+                runner.run(&args).await?;
+                Ok(())
+            })
+            .await;
+            Some(result.and_then(|_| {
+                let mut files = serde_json::Map::new();
+                for name in GEN_CONFIG_FILES {
+                    let path = dest.join(name);
+                    let entry = if inline && !explaining() {
+                        json!(std::fs::read_to_string(&path)
+                            .with_context(|| format!("Failed to read {}", path.display()))?)
+                    } else {
+                        json!(path.to_string_lossy())
+                    };
+                    files.insert(name.to_string(), entry);
+                }
+                Ok(json!({
+                    "cluster_name": cluster_name,
+                    "endpoint": endpoint,
+                    "output_dir": dest.to_string_lossy(),
+                    "files": files,
+                    "inline": inline
+                }))
+            }))
+        }
+        "gen_secrets" => {
+            let output = params_map
+                .get("output")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("secrets-{}.yaml", Utc::now().format("%Y%m%dT%H%M%SZ")));
+            let dest = match workdir::artifact_path(&output) {
+                Ok(dest) if dest.exists() => {
+                    return Some(Err(invalid_params!(
+                        "Secrets bundle {} already exists",
+                        dest.display()
+                    )))
+                }
+                Ok(dest) => dest,
+                Err(e) => return Some(Err(e)),
+            };
+            let result = workdir::produce(&dest, |temp| async move {
+                let temp_str = temp.to_string_lossy().to_string();
+                runner
+                    .run(&["gen", "secrets", "--output-file", &temp_str])
+                    .await?;
+                Ok(())
+            })
+            .await;
+            Some(result.map(|_| json!({"path": dest.to_string_lossy()})))
+        }
+        _ => None,
+    }
+}
+
+// Handle MCP protocol methods
+fn handle_mcp_protocol_methods(
+    method: &str,
+    params_map: &HashMap<String, Value>,
+) -> Option<Result<Value>> {
+    match method {
+        "initialize" => {
+            // MCP initialization - validate required fields and return proper server capabilities
+            // Validate that required fields are present (as per MCP schema)
+            if params_map.get("capabilities").is_none()
+                || params_map.get("clientInfo").is_none()
+                || params_map.get("protocolVersion").is_none()
+            {
+                return Some(Err(invalid_params!("Missing required initialize parameters: capabilities, clientInfo, and protocolVersion are required")));
+            }
+
+            let requested = params_map
+                .get("protocolVersion")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default();
+            let protocol_version = match negotiate_protocol_version(requested) {
+                Ok(version) => version,
+                Err(e) => return Some(Err(e)),
+            };
+
+            Some(Ok(json!({
+                "protocolVersion": protocol_version,
+                "capabilities": {
+                    "tools": {
+                        "listChanged": true
+                    }
+                },
+                "serverInfo": {
+                    "name": "talos-mcp-server",
+                    "title": "Talos OS MCP Server",
+                    "version": "1.0.0"
+                }
+            })))
+        }
+        "notifications/initialized" => {
+            // MCP initialized notification - no response should be sent
+            None
+        }
+        method if method.starts_with("notifications/") => {
+            // Debug: catch any notifications we might be missing
+            None
+        }
+        "ping" => {
+            // MCP ping request - respond with empty object for connection health
+            Some(Ok(json!({})))
+        }
+        "tools/list" => {
+            // Return a page of available tools with schemas
+            let cursor = params_map.get("cursor").and_then(|v| v.as_str());
+            Some(list_tools_page(get_capabilities(), cursor))
+        }
+        _ => None,
+    }
+}
+
+// Slice the tool list into pages of TOOLS_PAGE_SIZE. The cursor is the opaque offset of
+// the page to return, and nextCursor is set while tools remain after it.
+fn list_tools_page(mut capabilities: Value, cursor: Option<&str>) -> Result<Value> {
+    let tools = match capabilities["tools"].take() {
+        Value::Array(tools) => tools,
+        _ => Vec::new(),
+    };
+    let start = match cursor {
+        Some(cursor) => cursor
+            .parse::<usize>()
+            .ok()
+            .filter(|start| *start > 0 && *start < tools.len())
+            .ok_or_else(|| invalid_params!("Invalid cursor '{}'", cursor))?,
+        None => 0,
+    };
+
+    let end = (start + TOOLS_PAGE_SIZE).min(tools.len());
+    let mut page = json!({ "tools": tools[start..end] });
+    if end < tools.len() {
+        page["nextCursor"] = json!(end.to_string());
+    }
+    Ok(page)
+}
+
+// Pick the protocol version to answer initialize with: the client's version when we
+// support it, otherwise our preferred one for the client to accept or reject. Versions
+// older than any we support, or not in YYYY-MM-DD form, are refused outright.
+fn negotiate_protocol_version(requested: &str) -> Result<&'static str> {
+    if let Some(version) = SUPPORTED_PROTOCOL_VERSIONS
+        .iter()
+        .find(|v| **v == requested)
+    {
+        return Ok(version);
+    }
+
+    let oldest = SUPPORTED_PROTOCOL_VERSIONS[SUPPORTED_PROTOCOL_VERSIONS.len() - 1];
+    if chrono::NaiveDate::parse_from_str(requested, "%Y-%m-%d").is_err() || requested < oldest {
+        return Err(invalid_params!(
+            "Unsupported protocol version '{}'; supported versions: {}",
+            requested,
+            SUPPORTED_PROTOCOL_VERSIONS.join(", ")
+        ));
+    }
+    Ok(SUPPORTED_PROTOCOL_VERSIONS[0])
+}
+
+// Handle tool invocation
+async fn handle_tool_invocation(
+    params_map: &HashMap<String, Value>,
+    runner: &dyn CommandRunner,
+) -> Result<Value> {
+    let name = params_map
+        .get("name")
+        .and_then(|v| v.as_str())
+        .ok_or(invalid_params!("Missing tool name"))?;
+    let default_args = json!({});
+    let arguments = params_map.get("arguments").unwrap_or(&default_args);
+
+    // Extract arguments as a map for the tool handlers
+    let args_map = extract_params(Some(arguments));
+    invoke_tool(name, args_map, runner)
+        .await
+        .map(|content| tool_content(&content))
+}
+
+// Run a tool call the same way whichever path it came in on: prepare its node params,
+// apply its per-call options, then audit and redact the result.
+async fn invoke_tool(
+    name: &str,
+    mut args_map: HashMap<String, Value>,
+    runner: &dyn CommandRunner,
+) -> Result<Value> {
+    let result = match nodes::prepare_node_params(&mut args_map).await {
+        Ok(resolutions) => {
+            let call = call_tool(name, &args_map, runner);
+            let result = with_call_options(name, &args_map, call).await;
+            nodes::with_resolutions(result, resolutions)
+        }
+        Err(e) => Err(e),
+    };
+
+    audit::record(name, &args_map, &result);
+    redact::apply(result)
+}
+
+// Wrap a tool result as MCP text content.
+fn tool_content(content: &Value) -> Value {
+    json!({
+        "content": [
+            {
+                "type": "text",
+                "text": serde_json::to_string_pretty(content).unwrap_or_else(|_| content.to_string())
+            }
+        ]
+    })
+}
+
+// Handle core cluster monitoring methods
+async fn handle_core_cluster_methods(
+    method: &str,
+    params_map: &HashMap<String, Value>,
+    runner: &dyn CommandRunner,
+) -> Option<Result<Value>> {
+    match method {
+        "get_version" => {
+            let short = params_map
+                .get("short")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+
+            let node = params_map.get("node").and_then(|v| v.as_str());
+
+            // With a node, talosctl reports the node's Talos version alongside the client's
+            let mut args = match node {
+                Some(node) => vec!["--nodes", node, "version"],
+                None => vec!["version", "--client"],
+            };
+            if short {
+                args.push("--short");
+            }
+
+            let output = runner.run(&args).await;
+            Some(output.map(|out| {
+                let mut result = json!({
+                    "version": out,
+                    "short_format": short
+                });
+                if node.is_some() {
+                    if let (Some(result), Value::Object(versions)) =
+                        (result.as_object_mut(), parse_version_output(&out))
+                    {
+                        result.extend(versions);
+                    }
+                }
+                result
+            }))
+        }
+        "get_metrics" => Some(Ok(metrics::snapshot())),
+        "get_members" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            match node {
+                Ok(node) => {
+                    let output = runner
+                        .run(&["--nodes", node, "get", "members", "--output", "json"])
+                        .await;
+                    Some(
+                        output.map(|out| match resources::parse_resource_stream(&out) {
+                            Ok(members) => {
+                                let members: Vec<Value> =
+                                    members.iter().map(parse_member).collect();
+                                json!({"node": node, "count": members.len(), "members": members})
+                            }
+                            Err(e) => json!({
+                                "node": node,
+                                "raw": out,
+                                "parse_error": e.to_string()
+                            }),
+                        }),
+                    )
+                }
+                Err(e) => Some(Err(e)),
+            }
+        }
+        "get_k8s_version" => {
+            let node = match params_map.get("node").and_then(|v| v.as_str()) {
+                Some(node) => node,
+                None => return Some(Err(invalid_params!("Missing node param"))),
+            };
+            // Control plane components only exist on control plane nodes, so a worker
+            // reports just its kubelet
+            let components = [
+                ("kubelet", "kubeletspec"),
+                ("kube-apiserver", "apiserverconfig"),
+                ("kube-controller-manager", "controllermanagerconfig"),
+                ("kube-scheduler", "schedulerconfig"),
+            ];
+            let lookups = components
+                .iter()
+                .map(|(_, resource)| resource_image_tag(node, resource, runner))
+                .collect();
+            let tags = join_all(lookups).await;
+
+            let mut versions = serde_json::Map::new();
+            let mut errors = serde_json::Map::new();
+            let mut first_error = None;
+            for ((component, _), tag) in components.iter().zip(tags) {
+                match tag {
+                    Ok(tag) => {
+                        versions.insert(component.to_string(), json!(tag));
+                    }
+                    Err(e) => {
+                        errors.insert(component.to_string(), json!(e.to_string()));
+                        first_error.get_or_insert(e);
+                    }
+                }
+            }
+            if let Some(e) = first_error.filter(|_| versions.is_empty() && !explaining()) {
+                return Some(Err(e));
+            }
+            let control_plane = versions
+                .iter()
+                .filter(|(component, _)| *component != "kubelet")
+                .map(|(_, version)| version.clone())
+                .max_by_key(|version| version_key(version.as_str().unwrap_or_default()));
+            let mut distinct: Vec<&Value> = versions.values().collect();
+            distinct.dedup();
+            Some(Ok(json!({
+                "node": node,
+                "kubelet_version": versions.get("kubelet"),
+                "control_plane_version": control_plane,
+                "components": versions,
+                "uniform": distinct.len() <= 1,
+                "errors": errors
+            })))
+        }
+        "version_report" => {
+            let list = |key: &str| {
+                params_map
+                    .get(key)
+                    .and_then(|v| v.as_array())
+                    .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>())
+                    .unwrap_or_default()
+            };
+            let fallback_node = nodes::default_node();
+            let mut targets: Vec<(&str, &str)> = list("control_planes")
+                .into_iter()
+                .map(|node| (node, "controlplane"))
+                .chain(
+                    list("worker_nodes")
+                        .into_iter()
+                        .map(|node| (node, "worker")),
+                )
+                .collect();
+            if targets.is_empty() {
+                match fallback_node.as_deref() {
+                    Some(node) => targets.push((node, "controlplane")),
+                    None => {
+                        return Some(Err(invalid_params!(
+                            "Missing control_planes or worker_nodes param"
+                        )))
+                    }
+                }
+            }
+
+            // Each node is queried separately so one unreachable node doesn't hide
+            // the others' versions
+            let entries = join_all(
+                targets
+                    .iter()
+                    .map(|(node, role)| node_versions(node, role, runner))
+                    .collect(),
+            )
+            .await;
+            let talos = version_skew(&entries, "talos");
+            let kubernetes = version_skew(&entries, "kubernetes");
+            let incomplete: Vec<&Value> = entries
+                .iter()
+                .filter(|entry| {
+                    entry.get("talos_error").is_some() || entry.get("kubernetes_error").is_some()
+                })
+                .map(|entry| &entry["node"])
+                .collect();
+            let uniform = talos["uniform"] == json!(true)
+                && kubernetes["uniform"] == json!(true)
+                && incomplete.is_empty();
+            Some(Ok(json!({
+                "uniform": uniform,
+                "talos": talos,
+                "kubernetes": kubernetes,
+                "incomplete": incomplete,
+                "nodes": entries
+            })))
+        }
+        "get_time" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .filter(|s| !s.is_empty());
+            let check = params_map.get("check").and_then(|v| v.as_str());
+
+            // Time command requires a node to be specified
+            let target_node = match node {
+                Some(n) => n,
+                None => {
+                    return Some(Err(invalid_params!("Time command requires a node to be specified. Please provide a node parameter.")));
+                }
+            };
+
+            let mut args = vec!["--nodes", target_node, "time"];
+
+            if let Some(ntp_server) = check {
+                args.extend(&["--check", ntp_server]);
+            }
+
+            let output = runner.run(&args).await;
+            Some(output.map(|out| {
+                json!({
+                    "time": out,
+                    "node": target_node,
+                    "ntp_check": check
+                })
+            }))
+        }
+        "get_health" => {
+            let fallback_node = nodes::default_node();
+            let control_planes = match params_map
+                .get("control_planes")
+                .and_then(|v| v.as_array())
+                .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>())
+                .or_else(|| fallback_node.as_deref().map(|node| vec![node]))
+            {
+                Some(control_planes) => control_planes,
+                None => return Some(Err(invalid_params!("Missing control_planes param"))),
+            };
+
+            let worker_nodes = params_map
+                .get("worker_nodes")
+                .and_then(|v| v.as_array())
+                .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>());
+
+            let init_node = params_map.get("init_node").and_then(|v| v.as_str());
+            let timeout = params_map
+                .get("timeout")
+                .and_then(|v| v.as_str())
+                .unwrap_or("120s");
+            let run_e2e = params_map
+                .get("run_e2e")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let k8s_endpoint = params_map.get("k8s_endpoint").and_then(|v| v.as_str());
+            let server = params_map
+                .get("server")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(true);
+
+            if control_planes.is_empty() {
+                return Some(Err(invalid_params!(
+                    "At least one control plane node must be specified"
+                )));
+            }
+
+            // Prepare string values that need to live for the entire function
+            let control_planes_str = control_planes.join(",");
+            let workers_str = worker_nodes.as_ref().map(|w| w.join(","));
+
+            // Build command arguments dynamically
+            let mut args = Vec::new();
+
+            // Always specify the first control plane node for --nodes
+            args.extend(&["--nodes", control_planes[0]]);
+
+            // Add the health command
+            args.push("health");
+
+            // Add control plane nodes
+            args.extend(&["--control-plane-nodes", &control_planes_str]);
+
+            // Add worker nodes if specified
+            if let Some(ref workers_string) = workers_str {
+                args.extend(&["--worker-nodes", workers_string]);
+            }
+
+            // Add init node if specified
+            if let Some(init) = init_node {
+                args.extend(&["--init-node", init]);
+            }
+
+            // Add timeout
+            args.extend(&["--wait-timeout", timeout]);
+
+            // Add run-e2e flag if true
+            if run_e2e {
+                args.push("--run-e2e");
+            }
+
+            // Add k8s endpoint if specified
+            if let Some(endpoint) = k8s_endpoint {
+                args.extend(&["--k8s-endpoint", endpoint]);
+            }
+
+            // Add server flag (note: --server is default true, --no-server to disable)
+            if !server {
+                args.push("--server=false");
+            }
+
+            let mut all_nodes: Vec<&str> = Vec::new();
+            for node in control_planes
+                .iter()
+                .chain(worker_nodes.iter().flatten())
+                .chain(init_node.as_ref())
+            {
+                if !all_nodes.contains(node) {
+                    all_nodes.push(node);
+                }
+            }
+
+            // A failed health check still reports which checks passed, so parse its
+            // output too and only fail outright when talosctl couldn't run
+            // talosctl must be allowed to outlast its own --wait-timeout
+            let command_timeout = parse_duration(timeout)
+                .map(|wait| (wait + WAIT_TIMEOUT_MARGIN).max(talosctl_timeout()))
+                .unwrap_or_else(|_| talosctl_timeout());
+            let output = match CALL_TIMEOUT
+                .scope(command_timeout, runner.run_with_stderr(&args))
+                .await
+            {
+                Ok(out) => Ok((out, true)),
+                Err(e) => match e.downcast::<TalosctlError>() {
+                    Ok(err) if err.category != Some("timeout") => Ok((err.stderr, false)),
+                    Ok(err) => Err(err.into()),
+                    Err(e) => Err(e),
+                },
+            };
+            match output {
+                Ok((out, succeeded)) => {
+                    let mut result = health::parse_report(&out, &all_nodes, succeeded);
+                    if let Some(obj) = result.as_object_mut() {
+                        obj.insert("health".to_string(), json!(out));
+                        obj.insert(
+                            "cluster_info".to_string(),
+                            json!({
+                                "control_planes": control_planes,
+                                "worker_nodes": worker_nodes,
+                                "init_node": init_node,
+                                "timeout": timeout,
+                                "run_e2e": run_e2e,
+                                "k8s_endpoint": k8s_endpoint,
+                                "server_side": server
+                            }),
+                        );
+                    }
+                    Some(Ok(result))
+                }
+                Err(e) => Some(Err(anyhow!("Health check failed: {}", e))),
+            }
+        }
+        "collect_diagnostics" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            match node {
+                Ok(node) => {
+                    let checks = [
+                        ("get_version", "get_version", json!({"node": node})),
+                        (
+                            "get_health",
+                            "get_health",
+                            json!({"control_planes": [node], "timeout": "30s"}),
+                        ),
+                        (
+                            "service_kubelet",
+                            "service",
+                            json!({"node": node, "service": "kubelet", "action": "status"}),
+                        ),
+                        (
+                            "dmesg",
+                            "dmesg",
+                            json!({"node": node, "tail": DIAGNOSTICS_TAIL}),
+                        ),
+                        (
+                            "get_events",
+                            "get_events",
+                            json!({"node": node, "tail": DIAGNOSTICS_TAIL}),
+                        ),
+                        ("disks", "disks", json!({"node": node})),
+                        ("interfaces", "interfaces", json!({"node": node})),
+                    ];
+
+                    // Run every check even if earlier ones fail, recording each outcome
+                    let mut diagnostics = serde_json::Map::new();
+                    let mut failed = Vec::new();
+                    for (key, tool, args) in checks {
+                        let args_map = extract_params(Some(&args));
+                        // Boxed since dispatch leads back into this handler
+                        let entry = match Box::pin(dispatch_tool(tool, &args_map, runner)).await {
+                            Ok(result) => json!({"success": true, "result": result}),
+                            Err(e) => {
+                                failed.push(key);
+                                json!({"success": false, "error": e.to_string()})
+                            }
+                        };
+                        diagnostics.insert(key.to_string(), entry);
+                    }
+
+                    Some(Ok(json!({
+                        "node": node,
+                        "diagnostics": diagnostics,
+                        "failed_checks": failed
+                    })))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        }
+        "get_apiserver_logs" | "get_kubelet_logs" => {
+            // kube-apiserver runs as a container in the k8s.io namespace, while kubelet
+            // is a Talos system service
+            let (service, kubernetes) = match method {
+                "get_apiserver_logs" => ("kube-apiserver", true),
+                _ => ("kubelet", false),
+            };
+            let mut logs_params = params_map.clone();
+            logs_params.insert("service".to_string(), json!(service));
+            logs_params.insert("kubernetes".to_string(), json!(kubernetes));
+            Box::pin(handle_core_cluster_methods(
+                "get_logs",
+                &logs_params,
+                runner,
+            ))
+            .await
+        }
+        "get_support_bundle" => {
+            let list = |key: &str| {
+                params_map
+                    .get(key)
+                    .and_then(|v| v.as_array())
+                    .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>())
+                    .unwrap_or_default()
+            };
+            let mut targets = list("control_planes");
+            targets.extend(list("worker_nodes"));
+            if targets.is_empty() {
+                match params_map.get("node").and_then(|v| v.as_str()) {
+                    Some(node) => targets.push(node),
+                    None => {
+                        return Some(Err(invalid_params!(
+                            "Missing control_planes, worker_nodes or node param"
+                        )))
+                    }
+                }
+            }
+            let output = params_map
+                .get("output")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("support-{}.zip", Utc::now().format("%Y%m%dT%H%M%SZ")));
+            let dest = match workdir::artifact_path(&output) {
+                Ok(dest) => dest,
+                Err(e) => return Some(Err(e)),
+            };
+
+            // The bundle is a binary zip, so only its location and size are returned
+            let node_list = targets.join(",");
+            let result = workdir::produce(&dest, |temp| async move {
+                let temp_str = temp.to_string_lossy().to_string();
+                runner
+                    .run(&["--nodes", &node_list, "support", "--output", &temp_str])
+                    .await?;
+                Ok(())
+            })
+            .await
+            .and_then(|_| workdir::artifact_size(&dest));
+            Some(result.map(|bytes| {
+                json!({
+                    "path": dest.to_string_lossy(),
+                    "format": "zip",
+                    "bytes": bytes,
+                    "nodes": targets
+                })
+            }))
+        }
+        "get_logs" if params_map.contains_key("services") => {
+            let services = match params_map.get("services").and_then(|v| v.as_array()) {
+                Some(services) if !services.is_empty() && services.iter().all(Value::is_string) => {
+                    services
+                        .iter()
+                        .filter_map(|v| v.as_str())
+                        .collect::<Vec<_>>()
+                }
+                _ => {
+                    return Some(Err(invalid_params!(
+                        "Invalid services: expected a non-empty array of service names"
+                    )))
+                }
+            };
+            if params_map.contains_key("service") {
+                return Some(Err(invalid_params!(
+                    "Pass either service or services, not both"
+                )));
+            }
+
+            // Each service is fetched as its own get_logs call, all at once
+            let calls = services
+                .iter()
+                .map(|service| {
+                    let mut service_params = params_map.clone();
+                    service_params.remove("services");
+                    service_params.insert("service".to_string(), json!(service));
+                    async move {
+                        Box::pin(handle_core_cluster_methods(
+                            "get_logs",
+                            &service_params,
+                            runner,
+                        ))
+                        .await
+                        .unwrap_or_else(|| Err(anyhow!("get_logs is not handled")))
+                    }
+                })
+                .collect();
+            let results = join_all(calls).await;
+
+            let mut logs = serde_json::Map::new();
+            let mut errors = serde_json::Map::new();
+            let mut first_error = None;
+            for (service, result) in services.iter().zip(results) {
+                match result {
+                    Ok(result) => {
+                        logs.insert(service.to_string(), result);
+                    }
+                    Err(e) => {
+                        errors.insert(service.to_string(), json!(e.to_string()));
+                        first_error.get_or_insert(e);
+                    }
+                }
+            }
+            // Only fail outright when no service's logs could be fetched
+            match first_error {
+                Some(e) if logs.is_empty() => Some(Err(e)),
+                _ => Some(Ok(json!({
+                    "node": params_map.get("node"),
+                    "services": services,
+                    "logs": logs,
+                    "errors": errors
+                }))),
+            }
+        }
+        "get_logs" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            let service = params_map
+                .get("service")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing service param"));
+            let tail = params_map.get("tail").and_then(|v| v.as_i64());
+            let kubernetes = params_map
+                .get("kubernetes")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let now = Utc::now();
+            let since = params_map
+                .get("since")
+                .and_then(|v| v.as_str())
+                .map(|v| parse_time_bound(v, now))
+                .transpose();
+            let until = params_map
+                .get("until")
+                .and_then(|v| v.as_str())
+                .map(|v| parse_time_bound(v, now))
+                .transpose();
+            let limit = match output::max_output_bytes(params_map) {
+                Ok(limit) => limit,
+                Err(e) => return Some(Err(e)),
+            };
+            match (node, service, since, until) {
+                (Ok(node), Ok(service), Ok(since), Ok(until)) => {
+                    let windowed = since.is_some() || until.is_some();
+                    let mut args = vec!["--nodes", node, "logs", service];
+
+                    // talosctl has no time filter, so with a window the tail is applied
+                    // after filtering rather than truncating the capture up front
+                    let tail_str = tail.map(|t| t.to_string());
+                    if let (Some(ref tail_count), false) = (&tail_str, windowed) {
+                        args.extend(&["--tail", tail_count]);
+                    }
+
+                    if kubernetes {
+                        args.push("--kubernetes");
+                    }
+
+                    let output = runner.run(&args).await;
+                    Some(output.map(|out| {
+                        let logs = if windowed {
+                            filter_log_window(&out, since, until, tail)
+                        } else {
+                            out
+                        };
+                        let mut result = json!({
+                            "logs": logs,
+                            "service": service,
+                            "tail_lines": tail,
+                            "window": {
+                                "since": since.map(|t| t.to_rfc3339()),
+                                "until": until.map(|t| t.to_rfc3339())
+                            },
+                            "namespace": if kubernetes { "k8s.io" } else { "system" }
+                        });
+                        output::limit_field(&mut result, "logs", limit, output::Keep::Tail);
+                        result
+                    }))
+                }
+                (Err(e), _, _, _) | (_, Err(e), _, _) | (_, _, Err(e), _) | (_, _, _, Err(e)) => {
+                    Some(Err(e))
+                }
+            }
+        }
+        _ => None,
+    }
+}
+
+// Handle node management methods
+async fn handle_node_management_methods(
+    method: &str,
+    params_map: &HashMap<String, Value>,
+    runner: &dyn CommandRunner,
+) -> Option<Result<Value>> {
+    match method {
+        "reboot_node" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            let mode = params_map.get("mode").and_then(|v| v.as_str());
+            if let Some(mode) = mode.filter(|m| !tools::REBOOT_MODES.contains(m)) {
+                return Some(Err(invalid_params!(
+                    "Invalid mode '{}': expected one of: {}",
+                    mode,
+                    tools::REBOOT_MODES.join(", ")
+                )));
+            }
+            match node {
+                Ok(node) => {
+                    if let Err(e) = protect_quorum(node, runner).await {
+                        return Some(Err(e));
+                    }
+                    let mut args = vec!["--nodes", node, "reboot"];
+                    if let Some(mode) = mode {
+                        args.extend(&["--mode", mode]);
+                    }
+                    let output = runner.run(&args).await;
+                    Some(output.map(|_| {
+                        json!({"status": "reboot initiated", "mode": mode.unwrap_or("default")})
+                    }))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        }
+        "wait_node_ready" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            let timeout = params_map
+                .get("timeout")
+                .and_then(|v| v.as_str())
+                .unwrap_or("5m");
+            let wait_for_down = params_map
+                .get("wait_for_down")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            match (node, parse_duration(timeout)) {
+                (Ok(node), Ok(timeout)) => {
+                    Some(wait_for_node_ready(node, timeout, wait_for_down, runner).await)
+                }
+                (Err(e), _) | (_, Err(e)) => Some(Err(e)),
+            }
+        }
+        "shutdown_node" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            match node {
+                Ok(node) => {
+                    if let Err(e) = protect_quorum(node, runner).await {
+                        return Some(Err(e));
+                    }
+                    let output = runner.run(&["--nodes", node, "shutdown"]).await;
+                    Some(output.map(|_| json!({"status": "node shutdown initiated"})))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        }
+        "reset_node" => {
+            let node = match params_map.get("node").and_then(|v| v.as_str()) {
+                Some(node) => node,
+                None => return Some(Err(invalid_params!("Missing node param"))),
+            };
+            if let Err(e) = require_confirmation(
+                params_map,
+                "reset_node wipes the node and removes it from the cluster",
+            ) {
+                return Some(Err(e));
+            }
+            let reboot = params_map
+                .get("reboot")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let maintenance = params_map
+                .get("maintenance")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let graceful = params_map
+                .get("graceful")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(true);
+            let list = |key: &str| -> Vec<&str> {
+                params_map
+                    .get(key)
+                    .and_then(|v| v.as_array())
+                    .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect())
+                    .unwrap_or_default()
+            };
+            let wipe = list("wipe");
+            let user_disks = list("wipe_user_disks");
+            if let Some(label) = wipe.iter().find(|l| {
+                l.is_empty()
+                    || !l
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            }) {
+                return Some(Err(invalid_params!("Invalid partition label '{}'", label)));
+            }
+            if maintenance && !wipe.is_empty() {
+                return Some(Err(invalid_params!(
+                    "maintenance already wipes STATE and EPHEMERAL; it cannot be combined with wipe"
+                )));
+            }
+
+            let mut args = vec!["--nodes", node, "reset"];
+            if !graceful {
+                // Skip cordoning, draining and leaving etcd, for a node that's already dead
+                args.push("--graceful=false");
+            }
+
+            // Wiping only STATE and EPHEMERAL drops the machine config, so the
+            // installed system reboots into maintenance mode awaiting a new config
+            let wiped = if maintenance {
+                vec!["STATE", "EPHEMERAL"]
+            } else {
+                wipe
+            };
+            for label in &wiped {
+                args.extend(&["--system-labels-to-wipe", label]);
+            }
+            for disk in &user_disks {
+                args.extend(&["--user-disks-to-wipe", disk]);
+            }
+            let post_reset_state = if maintenance {
+                args.push("--reboot");
+                "maintenance"
+            } else if reboot {
+                args.push("--reboot");
+                "rebooting"
+            } else {
+                "powered_off"
+            };
+
+            let output = runner.run(&args).await;
+            Some(output.map(|_| {
+                json!({
+                    "status": "node reset initiated",
+                    "post_reset_state": post_reset_state,
+                    "graceful": graceful,
+                    "wiped_labels": if wiped.is_empty() { json!("all") } else { json!(wiped) },
+                    "wiped_user_disks": user_disks
+                })
+            }))
+        }
+        "upgrade_node" => {
+            let node = match params_map.get("node").and_then(|v| v.as_str()) {
+                Some(node) => node,
+                None => return Some(Err(invalid_params!("Missing node param"))),
+            };
+            let image = params_map
+                .get("image")
+                .and_then(|v| v.as_str())
+                .unwrap_or("ghcr.io/siderolabs/installer:latest");
+            let flag = |key: &str| {
+                params_map
+                    .get(key)
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false)
+            };
+            let (dry_run, preserve, stage, wait) = (
+                flag("dry_run"),
+                flag("preserve"),
+                flag("stage"),
+                flag("wait"),
+            );
+            let reboot_mode = params_map.get("reboot_mode").and_then(|v| v.as_str());
+            if let Some(mode) = reboot_mode.filter(|m| !tools::REBOOT_MODES.contains(m)) {
+                return Some(Err(invalid_params!(
+                    "Invalid reboot_mode '{}': expected one of: {}",
+                    mode,
+                    tools::REBOOT_MODES.join(", ")
+                )));
+            }
+            let timeout = params_map.get("timeout").and_then(|v| v.as_str());
+            if timeout.is_some() && !wait {
+                return Some(Err(invalid_params!("timeout only applies with wait")));
+            }
+            let timeout = timeout.unwrap_or(UPGRADE_WAIT_TIMEOUT);
+            let wait_timeout = match parse_duration(timeout) {
+                Ok(wait_timeout) => wait_timeout,
+                Err(e) => return Some(Err(e)),
+            };
+
+            let mut args = vec!["--nodes", node, "upgrade", "--image", image];
+            if preserve {
+                args.push("--preserve");
+            }
+            if stage {
+                args.push("--stage");
+            }
+            if let Some(mode) = reboot_mode {
+                args.extend(&["--reboot-mode", mode]);
+            }
+            if dry_run {
+                args.push("--dry-run");
+            }
+            let output = if wait {
+                // talosctl must be allowed to outlast its own --timeout
+                args.extend(&["--wait", "--timeout", timeout]);
+                let command_timeout = (wait_timeout + WAIT_TIMEOUT_MARGIN).max(talosctl_timeout());
+                CALL_TIMEOUT.scope(command_timeout, runner.run(&args)).await
+            } else {
+                runner.run(&args).await
+            };
+            Some(output.map(|out| {
+                let status = if dry_run {
+                    "dry run"
+                } else if wait {
+                    "upgrade completed"
+                } else if stage {
+                    "upgrade staged for next reboot"
+                } else {
+                    "upgrade initiated"
+                };
+                let mut result = json!({
+                    "status": status,
+                    "image": image,
+                    "preserve": preserve,
+                    "stage": stage
+                });
+                if dry_run {
+                    result["dry_run"] = json!(true);
+                    result["plan"] = json!(out);
+                } else if wait {
+                    result["output"] = json!(out);
+                }
+                result
+            }))
+        }
+        "upgrade_k8s" => {
+            let to = match params_map.get("to").and_then(|v| v.as_str()) {
+                Some(to) => to,
+                None => return Some(Err(invalid_params!("Missing to param"))),
+            };
+            // Without from, talosctl detects the cluster's current version itself
+            let from = params_map.get("from").and_then(|v| v.as_str());
+            if let Some(version) = [Some(to), from]
+                .into_iter()
+                .flatten()
+                .find(|v| !is_k8s_version(v))
+            {
+                return Some(Err(invalid_params!(
+                    "Invalid Kubernetes version '{}': expected e.g. 1.30.1",
+                    version
+                )));
+            }
+            let node = params_map.get("node").and_then(|v| v.as_str());
+            let dry_run = params_map
+                .get("dry_run")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+
+            let mut args = Vec::new();
+            if let Some(node) = node {
+                args.extend(&["--nodes", node]);
+            }
+            args.push("upgrade-k8s");
+            if let Some(from) = from {
+                args.extend(&["--from", from]);
+            }
+            args.extend(&["--to", to]);
+            if dry_run {
+                args.push("--dry-run");
+            }
+            let output = runner.run(&args).await;
+            Some(output.map(|out| {
+                json!({
+                    "status": if dry_run { "dry run" } else { "k8s upgrade completed" },
+                    "dry_run": dry_run,
+                    "from": from,
+                    "to": to,
+                    "output": out
+                })
+            }))
+        }
+        "rollback_node" => {
+            let node = match params_map.get("node").and_then(|v| v.as_str()) {
+                Some(node) => node,
+                None => return Some(Err(invalid_params!("Missing node param"))),
+            };
+            if let Err(e) = require_confirmation(
+                params_map,
+                "rollback_node reboots the node into its previous Talos installation",
+            ) {
+                return Some(Err(e));
+            }
+            if let Err(e) = protect_quorum(node, runner).await {
+                return Some(Err(e));
+            }
+            let output = runner.run(&["--nodes", node, "rollback"]).await;
+            Some(output.map(|out| {
+                json!({
+                    "status": "rollback initiated",
+                    "node": node,
+                    "output": out
+                })
+            }))
+        }
+        _ => None,
+    }
+}
+
+// A Kubernetes version as upgrade-k8s takes it: major.minor.patch, optionally v-prefixed.
+fn is_k8s_version(version: &str) -> bool {
+    let parts: Vec<&str> = version
+        .strip_prefix('v')
+        .unwrap_or(version)
+        .split('.')
+        .collect();
+    parts.len() == 3
+        && parts
+            .iter()
+            .all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))
+}
+
+// The config documents of a node's MachineConfig resource, from `talosctl get
+// machineconfig -o yaml`. The spec holds the config either inline or as YAML text,
+// which may be several `---`-separated documents.
+fn machine_config_documents(out: &str) -> Result<Vec<Value>> {
+    let resources = resources::parse_yaml_stream(out)?;
+    let spec = resources
+        .into_iter()
+        .find_map(|mut resource| resource.get_mut("spec").map(Value::take))
+        .ok_or_else(|| anyhow!("No machine config in talosctl output"))?;
+    match spec {
+        Value::String(text) => resources::parse_yaml_stream(&text),
+        document => Ok(vec![document]),
+    }
+}
+
+// The node's machine type (controlplane, worker, init), if it can be queried.
+async fn machine_type(node: &str, runner: &dyn CommandRunner) -> Option<String> {
+    runner
+        .run(&["--nodes", node, "get", "machinetype", "--output", "json"])
+        .await
+        .and_then(|out| resources::parse_resource_stream(&out))
+        .ok()
+        .and_then(|types| types.first()?["spec"].as_str().map(str::to_string))
+}
+
+// With TALOS_PROTECT_QUORUM set, refuse to take down a control plane node unless at
+// least one other voting etcd member is healthy, so the cluster API stays reachable.
+async fn protect_quorum(node: &str, runner: &dyn CommandRunner) -> Result<()> {
+    if !env::var("TALOS_PROTECT_QUORUM").is_ok_and(|v| !v.is_empty() && v != "0") || explaining() {
+        return Ok(());
+    }
+    if machine_type(node, runner).await.as_deref() == Some("worker") {
+        return Ok(());
+    }
+
+    let members = runner
+        .run(&["--nodes", node, "etcd", "members"])
+        .await
+        .map_err(|e| {
+            anyhow!(
+                "Quorum protection: could not list etcd members from {} ({}); refusing to proceed",
+                node,
+                e
+            )
+        })?;
+
+    // Voting members other than the target, by peer address; learners don't count
+    let others: Vec<String> = members
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.last() == Some(&"true") {
+                return None;
+            }
+            let hostname = fields.get(2).copied();
+            let peer = fields
+                .iter()
+                .find_map(|f| f.split(',').next()?.split_once("://"))
+                .map(|(_, addr)| {
+                    let host = addr.rsplit_once(':').map_or(addr, |(host, _)| host);
+                    host.trim_start_matches('[')
+                        .trim_end_matches(']')
+                        .to_string()
+                })?;
+            (peer != node && hostname != Some(node)).then_some(peer)
+        })
+        .collect();
+
+    let mut healthy = false;
+    for member in &others {
+        let status = runner
+            .run_bounded(&["--nodes", member, "etcd", "status"], NODE_PROBE_TIMEOUT)
+            .await;
+        if status.is_ok_and(|(_, finished)| finished) {
+            healthy = true;
+            break;
+        }
+    }
+    if !healthy {
+        return Err(anyhow!(
+            "Quorum protection: {} is the last healthy control plane ({} other etcd member(s), none healthy); refusing to take it down. Unset TALOS_PROTECT_QUORUM to override",
+            node,
+            others.len()
+        ));
+    }
+    Ok(())
+}
+
+// Whether etcd on the node already runs a cluster. `etcd status` only reports a member
+// once etcd has been bootstrapped; before that the call fails.
+async fn etcd_bootstrapped(node: &str, runner: &dyn CommandRunner) -> bool {
+    runner
+        .run(&["--nodes", node, "etcd", "status"])
+        .await
+        .is_ok_and(|out| out.lines().skip(1).any(|line| !line.trim().is_empty()))
+}
+
+// Refuse an etcd operation against a worker node, unless strict is false. talosctl's own
+// error for this is confusing; a node whose type can't be determined is let through.
+async fn require_control_plane(
+    params_map: &HashMap<String, Value>,
+    runner: &dyn CommandRunner,
+) -> Result<()> {
+    let strict = params_map
+        .get("strict")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true);
+    let Some(node) = params_map.get("node").and_then(|v| v.as_str()) else {
+        return Ok(());
+    };
+    if !strict || explaining() {
+        return Ok(());
+    }
+
+    if machine_type(node, runner).await.as_deref() == Some("worker") {
+        return Err(invalid_params!(
+            "node {} is a worker, not a control plane; etcd operations must target a control plane node (pass strict: false to skip this check)",
+            node
+        ));
+    }
+    Ok(())
+}
+
+// Validate JSON6902 (RFC 6902) patch operations and normalize each to the members its
+// op uses: path always, value for add/replace/test, and from for move/copy.
+fn json6902_ops(ops: &[Value]) -> Result<Vec<Value>> {
+    if ops.is_empty() {
+        return Err(invalid_params!("Invalid patch: no operations"));
+    }
+    let pointer = |op: &Value, index: usize, member: &str| -> Result<Value> {
+        match op.get(member).and_then(|v| v.as_str()) {
+            Some(path) if path.starts_with('/') => Ok(json!(path)),
+            _ => Err(invalid_params!(
+                "Invalid patch operation {}: {} must be a JSON pointer such as /machine/network",
+                index,
+                member
+            )),
+        }
+    };
+    ops.iter()
+        .enumerate()
+        .map(|(index, op)| {
+            let name = op.get("op").and_then(|v| v.as_str()).unwrap_or_default();
+            let mut normalized = serde_json::Map::new();
+            normalized.insert("op".to_string(), json!(name));
+            normalized.insert("path".to_string(), pointer(op, index, "path")?);
+            match name {
+                "add" | "replace" | "test" => {
+                    let value = op.get("value").ok_or_else(|| {
+                        invalid_params!(
+                            "Invalid patch operation {}: {} requires a value",
+                            index,
+                            name
+                        )
+                    })?;
+                    normalized.insert("value".to_string(), value.clone());
+                }
+                "move" | "copy" => {
+                    normalized.insert("from".to_string(), pointer(op, index, "from")?);
+                }
+                "remove" => {}
+                _ => {
+                    return Err(invalid_params!(
+                        "Invalid patch operation {}: op must be one of add, remove, replace, move, copy or test",
+                        index
+                    ))
+                }
+            }
+            Ok(Value::Object(normalized))
+        })
+        .collect()
+}
+
+// Refuse a destructive operation unless the caller passed confirm: true.
+fn require_confirmation(params_map: &HashMap<String, Value>, consequence: &str) -> Result<()> {
+    let confirmed = params_map
+        .get("confirm")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    if !confirmed {
+        return Err(invalid_params!(
+            "{}; pass confirm: true to proceed",
+            consequence
+        ));
+    }
+    Ok(())
+}
+
+// Handle configuration and etcd methods
+async fn handle_config_etcd_methods(
+    method: &str,
+    params_map: &HashMap<String, Value>,
+    runner: &dyn CommandRunner,
+) -> Option<Result<Value>> {
+    if ETCD_METHODS.contains(&method) {
+        if let Err(e) = require_control_plane(params_map, runner).await {
+            return Some(Err(e));
+        }
+    }
+
+    match method {
+        "apply_config" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            let file = params_map.get("file").and_then(|v| v.as_str());
+            let content = params_map.get("content").and_then(|v| v.as_str());
+            let mode = params_map.get("mode").and_then(|v| v.as_str());
+            let try_timeout = params_map.get("timeout").and_then(|v| v.as_str());
+            let dry_run = params_map
+                .get("dry_run")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            match (mode, try_timeout) {
+                (Some(mode), _) if !tools::APPLY_CONFIG_MODES.contains(&mode) => {
+                    return Some(Err(invalid_params!(
+                        "Invalid mode '{}': expected one of: {}",
+                        mode,
+                        tools::APPLY_CONFIG_MODES.join(", ")
+                    )))
+                }
+                (mode, Some(_)) if mode != Some("try") => {
+                    return Some(Err(invalid_params!("timeout only applies to mode 'try'")))
+                }
+                (_, Some(timeout)) => {
+                    if let Err(e) = parse_duration(timeout) {
+                        return Some(Err(e));
+                    }
+                }
+                _ => {}
+            }
+            // Inline content is staged in a private temp file, removed when `staged` drops
+            let staged = match (file, content) {
+                (Some(_), Some(_)) => {
+                    return Some(Err(invalid_params!(
+                        "Pass either file or content, not both"
+                    )))
+                }
+                (None, None) => return Some(Err(invalid_params!("Missing file or content param"))),
+                (Some(_), None) => None,
+                (None, Some(content)) => match workdir::TempInput::write("machine-config", content)
+                {
+                    Ok(staged) => Some(staged),
+                    Err(e) => return Some(Err(e)),
+                },
+            };
+            let staged_path = staged
+                .as_ref()
+                .map(|staged| staged.path().to_string_lossy().to_string());
+            let file = staged_path.as_deref().or(file).unwrap_or_default();
+            match node {
+                Ok(node) => {
+                    let mut args = vec!["--nodes", node, "apply-config", "--file", file];
+                    if let Some(mode) = mode {
+                        args.extend(&["--mode", mode]);
+                    }
+                    if let Some(timeout) = try_timeout {
+                        args.extend(&["--timeout", timeout]);
+                    }
+                    if dry_run {
+                        args.push("--dry-run");
+                    }
+                    let output = runner.run(&args).await;
+                    Some(output.map(|out| {
+                        if dry_run {
+                            return json!({"status": "dry run", "dry_run": true, "plan": out});
+                        }
+                        json!({
+                            "status": "config applied",
+                            "source": if staged.is_some() { "content" } else { "file" },
+                            "mode": mode.unwrap_or("auto"),
+                            "timeout": try_timeout
+                        })
+                    }))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        }
+        "patch_config" => {
+            let node = match params_map.get("node").and_then(|v| v.as_str()) {
+                Some(node) => node,
+                None => return Some(Err(invalid_params!("Missing node param"))),
+            };
+            let patch = match params_map.get("patch") {
+                Some(Value::String(patch)) => match serde_json::from_str::<Value>(patch) {
+                    Ok(patch) => patch,
+                    Err(e) => {
+                        return Some(Err(invalid_params!(
+                            "Invalid patch: not valid JSON ({})",
+                            e
+                        )))
+                    }
+                },
+                Some(patch @ (Value::Object(_) | Value::Array(_))) => patch.clone(),
+                Some(_) => {
+                    return Some(Err(invalid_params!(
+                        "Invalid patch: expected a JSON patch string, object or array"
+                    )))
+                }
+                None => return Some(Err(invalid_params!("Missing patch param"))),
+            };
+            // An array is a JSON6902 patch; each op is checked here rather than by talosctl
+            let patch = match patch {
+                Value::Array(ops) => match json6902_ops(&ops) {
+                    Ok(ops) => Value::Array(ops),
+                    Err(e) => return Some(Err(e)),
+                },
+                patch => patch,
+            };
+            let mode = params_map.get("mode").and_then(|v| v.as_str());
+            if let Some(mode) = mode.filter(|m| !tools::APPLY_CONFIG_MODES.contains(m)) {
+                return Some(Err(invalid_params!(
+                    "Invalid mode '{}': expected one of: {}",
+                    mode,
+                    tools::APPLY_CONFIG_MODES.join(", ")
+                )));
+            }
+            let dry_run = params_map
+                .get("dry_run")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            if !dry_run {
+                if let Err(e) = require_confirmation(
+                    params_map,
+                    "patch_config changes the node's machine config and may reboot it",
+                ) {
+                    return Some(Err(e));
+                }
+            }
+
+            // The patch may carry secrets, so it reaches talosctl as a private file
+            // rather than on the command line
+            let staged = match workdir::TempInput::write("config-patch", &patch.to_string()) {
+                Ok(staged) => staged,
+                Err(e) => return Some(Err(e)),
+            };
+            let patch_arg = format!("@{}", staged.path().to_string_lossy());
+            let mut args = vec![
+                "--nodes",
+                node,
+                "patch",
+                "machineconfig",
+                "--patch",
+                &patch_arg,
+            ];
+            if let Some(mode) = mode {
+                args.extend(&["--mode", mode]);
+            }
+            if dry_run {
+                args.push("--dry-run");
+            }
+            let output = runner.run(&args).await;
+            Some(output.map(|out| {
+                if dry_run {
+                    return json!({"status": "dry run", "dry_run": true, "plan": out});
+                }
+                json!({
+                    "status": "config patched",
+                    "node": node,
+                    "mode": mode.unwrap_or("auto"),
+                    "output": out
+                })
+            }))
+        }
+        "get_kubeconfig" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            let path = params_map.get("path").and_then(|v| v.as_str());
+            let merge = params_map
+                .get("merge")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(true);
+            let force = params_map
+                .get("force")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            match (node, path) {
+                // The kubeconfig carries cluster credentials, so it is only ever
+                // returned to the caller, never logged
+                (Ok(node), None) => {
+                    let output = runner.run(&["--nodes", node, "kubeconfig", "-"]).await;
+                    Some(output.map(|out| json!({"node": node, "kubeconfig": out})))
+                }
+                (Ok(node), Some(path)) => {
+                    let dest = match workdir::artifact_path(path) {
+                        Ok(dest) => dest.to_string_lossy().to_string(),
+                        Err(e) => return Some(Err(e)),
+                    };
+                    let mut args = vec!["--nodes", node, "kubeconfig", dest.as_str()];
+                    if !merge {
+                        args.push("--merge=false");
+                    }
+                    if force {
+                        args.push("--force");
+                    }
+                    let output = runner.run(&args).await;
+                    Some(output.map(|_| {
+                        json!({
+                            "node": node,
+                            "path": dest,
+                            "merged": merge,
+                            "forced": force
+                        })
+                    }))
+                }
+                (Err(e), _) => Some(Err(e)),
+            }
+        }
+        "get_machine_config" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            let redact_secrets = params_map
+                .get("redact_secrets")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(true);
+            match node {
+                Ok(node) => {
+                    let output = runner
+                        .run(&["--nodes", node, "get", "machineconfig", "--output", "yaml"])
+                        .await;
+                    Some(output.and_then(|out| {
+                        let documents = match machine_config_documents(&out) {
+                            Ok(documents) => documents,
+                            // Unparsed output can't be scrubbed, so it is only returned as is
+                            // when redaction was turned off (or there is none, when explaining)
+                            Err(e) if !redact_secrets || explaining() => {
+                                return Ok(json!({
+                                    "node": node,
+                                    "config": out,
+                                    "redacted": false,
+                                    "parse_error": e.to_string()
+                                }))
+                            }
+                            Err(e) => return Err(e),
+                        };
+                        let mut redactions = 0;
+                        let config = documents
+                            .into_iter()
+                            .map(|document| {
+                                let document = if redact_secrets {
+                                    let (document, count) = redact::redact_document(document);
+                                    redactions += count;
+                                    document
+                                } else {
+                                    document
+                                };
+                                serde_yaml::to_string(&document)
+                                    .context("Failed to serialize machine config")
+                            })
+                            .collect::<Result<Vec<_>>>()?;
+                        Ok(json!({
+                            "node": node,
+                            "config": config.join("---\n"),
+                            "documents": config.len(),
+                            "redacted": redact_secrets,
+                            "redactions": redactions
+                        }))
+                    }))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        }
+        "validate_config" => {
+            let config = params_map
+                .get("config")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing config param"));
+            let mode = params_map
+                .get("mode")
+                .and_then(|v| v.as_str())
+                .unwrap_or("container");
+            match config {
+                Ok(config) => {
+                    let output = runner
+                        .run(&["validate", "--config", config, "--mode", mode])
+                        .await;
+                    Some(output.map(|out| json!({"validation": out})))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        }
+        "get_etcd_status" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            match node {
+                Ok(node) => {
+                    let output = runner.run(&["--nodes", node, "etcd", "status"]).await;
+                    Some(output.map(|out| json!({"etcd_status": out})))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        }
+        "get_etcd_members" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            match node {
+                Ok(node) => {
+                    let output = runner.run(&["--nodes", node, "etcd", "members"]).await;
+                    Some(output.map(|out| json!({"etcd_members": out})))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        }
+        "defrag_etcd" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            match node {
+                Ok(node) => {
+                    let output = runner.run(&["--nodes", node, "etcd", "defrag"]).await;
+                    Some(output.map(|_| json!({"status": "etcd defragmented"})))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        }
+        "bootstrap_etcd" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            let force = params_map
+                .get("force")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            match node {
+                Ok(node) => {
+                    // Bootstrapping a second time would split the cluster's etcd
+                    if !force && !explaining() && etcd_bootstrapped(node, runner).await {
+                        return Some(Err(invalid_params!(
+                            "etcd on {} is already bootstrapped; bootstrapping again can split the cluster (pass force: true to bootstrap anyway)",
+                            node
+                        )));
+                    }
+                    let output = runner.run(&["--nodes", node, "bootstrap"]).await;
+                    Some(output.map(|_| json!({"status": "etcd bootstrapped", "forced": force})))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        }
+        "etcd_snapshot" => {
+            let node = match params_map.get("node").and_then(|v| v.as_str()) {
+                Some(node) => node,
+                None => return Some(Err(invalid_params!("Missing node param"))),
+            };
+            let overwrite = params_map
+                .get("overwrite")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let path = params_map
+                .get("path")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| {
+                    format!(
+                        "etcd-{}-{}.snapshot",
+                        node.replace(':', "_"),
+                        Utc::now().format("%Y%m%dT%H%M%SZ")
+                    )
+                });
+            let dest = match workdir::artifact_path(&path) {
+                Ok(dest) => dest,
+                Err(e) => return Some(Err(e)),
+            };
+            if dest.exists() && !overwrite {
+                return Some(Err(invalid_params!(
+                    "Snapshot file {} already exists; pass overwrite: true to replace it",
+                    dest.display()
+                )));
+            }
+
+            let result = workdir::produce(&dest, |temp| async move {
+                let temp_str = temp.to_string_lossy().to_string();
+                runner
+                    .run(&["--nodes", node, "etcd", "snapshot", &temp_str])
+                    .await
+            })
+            .await
+            .and_then(|_| workdir::artifact_size(&dest));
+            Some(result.map(|bytes| {
+                json!({
+                    "node": node,
+                    "path": dest.to_string_lossy(),
+                    "bytes": bytes
+                })
+            }))
+        }
+        "etcd_restore" => {
+            let node = match params_map.get("node").and_then(|v| v.as_str()) {
+                Some(node) => node,
+                None => return Some(Err(invalid_params!("Missing node param"))),
+            };
+            let path = match params_map.get("path").and_then(|v| v.as_str()) {
+                Some(path) => path,
+                None => return Some(Err(invalid_params!("Missing path param"))),
+            };
+            if let Err(e) = require_confirmation(
+                params_map,
+                "etcd_restore bootstraps a new etcd cluster from the snapshot, discarding the current etcd state",
+            ) {
+                return Some(Err(e));
+            }
+            let skip_hash_check = params_map
+                .get("skip_hash_check")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let snapshot = match workdir::artifact_path(path) {
+                Ok(snapshot) if snapshot.is_file() => snapshot.to_string_lossy().to_string(),
+                Ok(snapshot) => {
+                    return Some(Err(invalid_params!(
+                        "Snapshot file {} does not exist",
+                        snapshot.display()
+                    )))
+                }
+                Err(e) => return Some(Err(e)),
+            };
+
+            let mut args = vec!["--nodes", node, "bootstrap", "--recover-from", &snapshot];
+            if skip_hash_check {
+                args.push("--recover-skip-hash-check");
+            }
+            let output = runner.run(&args).await;
+            Some(output.map(|_| {
+                json!({
+                    "status": "etcd recovered from snapshot",
+                    "node": node,
+                    "snapshot": snapshot
+                })
+            }))
+        }
+        "etcd_remove_member" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            let member_id = params_map
+                .get("member_id")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing member_id param"));
+            match (node, member_id) {
+                (Ok(node), Ok(member_id)) => {
+                    if member_id.is_empty() || !member_id.chars().all(|c| c.is_ascii_alphanumeric())
+                    {
+                        return Some(Err(invalid_params!(
+                            "Invalid member_id '{}': expected an etcd member ID as listed by get_etcd_members",
+                            member_id
+                        )));
+                    }
+                    if let Err(e) = require_confirmation(
+                        params_map,
+                        &format!(
+                            "etcd_remove_member permanently removes member {} from the etcd cluster",
+                            member_id
+                        ),
+                    ) {
+                        return Some(Err(e));
+                    }
+                    let output = runner
+                        .run(&["--nodes", node, "etcd", "remove-member", member_id])
+                        .await;
+                    Some(output.map(|_| {
+                        json!({
+                            "status": "etcd member removed",
+                            "node": node,
+                            "member_id": member_id
+                        })
+                    }))
+                }
+                (Err(e), _) | (_, Err(e)) => Some(Err(e)),
+            }
+        }
+        "etcd_forfeit_leadership" => {
+            let node = params_map
+                .get("node")
+                .and_then(|v| v.as_str())
+                .ok_or(invalid_params!("Missing node param"));
+            match node {
+                Ok(node) => {
+                    let output = runner
+                        .run(&["--nodes", node, "etcd", "forfeit-leadership"])
+                        .await;
+                    Some(output.map(|out| {
+                        json!({
+                            "status": "etcd leadership forfeited",
+                            "node": node,
+                            "output": out.trim()
+                        })
+                    }))
+                }
+                Err(e) => Some(Err(e)),
+            }
+        }
+        _ => None,
+    }
+}
+
+// Handler for each method (following grok.md specification).
+async fn handle_method(
+    method: &str,
+    params: Option<&Value>,
+    runner: &dyn CommandRunner,
+) -> Option<Result<Value>> {
+    let params_map = extract_params(params);
+
+    // Try MCP protocol methods FIRST (ping, initialize, tools/list, etc.)
+    if let Some(result) = handle_mcp_protocol_methods(method, &params_map) {
+        return Some(result); // Found a matching MCP method
+    }
+
+    // Special handling for notifications that should return None
+    if method.starts_with("notifications/") {
+        return None; // Notifications should not have responses
+    }
+
+    // Tools are reached through tools/call, which prepares and audits its own arguments
+    if method == "tools/call" {
+        return Some(handle_tool_invocation(&params_map, runner).await);
+    }
+
+    // Calling a tool directly by name is only kept for legacy clients that opt in
+    if !tools::direct_tool_calls() || !tools::is_tool(method) {
+        return Some(Err(method_not_found!("Unknown method: {}", method)));
+    }
+    Some(invoke_tool(method, params_map, runner).await)
+}
+
+// Run a tool, or with `explain: true`, only report the talosctl commands it would run.
+async fn call_tool(
+    name: &str,
+    params_map: &HashMap<String, Value>,
+    runner: &dyn CommandRunner,
+) -> Result<Value> {
+    let explain = params_map
+        .get("explain")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    if !explain {
+        let started = Instant::now();
+        let result = dispatch_tool(name, params_map, runner).await;
+        metrics::record(name, started.elapsed(), result.is_ok());
+        return result;
+    }
+    if streaming::is_streaming_tool(name) {
+        return Err(invalid_params!(
+            "{} streams its output and can't be explained",
+            name
+        ));
+    }
+
+    // Nothing runs, so the confirmation gate doesn't apply
+    let mut params_map = params_map.clone();
+    params_map.insert("confirm".to_string(), json!(true));
+    let recorder = ExplainRunner::default();
+    let call = CALL_EXPLAIN.scope(true, dispatch_tool(name, &params_map, &recorder));
+    if let Ok(Err(e)) = tokio::time::timeout(EXPLAIN_TIMEOUT, call).await {
+        return Err(e);
+    }
+    let commands = recorder.commands();
+    let mut global_args = vec!["--talosconfig".to_string(), "[REDACTED]".to_string()];
+    global_args.extend(call_global_args());
+    Ok(json!({
+        "tool": name,
+        "explain": true,
+        "global_args": global_args,
+        "commands": commands
+    }))
+}
+
+// Route a tool call to the handler category that implements the tool.
+async fn dispatch_tool(
+    method: &str,
+    params_map: &HashMap<String, Value>,
+    runner: &dyn CommandRunner,
+) -> Result<Value> {
+    // Try system inspection methods
+    if let Some(result) = handle_system_inspection_methods(method, params_map, runner).await {
+        return result;
+    }
+
+    // Try file operations methods
+    if let Some(result) = handle_file_operations_methods(method, params_map, runner).await {
+        return result;
+    }
+
+    // Try network operations methods
+    if let Some(result) = handle_network_operations_methods(method, params_map, runner).await {
+        return result;
+    }
+
+    // Try service and logging methods
+    if let Some(result) = handle_service_log_methods(method, params_map, runner).await {
+        return result;
+    }
+
+    // Try storage and hardware methods
+    if let Some(result) = handle_storage_hardware_methods(method, params_map, runner).await {
+        return result;
+    }
+
+    // Try resource methods
+    if let Some(result) = handle_resource_methods(method, params_map, runner).await {
+        return result;
+    }
+
+    // Try inspect methods
+    if let Some(result) = handle_inspect_methods(method, params_map, runner).await {
+        return result;
+    }
+
+    // Try talosconfig context methods
+    if let Some(result) = handle_config_context_methods(method, runner).await {
+        return result;
+    }
+
+    // Try cluster generation methods
+    if let Some(result) = handle_gen_methods(method, params_map, runner).await {
+        return result;
+    }
+
+    // Try core cluster methods
+    if let Some(result) = handle_core_cluster_methods(method, params_map, runner).await {
+        return result;
+    }
+
+    // Try node management methods
+    if let Some(result) = handle_node_management_methods(method, params_map, runner).await {
+        return result;
+    }
+
+    // Try config/etcd methods
+    if let Some(result) = handle_config_etcd_methods(method, params_map, runner).await {
+        return result;
+    }
+
+    Err(invalid_params!("Unknown tool: {}", method))
+}
+
+// Serialize a method result as a JSON-RPC success or error response.
+fn build_response(id: Option<Value>, method_result: Result<Value>) -> Result<String> {
+    let resp_json = match method_result {
+        Ok(res) => {
+            let response = RpcSuccessResponse {
+                jsonrpc: "2.0".to_string(),
+                result: res,
+                id,
+            };
+            serde_json::to_string(&response)?
+        }
+        Err(err) => {
+            let response = RpcErrorResponse {
+                jsonrpc: "2.0".to_string(),
+                error: RpcError {
+                    code: err
+                        .downcast_ref::<RpcFailure>()
+                        .map_or(INTERNAL_ERROR, RpcFailure::code),
+                    message: err.to_string(),
+                    data: match err.downcast_ref::<PartialFailure>() {
+                        Some(partial) => Some(partial.error_data()),
+                        None => err
+                            .downcast_ref::<TalosctlError>()
+                            .map(TalosctlError::error_data),
+                    },
+                },
+                id,
+            };
+            serde_json::to_string(&response)?
+        }
+    };
+    Ok(resp_json)
+}
+
+// Main async RPC loop over stdio (from grok.md specification).
+async fn rpc_loop() -> Result<()> {
+    let stdout: SharedStdout = Arc::new(Mutex::new(Box::new(tokio::io::stdout())));
+    // A broken setup still serves the protocol, so the client can show why tools fail
+    let runner: Arc<dyn CommandRunner> = match preflight().await {
+        Ok(()) => Arc::new(TalosctlRunner),
+        Err(e) => {
+            tracing::error!(
+                error = %format_args!("{:#}", e),
+                "Preflight failed; every tool call will report this until it is fixed"
+            );
+            Arc::new(UnavailableRunner {
+                reason: format!("{:#}", e),
+            })
+        }
+    };
+    metrics::start();
+    if let Some((listen, token)) = transport::listen_config()? {
+        return transport::listen(listen, token, runner, shutdown_signal()).await;
+    }
+    serve(
+        BufReader::new(tokio::io::stdin()),
+        stdout,
+        runner,
+        shutdown_signal(),
+    )
+    .await
+}
+
+// Check that TALOSCONFIG names a readable file and that talosctl runs, so a broken
+// setup is reported at startup rather than as a cryptic first tool failure.
+async fn preflight() -> Result<()> {
+    let talosconfig = env::var("TALOSCONFIG").context("TALOSCONFIG env var not set")?;
+    std::fs::File::open(&talosconfig)
+        .with_context(|| format!("TALOSCONFIG {} is not readable", talosconfig))?;
+
+    let output = Command::new("talosctl")
+        .args(["version", "--client"])
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(PREFLIGHT_TIMEOUT, output)
+        .await
+        .map_err(|_| {
+            anyhow!(
+                "talosctl version --client did not finish within {}s",
+                PREFLIGHT_TIMEOUT.as_secs()
+            )
+        })?
+        .context("Failed to run talosctl; is it installed and on PATH?")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "talosctl version --client failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+// Resolves with the signal's name once the server is asked to stop.
+async fn shutdown_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => tokio::select! {
+                _ = terminate.recv() => "SIGTERM",
+                _ = tokio::signal::ctrl_c() => "SIGINT",
+            },
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to listen for SIGTERM");
+                let _ = tokio::signal::ctrl_c().await;
+                "SIGINT"
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        "Ctrl-C"
+    }
+}
+
+// Serve JSON-RPC requests, one per line, until the input ends or `shutdown` resolves.
+// On shutdown no further requests are read, and those in flight get the grace period
+// (TALOS_SHUTDOWN_GRACE) to finish, so an apply-config isn't cut off midway.
+async fn serve(
+    input: impl AsyncBufRead + Unpin,
+    stdout: SharedStdout,
+    runner: Arc<dyn CommandRunner>,
+    shutdown: impl std::future::Future<Output = &'static str>,
+) -> Result<()> {
+    let mut lines = input.lines();
+    let in_flight: InFlight = Arc::default();
+    // Tool set last returned by tools/list, for list_changed notifications
+    let advertised_tools: Arc<Mutex<Option<Value>>> = Arc::default();
+    let mut requests = JoinSet::new();
+    tokio::pin!(shutdown);
+
+    loop {
+        let line = tokio::select! {
+            line = lines.next_line() => match line? {
+                Some(line) => line,
+                None => break,
+            },
+            reason = &mut shutdown => {
+                return drain_requests(requests, reason).await;
+            }
+        };
+        // Reap finished request tasks so the set doesn't grow unbounded
+        while requests.try_join_next().is_some() {}
+
+        if line.trim().is_empty() {
+            continue;
+        }
+        // A malformed line is answered with an error; it doesn't end the session
+        let message: Value = match serde_json::from_str(&line) {
+            Ok(message) => message,
+            Err(e) => {
+                let failure = RpcFailure::ParseError(format!("Parse error: {}", e));
+                streaming::write_message(&stdout, &build_response(None, Err(failure.into()))?)
+                    .await?;
+                continue;
+            }
+        };
+
+        if let Value::Array(batch) = message {
+            let stdout = stdout.clone();
+            let in_flight = in_flight.clone();
+            let advertised_tools = advertised_tools.clone();
+            let runner = runner.clone();
+            let handled = async move {
+                if let Err(e) =
+                    handle_batch(batch, &stdout, &in_flight, &advertised_tools, runner).await
+                {
+                    tracing::error!(error = %e, "Failed to handle batch");
+                }
+            };
+            requests.spawn(handled.in_current_span());
+            continue;
+        }
+
+        let request = match parse_request(message) {
+            Ok(request) => request,
+            Err((id, e)) => {
+                streaming::write_message(&stdout, &build_response(id, Err(e))?).await?;
+                continue;
+            }
+        };
+
+        // Cancellation aborts an in-flight call; like all notifications it gets no response
+        if request.method == "notifications/cancelled" {
+            streaming::cancel_request(&in_flight, request.params.as_ref()).await;
+            continue;
+        }
+
+        // Streaming tools run in the background so cancellations can still be read
+        if let Some(call) = streaming::streaming_call(&request.method, request.params.as_ref()) {
+            streaming::spawn_streaming_call(call, request.id, stdout.clone(), in_flight.clone())
+                .await;
+            continue;
+        }
+
+        // Each request runs on its own task so a slow talosctl call doesn't hold up the
+        // requests behind it, and so it can be cancelled
+        let registration = streaming::register_cancel(&in_flight, request.id.as_ref()).await;
+        let stdout = stdout.clone();
+        let in_flight = in_flight.clone();
+        let advertised_tools = advertised_tools.clone();
+        let runner = runner.clone();
+        let handled = async move {
+            let handled = handle_request(request, &stdout, &advertised_tools, &*runner);
+            if let Some(Err(e)) =
+                streaming::run_cancellable(&in_flight, registration, handled).await
+            {
+                tracing::error!(error = %e, "Failed to handle request");
+            }
+        };
+        requests.spawn(handled.in_current_span());
+    }
+
+    // Answer requests still running when the client closes its end
+    while requests.join_next().await.is_some() {}
+    Ok(())
+}
+
+// Give in-flight requests the shutdown grace period to finish, then abort the rest.
+async fn drain_requests(mut requests: JoinSet<()>, reason: &str) -> Result<()> {
+    let grace = env::var("TALOS_SHUTDOWN_GRACE")
+        .ok()
+        .and_then(|v| parse_duration(&v).ok())
+        .unwrap_or(DEFAULT_SHUTDOWN_GRACE);
+    tracing::warn!(
+        signal = reason,
+        grace_secs = grace.as_secs(),
+        in_flight = requests.len(),
+        "Shutting down, waiting for in-flight requests"
+    );
+    let drained = tokio::time::timeout(grace, async {
+        while requests.join_next().await.is_some() {}
+    })
+    .await;
+    if drained.is_err() {
+        tracing::error!(
+            aborted = requests.len(),
+            "Shutdown grace period elapsed; aborting in-flight requests"
+        );
+        requests.shutdown().await;
+    }
+    Ok(())
+}
+
+// Read a request from a parsed message, or return the error to answer it with and the
+// request id, if it has one.
+fn parse_request(
+    message: Value,
+) -> std::result::Result<RpcRequest, (Option<Value>, anyhow::Error)> {
+    let id = message.get("id").cloned();
+    let mut request: RpcRequest = serde_json::from_value(message).map_err(|e| {
+        let failure = RpcFailure::InvalidRequest(format!("Invalid request: {}", e));
+        (id, anyhow::Error::new(failure))
+    })?;
+
+    // Canonical tool names are resolved to their handlers up front, so everything past
+    // here sees one name per tool whether it was called canonically or by a legacy alias
+    if request.method == "tools/call" {
+        if let Some(name) = request.params.as_mut().and_then(|p| p.get_mut("name")) {
+            if let Some(resolved) = name.as_str().map(tools::resolve_tool_name) {
+                *name = json!(resolved);
+            }
+        }
+    } else if tools::direct_tool_calls() {
+        request.method = tools::resolve_tool_name(&request.method).to_string();
+    }
+    Ok(request)
+}
+
+// Handle one request and write its response, if it has one. Slow tools report
+// progress in the meantime when the client passes a progress token.
+async fn handle_request(
+    request: RpcRequest,
+    stdout: &SharedStdout,
+    advertised_tools: &Mutex<Option<Value>>,
+    runner: &dyn CommandRunner,
+) -> Result<()> {
+    let progress_token = streaming::progress_token(&request.method, request.params.as_ref());
+    let response = streaming::with_progress(
+        stdout,
+        progress_token,
+        respond(request, advertised_tools, runner),
+    );
+    if let Some(resp_json) = response.await? {
+        streaming::write_message(stdout, &resp_json).await?;
+    }
+    notify_if_tools_changed(stdout, advertised_tools).await
+}
+
+// Run a request and build its response; notifications have none.
+async fn respond(
+    request: RpcRequest,
+    advertised_tools: &Mutex<Option<Value>>,
+    runner: &dyn CommandRunner,
+) -> Result<Option<String>> {
+    let span = request_span(
+        &request.method,
+        request.id.as_ref(),
+        request.params.as_ref(),
+    );
+    let started = Instant::now();
+    let result = handle_method(&request.method, request.params.as_ref(), runner)
+        .instrument(span.clone())
+        .await;
+    log_request(
+        &span,
+        request.params.as_ref(),
+        result.as_ref(),
+        started.elapsed(),
+    );
+    let Some(method_result) = result else {
+        return Ok(None);
+    };
+    if request.method == "tools/list" && method_result.is_ok() {
+        *advertised_tools.lock().await = Some(get_capabilities());
+    }
+    build_response(request.id, method_result).map(Some)
+}
+
+// A span covering one request, so everything logged while it is handled carries its
+// method, id and (for `tools/call`) tool name. `log_request` fills in the rest.
+fn request_span(method: &str, id: Option<&Value>, params: Option<&Value>) -> tracing::Span {
+    let id = id.map_or_else(|| "none".to_string(), Value::to_string);
+    let tool = params
+        .filter(|_| method == "tools/call")
+        .and_then(|p| p.get("name"))
+        .and_then(|v| v.as_str());
+    tracing::info_span!(
+        "request",
+        method,
+        id = %id,
+        tool,
+        duration_ms = tracing::field::Empty,
+        outcome = tracing::field::Empty
+    )
+}
+
+// Record a handled request's duration and outcome on its span and log it to stderr:
+// at info level (warn on failure), and its params, with secrets redacted, at debug.
+// Results are never logged.
+fn log_request(
+    span: &tracing::Span,
+    params: Option<&Value>,
+    outcome: Option<&Result<Value>>,
+    elapsed: Duration,
+) {
+    span.record("duration_ms", elapsed.as_millis() as u64);
+    span.record(
+        "outcome",
+        match outcome {
+            Some(Ok(_)) => "ok",
+            Some(Err(_)) => "error",
+            None => "notification",
+        },
+    );
+    let _entered = span.enter();
+    match outcome {
+        Some(Err(e)) => tracing::warn!(error = %e, "request failed"),
+        _ => tracing::info!("request handled"),
+    }
+    if let Some(params) = params {
+        tracing::debug!(params = %audit::redact_params(params), "request params");
+    }
+}
+
+// Run a batch of requests concurrently and write their responses as one array, in
+// request order. Notifications get no entry, and an all-notification batch no reply.
+async fn handle_batch(
+    batch: Vec<Value>,
+    stdout: &SharedStdout,
+    in_flight: &InFlight,
+    advertised_tools: &Arc<Mutex<Option<Value>>>,
+    runner: Arc<dyn CommandRunner>,
+) -> Result<()> {
+    if batch.is_empty() {
+        let failure = RpcFailure::InvalidRequest("Invalid request: empty batch".to_string());
+        return streaming::write_message(stdout, &build_response(None, Err(failure.into()))?).await;
+    }
+
+    let mut responses = Vec::new();
+    let mut calls = JoinSet::new();
+    for (index, message) in batch.into_iter().enumerate() {
+        let request = match parse_request(message) {
+            Ok(request) => request,
+            Err((id, e)) => {
+                responses.push((index, build_response(id, Err(e))?));
+                continue;
+            }
+        };
+        if request.method == "notifications/cancelled" {
+            streaming::cancel_request(in_flight, request.params.as_ref()).await;
+            continue;
+        }
+        // Streaming calls answer through progress notifications over time, which
+        // doesn't fit a single batch reply
+        if streaming::streaming_call(&request.method, request.params.as_ref()).is_some() {
+            let failure = RpcFailure::InvalidRequest(
+                "Invalid request: streaming tool calls can't be batched".to_string(),
+            );
+            responses.push((index, build_response(request.id, Err(failure.into()))?));
+            continue;
+        }
+
+        let advertised_tools = advertised_tools.clone();
+        let runner = runner.clone();
+        let call = async move { (index, respond(request, &advertised_tools, &*runner).await) };
+        calls.spawn(call.in_current_span());
+    }
+    while let Some(joined) = calls.join_next().await {
+        match joined {
+            Ok((index, Ok(Some(resp_json)))) => responses.push((index, resp_json)),
+            Ok((_, Ok(None))) => {}
+            Ok((_, Err(e))) => tracing::error!(error = %e, "Failed to build batch response"),
+            Err(e) => tracing::error!(error = %e, "Batch request task failed"),
+        }
+    }
+
+    if !responses.is_empty() {
+        responses.sort_by_key(|(index, _)| *index);
+        let items: Vec<String> = responses.into_iter().map(|(_, resp)| resp).collect();
+        streaming::write_message(stdout, &format!("[{}]", items.join(","))).await?;
+    }
+    notify_if_tools_changed(stdout, advertised_tools).await
+}
+
+// Send notifications/tools/list_changed when the effective tool set no longer matches
+// what the client last listed, e.g. after a runtime mode toggle. Clients that haven't
+// listed tools yet will see the current set anyway, so they aren't notified.
+async fn notify_if_tools_changed(
+    stdout: &SharedStdout,
+    advertised_tools: &Mutex<Option<Value>>,
+) -> Result<()> {
+    let mut advertised_tools = advertised_tools.lock().await;
+    let Some(advertised) = advertised_tools.as_ref() else {
+        return Ok(());
+    };
+    let current = get_capabilities();
+    if *advertised == current {
+        return Ok(());
+    }
+
+    *advertised_tools = Some(current);
+    let notification = json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/tools/list_changed"
+    });
+    streaming::write_message(stdout, &notification.to_string()).await
+}
+
+fn main() -> Result<()> {
+    // Logs go to stderr, leaving stdout to the JSON-RPC stream. `log` records from the
+    // rest of the server are forwarded into the same subscriber
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::IsTerminal::is_terminal(&std::io::stderr()))
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+    let rt = Runtime::new()?;
+    rt.block_on(rpc_loop())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use runner::MockRunner;
+
+    // Dispatch a tool against a mock runner and return every talosctl argument vector it built.
+    async fn talosctl_calls(tool: &str, params: Value) -> Vec<Vec<String>> {
+        let runner = MockRunner::default();
+        let params_map = extract_params(Some(&params));
+        dispatch_tool(tool, &params_map, &runner)
+            .await
+            .unwrap_or_else(|e| panic!("{} failed: {}", tool, e));
+        runner.calls()
+    }
+
+    // The argument vector of the last talosctl call a tool made.
+    async fn talosctl_args(tool: &str, params: Value) -> Vec<String> {
+        talosctl_calls(tool, params)
+            .await
+            .pop()
+            .expect("tool ran no talosctl command")
+    }
+
+    #[tokio::test]
+    async fn ping_node_routes_through_the_source_node() {
+        let runner = MockRunner::default();
+        let params_map = extract_params(Some(&json!({"node": "10.0.0.1", "target": "10.0.0.2"})));
+        let result = with_call_options(
+            "ping_node",
+            &params_map,
+            dispatch_tool("ping_node", &params_map, &runner),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result["reachable"], true);
+        assert!(result["elapsed_ms"].is_u64());
+        assert_eq!(
+            runner.calls(),
+            [["--endpoints", "10.0.0.1", "--nodes", "10.0.0.2", "version"]]
+        );
+
+        // A per-call endpoint would give talosctl a second --endpoints flag
+        let params_map = extract_params(Some(
+            &json!({"node": "10.0.0.1", "target": "10.0.0.2", "endpoint": "10.0.0.3"}),
+        ));
+        let err = with_call_options(
+            "ping_node",
+            &params_map,
+            dispatch_tool("ping_node", &params_map, &runner),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RpcFailure>(),
+            Some(RpcFailure::InvalidParams(_))
+        ));
+        assert_eq!(runner.calls().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn list_with_recurse() {
+        let args = talosctl_args(
+            "list",
+            json!({"node": "10.0.0.1", "path": "/var/log", "recurse": true}),
+        )
+        .await;
+        assert_eq!(
+            args,
+            ["--nodes", "10.0.0.1", "list", "/var/log", "--recurse"]
+        );
+    }
+
+    #[test]
+    fn list_output_is_paged() {
+        let out = "NODE       NAME\n\
+                   10.0.0.1   .\n\
+                   10.0.0.1   containerd\n\
+                   10.0.0.1   log\n\
+                   10.0.0.1   pods\n";
+        let page = page_list_output(out, 1, Some(2));
+        assert_eq!(page["total"], 4);
+        assert_eq!(page["has_more"], true);
+        assert_eq!(page["entries"][0]["name"], "containerd");
+        assert_eq!(page["entries"][1]["name"], "log");
+        assert!(page["list"].as_str().unwrap().starts_with("NODE"));
+
+        let last = page_list_output(out, 3, None);
+        assert_eq!(last["entries"].as_array().map(Vec::len), Some(1));
+        assert_eq!(last["has_more"], false);
+        assert_eq!(page_list_output(out, 10, Some(5))["entries"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn read_slices_lines() {
+        let runner = MockRunner::with_output("one\ntwo\nthree\nfour\n");
+        let read = |params: Value| {
+            let params_map = extract_params(Some(&params));
+            let runner = &runner;
+            async move { dispatch_tool("read", &params_map, runner).await }
+        };
+        let tail = read(json!({"node": "10.0.0.1", "path": "/var/log/x", "tail_lines": 2}))
+            .await
+            .unwrap();
+        assert_eq!(tail["content"], "three\nfour\n");
+        assert_eq!(tail["total_lines"], 4);
+        let head = read(json!({"node": "10.0.0.1", "path": "/var/log/x", "head_lines": 1}))
+            .await
+            .unwrap();
+        assert_eq!(head["content"], "one\n");
+        let both =
+            read(json!({"node": "10.0.0.1", "path": "/x", "head_lines": 1, "tail_lines": 1}));
+        assert!(both.await.is_err());
+    }
+
+    #[tokio::test]
+    async fn list_with_depth() {
+        let args = talosctl_args(
+            "list",
+            json!({"node": "10.0.0.1", "path": "/var/log", "depth": 3, "long": true}),
+        )
+        .await;
+        assert_eq!(
+            args,
+            ["--nodes", "10.0.0.1", "list", "/var/log", "--long", "--depth", "3"]
+        );
+    }
+
+    #[tokio::test]
+    async fn list_recurse_overrides_depth() {
+        let args = talosctl_args(
+            "list",
+            json!({"node": "10.0.0.1", "recurse": true, "depth": 3, "type": ["f", "d"]}),
+        )
+        .await;
+        assert_eq!(
+            args,
+            [
+                "--nodes",
+                "10.0.0.1",
+                "list",
+                "/",
+                "--recurse",
+                "--type",
+                "f",
+                "--type",
+                "d"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn read_file() {
+        let args = talosctl_args("read", json!({"node": "10.0.0.1", "path": "/etc/hosts"})).await;
+        assert_eq!(args, ["--nodes", "10.0.0.1", "read", "/etc/hosts"]);
+    }
+
+    #[tokio::test]
+    async fn netstat() {
+        let args = talosctl_args("get_netstat", json!({"node": "10.0.0.1"})).await;
+        assert_eq!(args, ["--nodes", "10.0.0.1", "netstat"]);
+    }
+
+    #[tokio::test]
+    async fn version_short() {
+        let args = talosctl_args("get_version", json!({"short": true})).await;
+        assert_eq!(args, ["version", "--client", "--short"]);
+
+        let args = talosctl_args("get_version", json!({"node": "10.0.0.1"})).await;
+        assert_eq!(args, ["--nodes", "10.0.0.1", "version"]);
+    }
+
+    #[test]
+    fn version_skew_flags_nodes_behind() {
+        let entries = [
+            json!({"node": "10.0.0.1", "talos_version": "v1.8.1", "kubernetes_version": "v1.31.0"}),
+            json!({"node": "10.0.0.2", "talos_version": "v1.8.1", "kubernetes_version": "v1.31.0"}),
+            json!({"node": "10.0.0.3", "talos_version": "v1.7.6", "kubernetes_version": "v1.31.0"}),
+            json!({"node": "10.0.0.4", "talos_error": "connection refused"}),
+        ];
+        let talos = version_skew(&entries, "talos");
+        assert_eq!(talos["latest"], "v1.8.1");
+        assert_eq!(talos["behind"], json!(["10.0.0.3"]));
+        assert_eq!(talos["uniform"], false);
+        assert_eq!(talos["versions"]["v1.8.1"], json!(["10.0.0.1", "10.0.0.2"]));
+        assert_eq!(version_skew(&entries, "kubernetes")["uniform"], true);
+        assert!(version_key("v1.10.0") > version_key("v1.9.3"));
+    }
+
+    #[tokio::test]
+    async fn gen_tools_write_to_fresh_paths() {
+        let dir = workdir::work_dir()
+            .unwrap()
+            .join(format!("gen-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let runner = MockRunner::default();
+        let gen = |tool: &str, params: Value| {
+            let params_map = extract_params(Some(&params));
+            let runner = &runner;
+            let tool = tool.to_string();
+            async move { dispatch_tool(&tool, &params_map, runner).await }
+        };
+
+        // MockRunner writes nothing, so the artifact is never moved into place
+        let err = gen(
+            "gen_config",
+            json!({
+                "cluster_name": "prod",
+                "endpoint": "https://10.0.0.1:6443",
+                "output_dir": dir.join("prod").to_string_lossy(),
+                "kubernetes_version": "1.31.1"
+            }),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("Failed to move artifact"));
+        let args = runner.calls().pop().unwrap();
+        assert_eq!(
+            args[..5],
+            [
+                "gen",
+                "config",
+                "prod",
+                "https://10.0.0.1:6443",
+                "--output-dir"
+            ]
+        );
+        assert_eq!(args[6..], ["--kubernetes-version", "1.31.1"]);
+
+        let bundle = dir.join("secrets.yaml");
+        assert!(
+            gen("gen_secrets", json!({"output": bundle.to_string_lossy()}))
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            runner.calls().pop().unwrap()[..3],
+            ["gen", "secrets", "--output-file"]
+        );
+
+        // Existing output is never overwritten, and endpoints must be URLs
+        std::fs::write(&bundle, "").unwrap();
+        let calls = runner.calls().len();
+        assert!(
+            gen("gen_secrets", json!({"output": bundle.to_string_lossy()}))
+                .await
+                .unwrap_err()
+                .to_string()
+                .contains("already exists")
+        );
+        assert!(gen(
+            "gen_config",
+            json!({"cluster_name": "prod", "endpoint": "10.0.0.1"})
+        )
+        .await
+        .is_err());
+        assert_eq!(runner.calls().len(), calls);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn patch_config_validates_and_confirms() {
+        let runner = MockRunner::default();
+        let call = |params: Value| {
+            let params_map = extract_params(Some(&params));
+            let runner = &runner;
+            async move { dispatch_tool("patch_config", &params_map, runner).await }
+        };
+        let patch = r#"[{"op": "add", "path": "/machine/registries", "value": {}}]"#;
+        assert!(call(json!({"node": "10.0.0.1", "patch": "{not json"}))
+            .await
+            .unwrap_err()
+            .to_string()
+            .contains("not valid JSON"));
+        assert!(call(json!({"node": "10.0.0.1", "patch": patch}))
+            .await
+            .unwrap_err()
+            .to_string()
+            .contains("confirm: true"));
+        assert!(runner.calls().is_empty());
+
+        let result = call(json!({"node": "10.0.0.1", "patch": patch, "dry_run": true}))
+            .await
+            .unwrap();
+        assert_eq!(result["dry_run"], true);
+        let args = runner.calls().pop().unwrap();
+        assert_eq!(
+            args[..5],
+            ["--nodes", "10.0.0.1", "patch", "machineconfig", "--patch"]
+        );
+        assert!(args[5].starts_with('@'));
+        assert_eq!(args[6], "--dry-run");
+
+        // The patch may carry secrets, so it is kept out of the audit log
+        let params_map = extract_params(Some(&json!({"node": "10.0.0.1", "patch": patch})));
+        let entry = audit::entry("patch_config", &params_map, &Ok(result));
+        assert_eq!(entry["args"]["patch"], "[REDACTED]");
+        assert_eq!(entry["args"]["node"], "10.0.0.1");
+    }
+
+    #[test]
+    fn json6902_patch_round_trips() {
+        let ops = json!([
+            {"op": "add", "path": "/machine/registries", "value": {"mirrors": {}}},
+            {"path": "/machine/install/wipe", "op": "replace", "value": false},
+            {"op": "remove", "path": "/cluster/proxy", "value": "ignored"},
+            {"op": "move", "from": "/machine/kubelet/extraArgs", "path": "/machine/kubelet/args"}
+        ]);
+        let normalized = json6902_ops(ops.as_array().unwrap()).unwrap();
+        let serialized = Value::Array(normalized).to_string();
+        let parsed: Value = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(parsed[0], ops[0]);
+        assert_eq!(parsed[1], ops[1]);
+        assert_eq!(parsed[2], json!({"op": "remove", "path": "/cluster/proxy"}));
+        assert_eq!(parsed[3], ops[3]);
+
+        for bad in [
+            json!([]),
+            json!([{"op": "add", "path": "/machine/registries"}]),
+            json!([{"op": "replace", "path": "machine", "value": 1}]),
+            json!([{"op": "copy", "path": "/a"}]),
+            json!([{"op": "merge", "path": "/a", "value": 1}]),
+        ] {
+            assert!(json6902_ops(bad.as_array().unwrap()).is_err(), "{}", bad);
+        }
+    }
+
+    #[tokio::test]
+    async fn machine_config_secrets_are_redacted() {
+        let runner = MockRunner::with_output(
+            "node: 10.0.0.1\nmetadata:\n  id: v1alpha1\nspec: |\n  version: v1alpha1\n  machine:\n    type: controlplane\n    token: abcdef.0123456789abcdef\n  cluster:\n    clusterName: prod\n    secret: c2VjcmV0\n  ---\n  apiVersion: v1alpha1\n  kind: HostnameConfig\n  hostname: cp-1\n",
+        );
+        let params_map = extract_params(Some(&json!({"node": "10.0.0.1"})));
+        let result = dispatch_tool("get_machine_config", &params_map, &runner)
+            .await
+            .unwrap();
+        let config = result["config"].as_str().unwrap();
+        assert!(!config.contains("abcdef.0123456789abcdef"));
+        assert!(!config.contains("c2VjcmV0"));
+        assert!(config.contains("clusterName: prod"));
+        assert!(config.contains("hostname: cp-1"));
+        assert_eq!(result["documents"], 2);
+        assert_eq!(result["redactions"], 2);
+
+        let params_map =
+            extract_params(Some(&json!({"node": "10.0.0.1", "redact_secrets": false})));
+        let result = dispatch_tool("get_machine_config", &params_map, &runner)
+            .await
+            .unwrap();
+        assert!(result["config"]
+            .as_str()
+            .unwrap()
+            .contains("abcdef.0123456789abcdef"));
+    }
+
+    #[test]
+    fn processes_table_is_parsed() {
+        let out = "NODE       PID    STATE   THREADS   CPU-TIME   VIRTMEM   RESMEM    LABEL   COMMAND\n\
+                   10.0.0.1   2210   S       18        492.85     11 GB     1.2 GB            /usr/local/bin/kube-apiserver --advertise-address=10.0.0.1\n\
+                   10.0.0.1   1      S       11        3.50       1.4 GB    60 MB             /sbin/init\n";
+        let processes = parse_processes_table(out).unwrap();
+        assert_eq!(processes[0]["pid"], 2210);
+        assert_eq!(processes[0]["threads"], 18);
+        assert_eq!(processes[0]["cpu_time"], 492.85);
+        assert_eq!(processes[0]["res_mem"], "1.2 GB");
+        assert_eq!(processes[0]["label"], "");
+        assert_eq!(
+            processes[0]["command"],
+            "/usr/local/bin/kube-apiserver --advertise-address=10.0.0.1"
+        );
+        assert_eq!(processes[1]["state"], "S");
+        assert!(parse_processes_table("unexpected").is_none());
+    }
+
+    #[test]
+    fn memory_output_is_parsed() {
+        let table = "NODE       TOTAL   USED   FREE   SHARED   BUFFERS   CACHE   AVAILABLE\n\
+                     10.0.0.1   1969    1046   202    8        55        665     777\n";
+        let stats = parse_memory_output(table).unwrap();
+        assert_eq!(stats[0]["node"], "10.0.0.1");
+        assert_eq!(stats[0]["total_bytes"], 1969 * 1024 * 1024);
+        assert_eq!(stats[0]["available_bytes"], 777 * 1024 * 1024);
+
+        let verbose = "NODE: 10.0.0.1\n\
+                       MemTotal:          2000 kB\n\
+                       MemFree:            500 kB\n\
+                       MemAvailable:      1200 kB\n\
+                       Buffers:            100 kB\n\
+                       Cached:             400 kB\n\
+                       SwapCached:           0 kB\n";
+        let stats = parse_memory_output(verbose).unwrap();
+        assert_eq!(stats[0]["node"], "10.0.0.1");
+        assert_eq!(stats[0]["total_bytes"], 2000 * 1024);
+        assert_eq!(stats[0]["cached_bytes"], 400 * 1024);
+        assert_eq!(stats[0]["used_bytes"], 1000 * 1024);
+        assert!(parse_memory_output("unexpected").is_none());
+    }
+
+    #[test]
+    fn pcap_is_cut_at_packet_boundaries() {
+        let mut pcap = vec![0xd4, 0xc3, 0xb2, 0xa1];
+        pcap.extend([0; 20]);
+        for len in [10u32, 20] {
+            pcap.extend([0; 8]);
+            pcap.extend(len.to_le_bytes());
+            pcap.extend(len.to_le_bytes());
+            pcap.extend(vec![0xff; len as usize]);
+        }
+        assert_eq!(pcap_records(&pcap, usize::MAX), Some((pcap.len(), 2)));
+        assert_eq!(pcap_records(&pcap, 60), Some((50, 1)));
+        assert_eq!(pcap_records(b"not a capture at all....", usize::MAX), None);
+    }
+
+    #[tokio::test]
+    async fn capture_packets_args() {
+        let args = talosctl_args(
+            "capture_packets",
+            json!({"node": "10.0.0.1", "filter": "tcp port 6443"}),
+        )
+        .await;
+        for params in [
+            json!({"node": "10.0.0.1", "filter": " "}),
+            json!({"node": "10.0.0.1", "duration": "0s"}),
+        ] {
+            let params_map = extract_params(Some(&params));
+            let runner = MockRunner::default();
+            let result = dispatch_tool("capture_packets", &params_map, &runner).await;
+            assert!(result.is_err());
+            assert!(runner.calls().is_empty());
+        }
+        assert_eq!(
+            args,
+            [
+                "--nodes",
+                "10.0.0.1",
+                "pcap",
+                "--interface",
+                "eth0",
+                "--duration",
+                "10s",
+                "--bpf-filter",
+                "tcp port 6443",
+                "--output",
+                "-"
+            ]
+        );
+    }
+
+    #[test]
+    fn cgroup_tree_is_scoped() {
+        let out = "NAME                 CpuWeight   CpuNice\n\
+                   .                    unset       unset\n\
+                   ├──init              79          1\n\
+                   ├──kubepods          100         0\n\
+                   │   ├──besteffort    1           19\n\
+                   │   └──burstable     40          4\n\
+                   └──system            100         0\n";
+        let scoped = scope_cgroup_tree(out, "kubepods").unwrap();
+        let lines: Vec<&str> = scoped.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("NAME"));
+        assert!(lines[3].contains("burstable"));
+        assert_eq!(
+            scope_cgroup_tree(out, "besteffort")
+                .unwrap()
+                .lines()
+                .count(),
+            2
+        );
+        assert!(scope_cgroup_tree(out, "missing").is_none());
+    }
+
+    #[test]
+    fn image_list_is_parsed() {
+        let out = "NODE       IMAGE                                DIGEST                  SIZE\n\
+                   10.0.0.1   registry.k8s.io/pause:3.10           sha256:ee6521f290b2     320 kB\n\
+                   10.0.0.1   ghcr.io/siderolabs/kubelet:v1.31.1   sha256:07f3cbb84f1e     93 MB\n";
+        let images = parse_image_list(out).unwrap();
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[1]["name"], "ghcr.io/siderolabs/kubelet:v1.31.1");
+        assert_eq!(images[1]["digest"], "sha256:07f3cbb84f1e");
+        assert_eq!(images[0]["size"], "320 kB");
+        assert!(parse_image_list("unexpected").is_none());
+    }
+
+    #[tokio::test]
+    async fn unavailable_talosctl_is_reported_by_tools() {
+        let runner = UnavailableRunner {
+            reason: "TALOSCONFIG env var not set".to_string(),
+        };
+        let params_map = extract_params(Some(&json!({"node": "10.0.0.1"})));
+        let err = dispatch_tool("get_netstat", &params_map, &runner)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("TALOSCONFIG env var not set"));
+    }
 
-// Handle storage and hardware methods
-fn handle_storage_hardware_methods(
-    method: &str,
-    params_map: &HashMap<String, Value>,
-) -> Option<Result<Value>> {
-    match method {
-        "disks" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            let namespace = params_map.get("namespace").and_then(|v| v.as_str());
-            let output_format = params_map
-                .get("output")
-                .and_then(|v| v.as_str())
-                .unwrap_or("table");
+    #[tokio::test]
+    async fn rollback_requires_confirmation() {
+        let runner = MockRunner::default();
+        let params_map = extract_params(Some(&json!({"node": "10.0.0.1"})));
+        let err = dispatch_tool("rollback_node", &params_map, &runner)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("confirm: true"));
+        assert!(runner.calls().is_empty());
 
-            match node {
-                Ok(node) => {
-                    let mut args = vec!["--nodes", node, "get", "disks"];
+        let args = talosctl_args(
+            "rollback_node",
+            json!({"node": "10.0.0.1", "confirm": true}),
+        )
+        .await;
+        assert_eq!(args, ["--nodes", "10.0.0.1", "rollback"]);
+    }
 
-                    if let Some(ns) = namespace {
-                        args.extend(&["--namespace", ns]);
-                    }
+    #[tokio::test]
+    async fn members_are_summarized() {
+        let runner = MockRunner::with_output(
+            r#"{"node": "10.0.0.1", "metadata": {"id": "cp-1"}, "spec": {"nodeId": "x", "addresses": ["10.0.0.1"], "hostname": "cp-1", "machineType": "controlplane", "operatingSystem": "Talos (v1.8.1)"}}
+{"node": "10.0.0.1", "metadata": {"id": "worker-1"}, "spec": {"addresses": ["10.0.0.2", "fd00::2"], "hostname": "worker-1", "machineType": "worker", "operatingSystem": "Talos (v1.8.1)"}}"#,
+        );
+        let params_map = extract_params(Some(&json!({"node": "10.0.0.1"})));
+        let result = dispatch_tool("get_members", &params_map, &runner)
+            .await
+            .unwrap();
+        assert_eq!(result["count"], 2);
+        assert_eq!(result["members"][1]["hostname"], "worker-1");
+        assert_eq!(
+            result["members"][1]["addresses"],
+            json!(["10.0.0.2", "fd00::2"])
+        );
+        assert_eq!(result["members"][0]["machine_type"], "controlplane");
+        assert_eq!(result["members"][0]["os"], "Talos (v1.8.1)");
+    }
 
-                    args.extend(&["--output", output_format]);
+    #[test]
+    fn server_versions_are_parsed() {
+        let out = "Client:\n\tTag:         v1.8.0\n\tSHA:         abc\n\
+                   Server:\n\tNODE:        10.0.0.1\n\tTag:         v1.7.6\n\tSHA:         def\n";
+        let versions = parse_version_output(out);
+        assert_eq!(versions["client"], "Tag:         v1.8.0\nSHA:         abc");
+        assert_eq!(
+            versions["server_versions"],
+            json!([{"node": "10.0.0.1", "version": "v1.7.6"}])
+        );
+    }
 
-                    let output = run_talosctl(&args);
-                    Some(output.map(|out| {
-                        json!({
-                            "disks": out,
-                            "namespace": namespace,
-                            "output_format": output_format
-                        })
-                    }))
-                }
-                Err(e) => Some(Err(e)),
-            }
-        }
-        "list_disks" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            match node {
-                Ok(node) => {
-                    let output = run_talosctl(&["--nodes", node, "list", "/sys/block"]);
-                    Some(output.map(|out| json!({"disks": out})))
-                }
-                Err(e) => Some(Err(e)),
-            }
-        }
-        _ => None,
+    #[tokio::test]
+    async fn time_with_ntp_check() {
+        let args = talosctl_args(
+            "get_time",
+            json!({"node": "10.0.0.1", "check": "pool.ntp.org"}),
+        )
+        .await;
+        assert_eq!(
+            args,
+            ["--nodes", "10.0.0.1", "time", "--check", "pool.ntp.org"]
+        );
     }
-}
 
-// Handle MCP protocol methods
-fn handle_mcp_protocol_methods(
-    method: &str,
-    params_map: &HashMap<String, Value>,
-) -> Option<Result<Value>> {
-    match method {
-        "initialize" => {
-            // MCP initialization - validate required fields and return proper server capabilities
-            let _protocol_version = params_map
-                .get("protocolVersion")
-                .and_then(|v| v.as_str())
-                .unwrap_or("2025-06-18");
+    #[tokio::test]
+    async fn service_action() {
+        let args = talosctl_args(
+            "service",
+            json!({"node": "10.0.0.1", "service": "kubelet", "action": "restart"}),
+        )
+        .await;
+        assert_eq!(
+            args,
+            ["--nodes", "10.0.0.1", "service", "kubelet", "restart"]
+        );
+    }
 
-            // Validate that required fields are present (as per MCP schema)
-            if params_map.get("capabilities").is_none()
-                || params_map.get("clientInfo").is_none()
-                || params_map.get("protocolVersion").is_none()
-            {
-                return Some(Err(anyhow!("Missing required initialize parameters: capabilities, clientInfo, and protocolVersion are required")));
-            }
+    #[tokio::test]
+    async fn events_with_tail() {
+        let args = talosctl_args("get_events", json!({"node": "10.0.0.1", "tail": 5})).await;
+        assert_eq!(args, ["--nodes", "10.0.0.1", "events", "--tail", "5"]);
+    }
 
-            Some(Ok(json!({
-                "protocolVersion": "2025-06-18",
-                "capabilities": {
-                    "tools": {
-                        "listChanged": true
-                    }
-                },
-                "serverInfo": {
-                    "name": "talos-mcp-server",
-                    "title": "Talos OS MCP Server",
-                    "version": "1.0.0"
-                }
-            })))
-        }
-        "notifications/initialized" => {
-            // MCP initialized notification - no response should be sent
-            None
-        }
-        method if method.starts_with("notifications/") => {
-            // Debug: catch any notifications we might be missing
-            None
-        }
-        "ping" => {
-            // MCP ping request - respond with empty object for connection health
-            Some(Ok(json!({})))
-        }
-        "tools/list" => {
-            // Return list of available tools with schemas
-            Some(Ok(get_capabilities()))
-        }
-        _ => None,
+    #[tokio::test]
+    async fn disks_in_namespace() {
+        let args = talosctl_args(
+            "disks",
+            json!({"node": "10.0.0.1", "namespace": "hardware"}),
+        )
+        .await;
+        assert_eq!(
+            args,
+            [
+                "--nodes",
+                "10.0.0.1",
+                "get",
+                "disks",
+                "--namespace",
+                "hardware",
+                "--output",
+                "table"
+            ]
+        );
     }
-}
 
-// Handle tool invocation
-fn handle_tool_invocation(params_map: &HashMap<String, Value>) -> Result<Value> {
-    let name = params_map
-        .get("name")
-        .and_then(|v| v.as_str())
-        .ok_or(anyhow!("Missing tool name"))?;
-    let default_args = json!({});
-    let arguments = params_map.get("arguments").unwrap_or(&default_args);
+    #[tokio::test]
+    async fn reboot_node() {
+        let args = talosctl_args("reboot_node", json!({"node": "10.0.0.1"})).await;
+        assert_eq!(args, ["--nodes", "10.0.0.1", "reboot"]);
 
-    // Extract arguments as a map for the tool handlers
-    let args_map = extract_params(Some(arguments));
+        let args = talosctl_args(
+            "reboot_node",
+            json!({"node": "10.0.0.1", "mode": "powercycle"}),
+        )
+        .await;
+        assert_eq!(
+            args,
+            ["--nodes", "10.0.0.1", "reboot", "--mode", "powercycle"]
+        );
+        let params_map = extract_params(Some(&json!({"node": "10.0.0.1", "mode": "hard"})));
+        let invalid = dispatch_tool("reboot_node", &params_map, &MockRunner::default()).await;
+        assert!(invalid
+            .unwrap_err()
+            .to_string()
+            .contains("Invalid mode 'hard'"));
+    }
 
-    // Try each handler category to find the tool
-    let tool_result = if let Some(result) = handle_system_inspection_methods(name, &args_map) {
-        Some(result)
-    } else if let Some(result) = handle_file_operations_methods(name, &args_map) {
-        Some(result)
-    } else if let Some(result) = handle_network_operations_methods(name, &args_map) {
-        Some(result)
-    } else if let Some(result) = handle_service_log_methods(name, &args_map) {
-        Some(result)
-    } else if let Some(result) = handle_storage_hardware_methods(name, &args_map) {
-        Some(result)
-    } else {
-        let result = handle_core_cluster_methods(name, &args_map);
-        if result.is_some() {
-            result // Core methods can return None
-        } else if let Some(result) = handle_node_management_methods(name, &args_map) {
-            Some(result)
-        } else if let Some(result) = handle_config_etcd_methods(name, &args_map) {
-            Some(result)
-        } else {
-            Some(Err(anyhow!("Unknown tool: {}", name)))
-        }
-    };
+    #[tokio::test]
+    async fn reset_node_options() {
+        let unconfirmed = dispatch_tool(
+            "reset_node",
+            &extract_params(Some(&json!({"node": "10.0.0.1"}))),
+            &MockRunner::default(),
+        )
+        .await;
+        assert!(unconfirmed
+            .unwrap_err()
+            .to_string()
+            .contains("confirm: true"));
 
-    match tool_result {
-        Some(Ok(content)) => Ok(json!({
-            "content": [
-                {
-                    "type": "text",
-                    "text": serde_json::to_string_pretty(&content).unwrap_or_else(|_| content.to_string())
-                }
+        let args = talosctl_args(
+            "reset_node",
+            json!({"node": "10.0.0.1", "confirm": true, "graceful": false, "wipe": ["STATE"], "reboot": true}),
+        )
+        .await;
+        assert_eq!(
+            args,
+            [
+                "--nodes",
+                "10.0.0.1",
+                "reset",
+                "--graceful=false",
+                "--system-labels-to-wipe",
+                "STATE",
+                "--reboot"
             ]
-        })),
-        Some(Err(e)) => Err(e),
-        None => Err(anyhow!("Tool {} returned no response", name)),
+        );
     }
-}
 
-// Handle core cluster monitoring methods
-fn handle_core_cluster_methods(
-    method: &str,
-    params_map: &HashMap<String, Value>,
-) -> Option<Result<Value>> {
-    match method {
-        "tools/call" => Some(handle_tool_invocation(params_map)),
-        "get_version" => {
-            let short = params_map
-                .get("short")
-                .and_then(|v| v.as_bool())
-                .unwrap_or(false);
+    #[tokio::test]
+    async fn upgrade_node() {
+        let args = talosctl_args(
+            "upgrade_node",
+            json!({"node": "10.0.0.1", "image": "ghcr.io/siderolabs/installer:v1.8.0"}),
+        )
+        .await;
+        assert_eq!(
+            args,
+            [
+                "--nodes",
+                "10.0.0.1",
+                "upgrade",
+                "--image",
+                "ghcr.io/siderolabs/installer:v1.8.0"
+            ]
+        );
 
-            let mut args = vec!["version", "--client"];
-            if short {
-                args.push("--short");
-            }
+        let args = talosctl_args(
+            "upgrade_node",
+            json!({"node": "10.0.0.1", "image": "installer:v1.8.0", "preserve": true, "wait": true, "timeout": "10m"}),
+        )
+        .await;
+        assert_eq!(
+            args,
+            [
+                "--nodes",
+                "10.0.0.1",
+                "upgrade",
+                "--image",
+                "installer:v1.8.0",
+                "--preserve",
+                "--wait",
+                "--timeout",
+                "10m"
+            ]
+        );
+    }
 
-            let output = run_talosctl(&args);
-            Some(output.map(|out| {
-                json!({
-                    "version": out,
-                    "short_format": short
-                })
-            }))
+    #[tokio::test]
+    async fn upgrade_k8s() {
+        let args = talosctl_args(
+            "upgrade_k8s",
+            json!({"node": "10.0.0.1", "to": "1.30.1", "dry_run": true}),
+        )
+        .await;
+        assert_eq!(
+            args,
+            [
+                "--nodes",
+                "10.0.0.1",
+                "upgrade-k8s",
+                "--to",
+                "1.30.1",
+                "--dry-run"
+            ]
+        );
+
+        let runner = MockRunner::default();
+        let missing = dispatch_tool("upgrade_k8s", &HashMap::new(), &runner).await;
+        assert!(missing
+            .unwrap_err()
+            .to_string()
+            .contains("Missing to param"));
+        let params_map = extract_params(Some(&json!({"to": "latest"})));
+        let invalid = dispatch_tool("upgrade_k8s", &params_map, &runner).await;
+        assert!(invalid
+            .unwrap_err()
+            .to_string()
+            .contains("Invalid Kubernetes version"));
+    }
+
+    #[tokio::test]
+    async fn apply_config() {
+        let args = talosctl_args(
+            "apply_config",
+            json!({"node": "10.0.0.1", "file": "controlplane.yaml"}),
+        )
+        .await;
+        assert_eq!(
+            args,
+            [
+                "--nodes",
+                "10.0.0.1",
+                "apply-config",
+                "--file",
+                "controlplane.yaml"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn apply_config_try_mode() {
+        let args = talosctl_args(
+            "apply_config",
+            json!({"node": "10.0.0.1", "file": "cp.yaml", "mode": "try", "timeout": "2m"}),
+        )
+        .await;
+        assert_eq!(
+            args,
+            [
+                "--nodes",
+                "10.0.0.1",
+                "apply-config",
+                "--file",
+                "cp.yaml",
+                "--mode",
+                "try",
+                "--timeout",
+                "2m"
+            ]
+        );
+
+        let params_map = extract_params(Some(
+            &json!({"node": "10.0.0.1", "file": "cp.yaml", "mode": "staged", "timeout": "2m"}),
+        ));
+        let err = dispatch_tool("apply_config", &params_map, &MockRunner::default())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("only applies to mode 'try'"));
+    }
+
+    #[tokio::test]
+    async fn upgrade_dry_run() {
+        let args = talosctl_args(
+            "upgrade_node",
+            json!({"node": "10.0.0.1", "image": "ghcr.io/siderolabs/installer:v1.7.0", "dry_run": true}),
+        )
+        .await;
+        assert_eq!(args.last().map(String::as_str), Some("--dry-run"));
+    }
+
+    #[tokio::test]
+    async fn apply_config_inline_content_is_staged_and_removed() {
+        let args = talosctl_args(
+            "apply_config",
+            json!({"node": "10.0.0.1", "content": "version: v1alpha1\n"}),
+        )
+        .await;
+        assert_eq!(args[2..4], ["apply-config", "--file"]);
+        assert!(!std::path::Path::new(&args[4]).exists());
+    }
+
+    #[tokio::test]
+    async fn etcd_members_checks_machine_type_first() {
+        let calls = talosctl_calls("get_etcd_members", json!({"node": "10.0.0.1"})).await;
+        assert_eq!(
+            calls,
+            [
+                vec![
+                    "--nodes",
+                    "10.0.0.1",
+                    "get",
+                    "machinetype",
+                    "--output",
+                    "json"
+                ],
+                vec!["--nodes", "10.0.0.1", "etcd", "members"],
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn etcd_members_without_strict_skips_preflight() {
+        let calls = talosctl_calls(
+            "get_etcd_members",
+            json!({"node": "10.0.0.1", "strict": false}),
+        )
+        .await;
+        assert_eq!(calls, [vec!["--nodes", "10.0.0.1", "etcd", "members"]]);
+    }
+
+    #[tokio::test]
+    async fn etcd_refused_on_worker() {
+        let runner =
+            MockRunner::with_output(r#"{"metadata": {"id": "machine-type"}, "spec": "worker"}"#);
+        let params_map = extract_params(Some(&json!({"node": "10.0.0.2"})));
+        let err = dispatch_tool("defrag_etcd", &params_map, &runner)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("is a worker"));
+        assert_eq!(runner.calls().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn usage_across_paths_ranks_largest() {
+        let runner = MockRunner::with_output(
+            "NODE       NAME        SIZE\n\
+             10.0.0.1   /var/log    2048\n\
+             10.0.0.1   /var/lib    8192\n",
+        );
+        let params_map = extract_params(Some(&json!({
+            "node": "10.0.0.1",
+            "paths": ["/var/log", "/var/lib"]
+        })));
+        let result = dispatch_tool("get_usage", &params_map, &runner)
+            .await
+            .unwrap();
+        let entries = &result["paths"]["/var/log"]["entries"];
+        assert_eq!(entries[0], json!({"path": "/var/log", "size_bytes": 2048}));
+        assert_eq!(result["largest"][0]["size_bytes"], 8192);
+        assert_eq!(result["largest"].as_array().map(Vec::len), Some(4));
+        assert_eq!(runner.calls().len(), 2);
+
+        let params_map = extract_params(Some(&json!({"node": "10.0.0.1", "path": "/var"})));
+        let result = dispatch_tool("get_usage", &params_map, &runner)
+            .await
+            .unwrap();
+        assert_eq!(result["entries"][1]["path"], "/var/lib");
+    }
+
+    #[tokio::test]
+    async fn k8s_version_reads_component_images() {
+        let runner = MockRunner::with_output(
+            r#"{"node": "10.0.0.1", "metadata": {"id": "kubelet"}, "spec": {"image": "ghcr.io/siderolabs/kubelet:v1.31.1"}}"#,
+        );
+        let params_map = extract_params(Some(&json!({"node": "10.0.0.1"})));
+        let result = dispatch_tool("get_k8s_version", &params_map, &runner)
+            .await
+            .unwrap();
+        assert_eq!(result["kubelet_version"], "v1.31.1");
+        assert_eq!(result["control_plane_version"], "v1.31.1");
+        assert_eq!(result["components"]["kube-scheduler"], "v1.31.1");
+        assert_eq!(result["uniform"], true);
+        let mut calls = runner.calls();
+        calls.sort();
+        assert_eq!(
+            calls[0],
+            [
+                "--nodes",
+                "10.0.0.1",
+                "get",
+                "apiserverconfig",
+                "--output",
+                "json"
+            ]
+        );
+
+        // A worker has no control plane components, only its kubelet
+        let runner = MockRunner {
+            output: runner.output.clone(),
+            fail_for: vec![
+                "apiserverconfig".to_string(),
+                "controllermanagerconfig".to_string(),
+                "schedulerconfig".to_string(),
+            ],
+            ..Default::default()
+        };
+        let result = dispatch_tool("get_k8s_version", &params_map, &runner)
+            .await
+            .unwrap();
+        assert_eq!(result["kubelet_version"], "v1.31.1");
+        assert!(result["control_plane_version"].is_null());
+        assert_eq!(result["errors"].as_object().map(|e| e.len()), Some(3));
+    }
+
+    #[tokio::test]
+    async fn bootstrap_refuses_a_bootstrapped_cluster() {
+        let runner = MockRunner::with_output(
+            "NODE       MEMBER             DB SIZE   IN USE   LEADER             RAFT INDEX\n\
+             10.0.0.1   8a9cf4ac3e6f1f8b   20 MB     12 MB    8a9cf4ac3e6f1f8b   1234\n",
+        );
+        let params_map = extract_params(Some(&json!({"node": "10.0.0.1"})));
+        let err = dispatch_tool("bootstrap_etcd", &params_map, &runner)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("already bootstrapped"));
+        assert!(!runner
+            .calls()
+            .iter()
+            .any(|call| call.contains(&"bootstrap".to_string())));
+
+        let params_map = extract_params(Some(&json!({"node": "10.0.0.1", "force": true})));
+        let calls =
+            talosctl_calls("bootstrap_etcd", json!({"node": "10.0.0.1", "force": true})).await;
+        assert_eq!(calls.last().unwrap(), &["--nodes", "10.0.0.1", "bootstrap"]);
+        assert!(dispatch_tool("bootstrap_etcd", &params_map, &runner)
+            .await
+            .is_ok());
+
+        // A fresh node's etcd status fails, so bootstrap goes ahead
+        let runner = MockRunner {
+            fail_for: vec!["status".to_string()],
+            ..Default::default()
+        };
+        let params_map = extract_params(Some(&json!({"node": "10.0.0.1"})));
+        assert!(dispatch_tool("bootstrap_etcd", &params_map, &runner)
+            .await
+            .is_ok());
+        assert_eq!(
+            runner.calls().last().unwrap(),
+            &["--nodes", "10.0.0.1", "bootstrap"]
+        );
+    }
+
+    #[tokio::test]
+    async fn wipe_disk_validates_and_confirms() {
+        let args = talosctl_args(
+            "wipe_disk",
+            json!({"node": "10.0.0.1", "device": "/dev/sdb", "confirm": true}),
+        )
+        .await;
+        assert_eq!(args, ["--nodes", "10.0.0.1", "wipe", "disk", "sdb"]);
+
+        let runner = MockRunner::default();
+        for params in [
+            json!({"node": "10.0.0.1", "device": "/dev/sdb"}),
+            json!({"node": "10.0.0.1", "device": "/dev/../etc/passwd", "confirm": true}),
+            json!({"node": "10.0.0.1", "device": "sdb; reboot", "confirm": true}),
+            json!({"node": "10.0.0.1", "device": "sdb", "method": "shred", "confirm": true}),
+        ] {
+            let params_map = extract_params(Some(&params));
+            assert!(dispatch_tool("wipe_disk", &params_map, &runner)
+                .await
+                .is_err());
         }
-        "get_time" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .filter(|s| !s.is_empty());
-            let check = params_map.get("check").and_then(|v| v.as_str());
+        assert!(runner.calls().is_empty());
+        assert_eq!(block_device_name("nvme0n1p2").unwrap(), "nvme0n1p2");
+    }
 
-            // Time command requires a node to be specified
-            let target_node = match node {
-                Some(n) => n,
-                None => {
-                    return Some(Err(anyhow!("Time command requires a node to be specified. Please provide a node parameter.")));
-                }
-            };
+    #[tokio::test]
+    async fn events_are_parsed_and_filtered() {
+        let runner = MockRunner::with_output(
+            "NODE       ID                     EVENT                       ACTOR   SOURCE   MESSAGE\n\
+             10.0.0.1   cq4g4e8ble1jg7ti2a8g   runtime.SequenceEvent               boot     reboot\n\
+             10.0.0.1   cq4g4e8ble1jg7ti2a90   runtime.ServiceStateEvent           apid     Running\n\
+             10.0.0.1   cq4g4e8ble1jg7ti2a9g   runtime.ServiceStateEvent           etcd     Running\n",
+        );
+        let params_map = extract_params(Some(&json!({
+            "node": "10.0.0.1",
+            "type": "servicestateevent",
+            "tail": 1
+        })));
+        let result = dispatch_tool("get_events", &params_map, &runner)
+            .await
+            .unwrap();
+        assert_eq!(result["count"], 1);
+        assert_eq!(result["events"][0]["source"], "etcd");
+        assert_eq!(
+            result["events"][0]["timestamp"],
+            "2024-07-06T08:37:13+00:00"
+        );
+        assert_eq!(
+            runner.calls(),
+            [["--nodes", "10.0.0.1", "events", "--tail", "-1"]]
+        );
 
-            let mut args = vec!["--nodes", target_node, "time"];
+        let args = talosctl_args("get_events", json!({"node": "10.0.0.1", "since": "15m"})).await;
+        assert_eq!(args[3..], ["--duration", "900s"]);
+    }
 
-            if let Some(ntp_server) = check {
-                args.extend(&["--check", ntp_server]);
-            }
+    #[tokio::test]
+    async fn metrics_count_calls_and_latency() {
+        // No other test calls get_metrics, so its counters are this test's alone
+        metrics::record("get_metrics", Duration::from_millis(5), true);
+        metrics::record("get_metrics", Duration::from_millis(700), false);
+        metrics::record("no_such_tool", Duration::from_millis(1), true);
+        let runner = MockRunner::default();
+        let snapshot = dispatch_tool("get_metrics", &HashMap::new(), &runner)
+            .await
+            .unwrap();
+        let stats = &snapshot["tools"]["get_metrics"];
+        assert_eq!(stats["calls"], 2);
+        assert_eq!(stats["errors"], 1);
+        assert_eq!(stats["error_rate"], 0.5);
+        assert_eq!(stats["p50_ms"], 10);
+        assert_eq!(stats["p99_ms"], 1000);
+        assert!(snapshot["tools"].get("no_such_tool").is_none());
+    }
 
-            let output = run_talosctl(&args);
-            Some(output.map(|out| {
-                json!({
-                    "time": out,
-                    "node": target_node,
-                    "ntp_check": check
-                })
-            }))
+    #[tokio::test]
+    async fn explain_reports_commands_without_running_them() {
+        let runner = MockRunner::default();
+        let params_map = extract_params(Some(&json!({
+            "name": "reboot_node",
+            "arguments": {"node": "10.0.0.1", "explain": true}
+        })));
+        let result = handle_tool_invocation(&params_map, &runner).await.unwrap();
+        let text = result["content"][0]["text"].as_str().unwrap();
+        let explained: Value = serde_json::from_str(text).unwrap();
+        assert_eq!(
+            explained["commands"],
+            json!([["--nodes", "10.0.0.1", "reboot"]])
+        );
+        assert_eq!(
+            explained["global_args"],
+            json!(["--talosconfig", "[REDACTED]"])
+        );
+        assert!(runner.calls().is_empty());
+
+        // The confirmation gate is skipped, but invalid params still fail
+        let params_map = extract_params(Some(&json!({
+            "name": "reset_node",
+            "arguments": {"node": "10.0.0.1", "explain": true}
+        })));
+        assert!(handle_tool_invocation(&params_map, &runner).await.is_ok());
+        let params_map = extract_params(Some(&json!({
+            "name": "reboot_node",
+            "arguments": {"node": "10.0.0.1", "mode": "bogus", "explain": true}
+        })));
+        assert!(handle_tool_invocation(&params_map, &runner).await.is_err());
+        assert!(runner.calls().is_empty());
+
+        // Local artifacts aren't created either
+        let destination = format!("explain-{}/etc.tar", std::process::id());
+        let params_map = extract_params(Some(&json!({
+            "node": "10.0.0.1",
+            "source": "/etc",
+            "destination": destination,
+            "direction": "from_node",
+            "explain": true
+        })));
+        let explained = call_tool("copy", &params_map, &runner).await.unwrap();
+        assert_eq!(
+            explained["commands"],
+            json!([["--nodes", "10.0.0.1", "copy", "/etc", "-"]])
+        );
+        let dest = workdir::work_dir().unwrap().join(&destination);
+        assert!(!dest.parent().unwrap().exists());
+    }
+
+    #[tokio::test]
+    async fn explain_skips_quorum_protection() {
+        // Quorum protection is switched on through the environment, which every test
+        // shares, so the checks run in a child test process that has it set
+        if env::var_os("TALOS_PROTECT_QUORUM").is_none() {
+            let status = std::process::Command::new(env::current_exe().unwrap())
+                .args(["--exact", "tests::explain_skips_quorum_protection"])
+                .env("TALOS_PROTECT_QUORUM", "1")
+                .stdout(Stdio::null())
+                .status()
+                .unwrap();
+            assert!(status.success());
+            return;
         }
-        "get_health" => {
-            let control_planes = params_map
-                .get("control_planes")
-                .and_then(|v| v.as_array())
-                .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>())
-                .unwrap_or(vec!["192.168.1.77"]);
 
-            let worker_nodes = params_map
-                .get("worker_nodes")
-                .and_then(|v| v.as_array())
-                .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>());
+        // The mock cluster has no other healthy etcd member, so a real reboot is refused
+        let runner = MockRunner::default();
+        let params_map = extract_params(Some(&json!({"node": "10.0.0.1"})));
+        let err = dispatch_tool("reboot_node", &params_map, &runner)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Quorum protection"));
 
-            let init_node = params_map.get("init_node").and_then(|v| v.as_str());
-            let timeout = params_map
-                .get("timeout")
-                .and_then(|v| v.as_str())
-                .unwrap_or("120s");
-            let run_e2e = params_map
-                .get("run_e2e")
-                .and_then(|v| v.as_bool())
-                .unwrap_or(false);
-            let k8s_endpoint = params_map.get("k8s_endpoint").and_then(|v| v.as_str());
-            let server = params_map
-                .get("server")
-                .and_then(|v| v.as_bool())
-                .unwrap_or(true);
+        let params_map = extract_params(Some(&json!({"node": "10.0.0.1", "explain": true})));
+        let explained = call_tool("reboot_node", &params_map, &runner)
+            .await
+            .unwrap();
+        assert_eq!(
+            explained["commands"],
+            json!([["--nodes", "10.0.0.1", "reboot"]])
+        );
+    }
 
-            if control_planes.is_empty() {
-                return Some(Err(anyhow!(
-                    "At least one control plane node must be specified"
-                )));
-            }
+    #[tokio::test]
+    async fn logs_for_several_services() {
+        let runner = MockRunner::with_output("line one\nline two\n");
+        let params_map = extract_params(Some(&json!({
+            "node": "10.0.0.1",
+            "services": ["etcd", "kubelet"],
+            "tail": 5
+        })));
+        let result = dispatch_tool("get_logs", &params_map, &runner)
+            .await
+            .unwrap();
+        assert_eq!(result["logs"]["etcd"]["service"], "etcd");
+        assert_eq!(result["logs"]["kubelet"]["logs"], "line one\nline two\n");
+        let mut calls = runner.calls();
+        calls.sort();
+        assert_eq!(
+            calls,
+            [
+                ["--nodes", "10.0.0.1", "logs", "etcd", "--tail", "5"],
+                ["--nodes", "10.0.0.1", "logs", "kubelet", "--tail", "5"]
+            ]
+        );
 
-            // Prepare string values that need to live for the entire function
-            let control_planes_str = control_planes.join(",");
-            let workers_str = worker_nodes.as_ref().map(|w| w.join(","));
+        let params_map = extract_params(Some(&json!({
+            "node": "10.0.0.1",
+            "service": "etcd",
+            "services": ["kubelet"]
+        })));
+        assert!(dispatch_tool("get_logs", &params_map, &runner)
+            .await
+            .is_err());
+    }
 
-            // Build command arguments dynamically
-            let mut args = Vec::new();
+    #[tokio::test]
+    async fn jsonpath_output_takes_an_expression() {
+        let args = talosctl_args(
+            "interfaces",
+            json!({"node": "10.0.0.1", "output": "jsonpath", "jsonpath": "{.spec.address}"}),
+        )
+        .await;
+        assert_eq!(
+            args,
+            [
+                "--nodes",
+                "10.0.0.1",
+                "get",
+                "addresses",
+                "--output",
+                "jsonpath={.spec.address}"
+            ]
+        );
 
-            // Always specify the first control plane node for --nodes
-            args.extend(&["--nodes", control_planes[0]]);
+        let runner = MockRunner::default();
+        for params in [
+            json!({"node": "10.0.0.1", "output": "jsonpath"}),
+            json!({"node": "10.0.0.1", "output": "json", "jsonpath": "{.spec}"}),
+        ] {
+            let params_map = extract_params(Some(&params));
+            assert!(dispatch_tool("routes", &params_map, &runner).await.is_err());
+        }
+        assert!(runner.calls().is_empty());
+    }
 
-            // Add the health command
-            args.push("health");
+    #[tokio::test]
+    async fn resource_output_is_structured() {
+        let runner = MockRunner::with_output(
+            "{\"metadata\": {\"id\": \"a\"}, \"spec\": {}}\n{\"metadata\": {\"id\": \"b\"}, \"spec\": {}}",
+        );
+        let params_map = extract_params(Some(&json!({"node": "10.0.0.1", "output": "json"})));
+        let result = dispatch_tool("routes", &params_map, &runner).await.unwrap();
+        assert_eq!(result["routes"].as_array().map(Vec::len), Some(2));
+        assert_eq!(result["routes"][1]["metadata"]["id"], "b");
 
-            // Add control plane nodes
-            args.extend(&["--control-plane-nodes", &control_planes_str]);
+        let runner = MockRunner::with_output("not json");
+        let result = dispatch_tool("routes", &params_map, &runner).await.unwrap();
+        assert_eq!(result["routes"], "not json");
+        assert!(result["parse_error"].is_string());
 
-            // Add worker nodes if specified
-            if let Some(ref workers_string) = workers_str {
-                args.extend(&["--worker-nodes", workers_string]);
-            }
+        let runner = MockRunner::with_output(
+            "node: 10.0.0.1\nmetadata:\n  id: sda\nspec:\n  size: 100\n---\n\
+             node: 10.0.0.1\nmetadata:\n  id: sdb\nspec:\n  size: 200\n",
+        );
+        let params_map = extract_params(Some(&json!({"node": "10.0.0.1", "output": "yaml"})));
+        let result = dispatch_tool("disks", &params_map, &runner).await.unwrap();
+        assert_eq!(result["disks"][1]["metadata"]["id"], "sdb");
+        assert_eq!(result["disks"][0]["spec"]["size"], 100);
+    }
 
-            // Add init node if specified
-            if let Some(init) = init_node {
-                args.extend(&["--init-node", init]);
-            }
+    #[tokio::test]
+    async fn kubeconfig_to_path_without_merge() {
+        let args = talosctl_args(
+            "get_kubeconfig",
+            json!({"node": "10.0.0.1", "path": "kubeconfig", "merge": false, "force": true}),
+        )
+        .await;
+        let dest = workdir::work_dir().unwrap().join("kubeconfig");
+        assert_eq!(
+            args,
+            [
+                "--nodes",
+                "10.0.0.1",
+                "kubeconfig",
+                &dest.to_string_lossy(),
+                "--merge=false",
+                "--force"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn local_destinations_stay_in_the_work_dir() {
+        let work = workdir::work_dir().unwrap();
+        assert_eq!(
+            workdir::artifact_path("copies/etc.tar").unwrap(),
+            work.join("copies/etc.tar")
+        );
+        let inside = work.join("copies/hosts.tar");
+        assert_eq!(
+            workdir::artifact_path(&inside.to_string_lossy()).unwrap(),
+            inside
+        );
+        for path in ["../escape.tar", "copies/../../escape.tar"] {
+            assert!(workdir::artifact_path(path).is_err(), "{}", path);
+        }
+        let climbing = format!("{}/../escape.tar", work.display());
+        assert!(workdir::artifact_path(&climbing).is_err());
+
+        let runner = MockRunner::default();
+        let params_map = extract_params(Some(&json!({
+            "node": "10.0.0.1",
+            "source": "/etc/hosts",
+            "destination": "/tmp/x",
+            "direction": "from_node"
+        })));
+        let err = dispatch_tool("copy", &params_map, &runner)
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("must stay inside the work directory"));
+        assert!(runner.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn get_resource_by_id() {
+        let args = talosctl_args(
+            "get_resource",
+            json!({"node": "10.0.0.1", "resource": "members", "id": "cp-1", "namespace": "cluster"}),
+        )
+        .await;
+        assert_eq!(
+            args,
+            [
+                "--nodes",
+                "10.0.0.1",
+                "get",
+                "members",
+                "cp-1",
+                "--namespace",
+                "cluster",
+                "--output",
+                "table"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn get_resource_rejects_flag_like_names() {
+        let params_map = extract_params(Some(
+            &json!({"node": "10.0.0.1", "resource": "--talosconfig"}),
+        ));
+        let runner = MockRunner::default();
+        let err = dispatch_tool("get_resource", &params_map, &runner)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid resource"));
+        assert!(runner.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn compare_resource_rejects_flag_like_names() {
+        let runner = MockRunner::default();
+        for params in [
+            json!({"nodes": ["10.0.0.1", "10.0.0.2"], "resource": "--talosconfig=/tmp/evil"}),
+            json!({"nodes": ["10.0.0.1", "10.0.0.2"], "resource": "members", "id": "--endpoints=10.9.9.9"}),
+        ] {
+            let params_map = extract_params(Some(&params));
+            let err = dispatch_tool("compare_resource", &params_map, &runner)
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<RpcFailure>(),
+                Some(RpcFailure::InvalidParams(_))
+            ));
+        }
+        assert!(runner.calls().is_empty());
+    }
 
-            // Add timeout
-            args.extend(&["--wait-timeout", timeout]);
+    #[tokio::test]
+    async fn etcd_restore_requires_confirmation() {
+        let runner = MockRunner::with_output(
+            r#"{"metadata": {"id": "machine-type"}, "spec": "controlplane"}"#,
+        );
+        let params_map = extract_params(Some(
+            &json!({"node": "10.0.0.1", "path": "/tmp/etcd.snapshot"}),
+        ));
+        let err = dispatch_tool("etcd_restore", &params_map, &runner)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("confirm: true"));
+        assert!(!runner
+            .calls()
+            .iter()
+            .any(|args| args.contains(&"bootstrap".to_string())));
+    }
 
-            // Add run-e2e flag if true
-            if run_e2e {
-                args.push("--run-e2e");
-            }
+    // Serve the given input lines against a mock runner and return every message written.
+    async fn serve_lines(input: &str) -> Vec<Value> {
+        let (writer, mut reader) = tokio::io::duplex(64 * 1024);
+        let stdout: SharedStdout = Arc::new(Mutex::new(Box::new(writer)));
+        let shutdown = std::future::pending();
+        serve(
+            input.as_bytes(),
+            stdout,
+            Arc::new(MockRunner::default()),
+            shutdown,
+        )
+        .await
+        .unwrap();
 
-            // Add k8s endpoint if specified
-            if let Some(endpoint) = k8s_endpoint {
-                args.extend(&["--k8s-endpoint", endpoint]);
-            }
+        let mut output = String::new();
+        reader.read_to_string(&mut output).await.unwrap();
+        output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
 
-            // Add server flag (note: --server is default true, --no-server to disable)
-            if !server {
-                args.push("--server=false");
-            }
+    #[tokio::test]
+    async fn malformed_line_does_not_end_session() {
+        let responses = serve_lines(concat!(
+            "not json\n",
+            r#"{"jsonrpc": "2.0"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 7, "method": "ping"}"#,
+            "\n"
+        ))
+        .await;
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0]["error"]["code"], PARSE_ERROR);
+        assert_eq!(responses[0]["id"], Value::Null);
+        assert_eq!(responses[1]["error"]["code"], INVALID_REQUEST);
+        assert_eq!(responses[2]["id"], 7);
+        assert!(responses[2]["result"].is_object());
+    }
 
-            let output = run_talosctl_with_stderr(&args);
-            match output {
-                Ok(out) => Some(Ok(json!({
-                    "health": out,
-                    "cluster_info": {
-                        "control_planes": control_planes,
-                        "worker_nodes": worker_nodes,
-                        "init_node": init_node,
-                        "timeout": timeout,
-                        "run_e2e": run_e2e,
-                        "k8s_endpoint": k8s_endpoint,
-                        "server_side": server
-                    }
-                }))),
-                Err(e) => Some(Err(anyhow!("Health check failed: {}", e))),
-            }
-        }
-        "get_logs" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            let service = params_map
-                .get("service")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing service param"));
-            let tail = params_map.get("tail").and_then(|v| v.as_i64());
-            let kubernetes = params_map
-                .get("kubernetes")
-                .and_then(|v| v.as_bool())
-                .unwrap_or(false);
-            match (node, service) {
-                (Ok(node), Ok(service)) => {
-                    let mut args = vec!["--nodes", node, "logs", service];
+    #[tokio::test]
+    async fn cancelled_request_is_aborted_without_response() {
+        let (writer, mut reader) = tokio::io::duplex(64 * 1024);
+        let stdout: SharedStdout = Arc::new(Mutex::new(Box::new(writer)));
+        let runner = MockRunner {
+            delay: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let input = concat!(
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": {"name": "get_netstat", "arguments": {"node": "10.0.0.1"}}}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "method": "notifications/cancelled", "params": {"requestId": 1}}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "ping"}"#,
+            "\n"
+        );
+        let served = serve(
+            input.as_bytes(),
+            stdout,
+            Arc::new(runner),
+            std::future::pending(),
+        );
+        tokio::time::timeout(Duration::from_secs(5), served)
+            .await
+            .expect("cancelled request still running")
+            .unwrap();
 
-                    let tail_str = tail.map(|t| t.to_string());
-                    if let Some(ref tail_count) = tail_str {
-                        args.extend(&["--tail", tail_count]);
-                    }
+        let mut output = String::new();
+        reader.read_to_string(&mut output).await.unwrap();
+        let responses: Vec<Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0]["id"], 2);
+    }
 
-                    if kubernetes {
-                        args.push("--kubernetes");
-                    }
+    #[test]
+    fn progress_is_reported_for_slow_tools_with_a_token() {
+        let call = |name: &str, meta: Value| json!({"name": name, "arguments": {"node": "10.0.0.1"}, "_meta": meta});
+        let token = streaming::progress_token(
+            "tools/call",
+            Some(&call("upgrade_node", json!({"progressToken": "t1"}))),
+        );
+        assert_eq!(token, Some(json!("t1")));
+        let quick = call("get_netstat", json!({"progressToken": "t2"}));
+        assert_eq!(streaming::progress_token("tools/call", Some(&quick)), None);
+        let untracked = call("get_health", json!({}));
+        assert_eq!(
+            streaming::progress_token("tools/call", Some(&untracked)),
+            None
+        );
+    }
 
-                    let output = run_talosctl(&args);
-                    Some(output.map(|out| {
-                        json!({
-                            "logs": out,
-                            "service": service,
-                            "tail_lines": tail,
-                            "namespace": if kubernetes { "k8s.io" } else { "system" }
-                        })
-                    }))
-                }
-                (Err(e), _) | (_, Err(e)) => Some(Err(e)),
-            }
+    #[tokio::test]
+    async fn health_needs_a_control_plane() {
+        // Without TALOS_DEFAULT_NODE there is no node to fall back to
+        if nodes::default_node().is_some() {
+            return;
         }
-        _ => None,
+        let runner = MockRunner::default();
+        let err = dispatch_tool("get_health", &HashMap::new(), &runner)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Missing control_planes param"));
+        assert!(runner.calls().is_empty());
     }
-}
 
-// Handle node management methods
-fn handle_node_management_methods(
-    method: &str,
-    params_map: &HashMap<String, Value>,
-) -> Option<Result<Value>> {
-    match method {
-        "reboot_node" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            match node {
-                Ok(node) => {
-                    let output = run_talosctl(&["--nodes", node, "reboot"]);
-                    Some(output.map(|_| json!({"status": "reboot initiated"})))
-                }
-                Err(e) => Some(Err(e)),
-            }
-        }
-        "shutdown_node" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            match node {
-                Ok(node) => {
-                    let output = run_talosctl(&["--nodes", node, "shutdown"]);
-                    Some(output.map(|_| json!({"status": "node shutdown initiated"})))
-                }
-                Err(e) => Some(Err(e)),
-            }
-        }
-        "reset_node" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            match node {
-                Ok(node) => {
-                    let output = run_talosctl(&["--nodes", node, "reset"]);
-                    Some(output.map(|_| json!({"status": "node reset initiated"})))
-                }
-                Err(e) => Some(Err(e)),
-            }
-        }
-        "upgrade_node" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            let image = params_map
-                .get("image")
-                .and_then(|v| v.as_str())
-                .unwrap_or("ghcr.io/siderolabs/installer:latest");
-            match node {
-                Ok(node) => {
-                    let output = run_talosctl(&["--nodes", node, "upgrade", "--image", image]);
-                    Some(output.map(|_| json!({"status": "upgrade initiated"})))
-                }
-                Err(e) => Some(Err(e)),
-            }
-        }
-        "upgrade_k8s" => {
-            let from = params_map
-                .get("from")
-                .and_then(|v| v.as_str())
-                .unwrap_or("1.28.0");
-            let to = params_map
-                .get("to")
-                .and_then(|v| v.as_str())
-                .unwrap_or("1.29.0");
-            let output = run_talosctl(&["upgrade-k8s", "--from", from, "--to", to]);
-            Some(output.map(|_| json!({"status": "k8s upgrade initiated"})))
-        }
-        _ => None,
+    #[tokio::test]
+    async fn batch_requests() {
+        let responses = serve_lines(concat!(
+            r#"[{"jsonrpc": "2.0", "id": 1, "method": "ping"},"#,
+            r#" {"jsonrpc": "2.0", "method": "notifications/initialized"},"#,
+            r#" {"jsonrpc": "2.0", "id": 2, "method": "no_such_method"}]"#,
+            "\n[]\n"
+        ))
+        .await;
+        assert_eq!(responses.len(), 2);
+        let batch = responses.iter().find(|r| r.is_array()).unwrap();
+        assert_eq!(batch.as_array().map(Vec::len), Some(2));
+        assert_eq!(batch[0]["id"], 1);
+        assert_eq!(batch[1]["error"]["code"], METHOD_NOT_FOUND);
+        let empty = responses.iter().find(|r| r.is_object()).unwrap();
+        assert_eq!(empty["error"]["code"], INVALID_REQUEST);
     }
-}
 
-// Handle configuration and etcd methods
-fn handle_config_etcd_methods(
-    method: &str,
-    params_map: &HashMap<String, Value>,
-) -> Option<Result<Value>> {
-    match method {
-        "apply_config" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            let file = params_map
-                .get("file")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing file param"));
-            match (node, file) {
-                (Ok(node), Ok(file)) => {
-                    let output = run_talosctl(&["--nodes", node, "apply-config", "--file", file]);
-                    Some(output.map(|_| json!({"status": "config applied"})))
-                }
-                (Err(e), _) | (_, Err(e)) => Some(Err(e)),
-            }
+    #[tokio::test]
+    async fn canonical_and_legacy_tool_names_resolve() {
+        let responses = serve_lines(concat!(
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": {"name": "talos_list", "arguments": {"node": "10.0.0.1"}}}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": {"name": "list", "arguments": {"node": "10.0.0.1"}}}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "talos_list", "params": {"node": "10.0.0.1"}}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 4, "method": "tools/call", "params": {"name": "talos_nonexistent"}}"#,
+            "\n",
+        ))
+        .await;
+        let response = |id: i64| {
+            responses
+                .iter()
+                .find(|r| r["id"] == id)
+                .map(Value::to_string)
+                .unwrap()
+        };
+        for id in 1..=2 {
+            assert!(!response(id).contains("Unknown tool"), "{}", response(id));
         }
-        "validate_config" => {
-            let config = params_map
-                .get("config")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing config param"));
-            let mode = params_map
-                .get("mode")
-                .and_then(|v| v.as_str())
-                .unwrap_or("container");
-            match config {
-                Ok(config) => {
-                    let output = run_talosctl(&["validate", "--config", config, "--mode", mode]);
-                    Some(output.map(|out| json!({"validation": out})))
-                }
-                Err(e) => Some(Err(e)),
-            }
-        }
-        "get_etcd_status" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            match node {
-                Ok(node) => {
-                    let output = run_talosctl(&["--nodes", node, "etcd", "status"]);
-                    Some(output.map(|out| json!({"etcd_status": out})))
+        // Tools aren't methods of their own unless TALOS_DIRECT_TOOL_CALLS is set
+        assert!(response(3).contains("Unknown method"));
+        assert!(response(4).contains("Unknown tool"));
+
+        let capabilities = get_capabilities();
+        let tools = capabilities["tools"].as_array().unwrap();
+        assert!(tools.iter().all(|tool| tool["name"]
+            .as_str()
+            .is_some_and(|name| name.starts_with(tools::TOOL_NAME_PREFIX))));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn socket_sessions_require_the_token() {
+        let path = env::temp_dir().join(format!("talos-mcp-test-{}.sock", std::process::id()));
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(transport::listen(
+            transport::Listen::Unix(path.clone()),
+            "s3cret".to_string(),
+            Arc::new(MockRunner::default()),
+            async {
+                let _ = stopped.await;
+                "SIGTERM"
+            },
+        ));
+        let connect = || async {
+            for _ in 0..50 {
+                if let Ok(stream) = tokio::net::UnixStream::connect(&path).await {
+                    return stream;
                 }
-                Err(e) => Some(Err(e)),
+                tokio::time::sleep(Duration::from_millis(20)).await;
             }
-        }
-        "get_etcd_members" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            match node {
-                Ok(node) => {
-                    let output = run_talosctl(&["--nodes", node, "etcd", "members"]);
-                    Some(output.map(|out| json!({"etcd_members": out})))
-                }
-                Err(e) => Some(Err(e)),
+            panic!("server never listened");
+        };
+
+        let mut client = BufReader::new(connect().await);
+        client
+            .write_all(b"s3cret\n{\"jsonrpc\": \"2.0\", \"id\": 1, \"method\": \"ping\"}\n")
+            .await
+            .unwrap();
+        let mut line = String::new();
+        client.read_line(&mut line).await.unwrap();
+        let response: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(response["id"], 1);
+
+        let mut intruder = BufReader::new(connect().await);
+        intruder.write_all(b"guess\n").await.unwrap();
+        let mut rejection = String::new();
+        intruder.read_to_string(&mut rejection).await.unwrap();
+        assert!(rejection.contains("invalid auth token"));
+
+        // A first line longer than the token is rejected without waiting for its end
+        let mut flooder = BufReader::new(connect().await);
+        flooder.write_all(&[b'x'; 1024]).await.unwrap();
+        let mut rejection = String::new();
+        flooder.read_line(&mut rejection).await.unwrap();
+        assert!(rejection.contains("invalid auth token"));
+
+        drop(client);
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn shutdown_stops_reading_and_answers_in_flight_requests() {
+        // The client keeps its end open, so only the shutdown signal ends the session
+        let (mut client, input) = tokio::io::duplex(1024);
+        client
+            .write_all(b"{\"jsonrpc\": \"2.0\", \"id\": 1, \"method\": \"ping\"}\n")
+            .await
+            .unwrap();
+        let (writer, mut reader) = tokio::io::duplex(64 * 1024);
+        let stdout: SharedStdout = Arc::new(Mutex::new(Box::new(writer)));
+        let shutdown = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            "SIGTERM"
+        };
+        serve(
+            BufReader::new(input),
+            stdout,
+            Arc::new(MockRunner::default()),
+            shutdown,
+        )
+        .await
+        .unwrap();
+
+        let mut output = String::new();
+        reader.read_to_string(&mut output).await.unwrap();
+        let response: Value = serde_json::from_str(output.trim()).unwrap();
+        assert_eq!(response["id"], 1);
+        drop(client);
+    }
+
+    #[tokio::test]
+    async fn error_codes() {
+        let code = |result: Result<Value>| {
+            let response: Value =
+                serde_json::from_str(&build_response(Some(json!(1)), result).unwrap()).unwrap();
+            response["error"]["code"].clone()
+        };
+        let runner = MockRunner::default();
+        let unknown = handle_method("no_such_method", None, &runner)
+            .await
+            .unwrap();
+        assert_eq!(code(unknown), METHOD_NOT_FOUND);
+        let direct = handle_method("get_netstat", None, &runner).await.unwrap();
+        assert_eq!(code(direct), METHOD_NOT_FOUND);
+        let unknown_tool = dispatch_tool("no_such_tool", &HashMap::new(), &runner).await;
+        assert_eq!(code(unknown_tool), INVALID_PARAMS);
+        let missing_node = dispatch_tool("get_netstat", &HashMap::new(), &runner).await;
+        assert_eq!(code(missing_node), INVALID_PARAMS);
+        let failed = Err(TalosctlError::new(&["version"], Some(1), String::new()).into());
+        assert_eq!(code(failed), INTERNAL_ERROR);
+    }
+
+    #[test]
+    fn tools_list_pages_through_every_tool() {
+        let all = get_capabilities()["tools"].as_array().unwrap().len();
+        let mut seen = 0;
+        let mut cursor: Option<String> = None;
+        loop {
+            let page = list_tools_page(get_capabilities(), cursor.as_deref()).unwrap();
+            let tools = page["tools"].as_array().unwrap();
+            assert!(!tools.is_empty() && tools.len() <= TOOLS_PAGE_SIZE);
+            seen += tools.len();
+            match page.get("nextCursor") {
+                Some(next) => cursor = Some(next.as_str().unwrap().to_string()),
+                None => break,
             }
         }
-        "defrag_etcd" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            match node {
-                Ok(node) => {
-                    let output = run_talosctl(&["--nodes", node, "etcd", "defrag"]);
-                    Some(output.map(|_| json!({"status": "etcd defragmented"})))
-                }
-                Err(e) => Some(Err(e)),
-            }
+        assert_eq!(seen, all);
+
+        for bad in ["abc", "0", "100000"] {
+            let err = list_tools_page(get_capabilities(), Some(bad)).unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<RpcFailure>(),
+                Some(RpcFailure::InvalidParams(_))
+            ));
         }
-        "bootstrap_etcd" => {
-            let node = params_map
-                .get("node")
-                .and_then(|v| v.as_str())
-                .ok_or(anyhow!("Missing node param"));
-            match node {
-                Ok(node) => {
-                    let output = run_talosctl(&["--nodes", node, "bootstrap"]);
-                    Some(output.map(|_| json!({"status": "etcd bootstrapped"})))
-                }
-                Err(e) => Some(Err(e)),
-            }
+    }
+
+    #[test]
+    fn every_tool_is_annotated() {
+        let capabilities = get_capabilities();
+        let tools = capabilities["tools"].as_array().unwrap();
+        for tool in tools {
+            assert!(
+                tool["annotations"]["readOnlyHint"].is_boolean(),
+                "{}",
+                tool["name"]
+            );
         }
-        _ => None,
+        let annotations = |name: &str| {
+            tools
+                .iter()
+                .find(|tool| tool["name"] == name)
+                .map(|tool| tool["annotations"].clone())
+                .unwrap()
+        };
+        assert_eq!(annotations("talos_get_version")["readOnlyHint"], true);
+        assert_eq!(annotations("talos_reset_node")["destructiveHint"], true);
+        assert_eq!(annotations("talos_etcd_snapshot")["destructiveHint"], false);
     }
-}
 
-// Handler for each method (following grok.md specification).
-fn handle_method(method: &str, params: Option<&Value>) -> Option<Result<Value>> {
-    let params_map = extract_params(params);
+    #[test]
+    fn services_table_is_parsed() {
+        let out = "NODE       SERVICE   STATE     HEALTH   LAST CHANGE   LAST EVENT\n\
+                   10.0.0.1   apid      Running   OK       2m36s ago     Health check successful\n\
+                   10.0.0.1   kubelet   Waiting   ?        5s ago        Waiting for service \"cri\" to be \"up\"\n";
+        let services = parse_services_table(out).unwrap();
+        assert_eq!(services.len(), 2);
+        assert_eq!(services[0]["name"], "apid");
+        assert_eq!(services[0]["last_change"], "2m36s ago");
+        assert_eq!(services[1]["state"], "Waiting");
+        assert_eq!(services[1]["health"], "?");
+        assert_eq!(
+            services[1]["last_event"],
+            "Waiting for service \"cri\" to be \"up\""
+        );
+        assert!(parse_services_table("error: something unexpected").is_none());
+    }
 
-    // Try MCP protocol methods FIRST (ping, initialize, tools/list, etc.)
-    if let Some(result) = handle_mcp_protocol_methods(method, &params_map) {
-        return Some(result); // Found a matching MCP method
+    #[test]
+    fn config_contexts_are_parsed() {
+        let out = "CURRENT   NAME      ENDPOINTS             NODES\n\
+                   *         prod      10.0.0.1,10.0.0.2     10.0.0.1\n\
+                   \x20         staging   10.1.0.1\n";
+        let contexts = parse_config_contexts(out).unwrap();
+        assert_eq!(contexts.len(), 2);
+        assert_eq!(contexts[0]["current"], true);
+        assert_eq!(contexts[0]["endpoints"], json!(["10.0.0.1", "10.0.0.2"]));
+        assert_eq!(contexts[1]["name"], "staging");
+        assert_eq!(contexts[1]["current"], false);
+        assert_eq!(contexts[1]["nodes"], json!([]));
+
+        let info = parse_config_info(
+            "Current context:     prod\nNodes:               10.0.0.1 10.0.0.2\n",
+        );
+        assert_eq!(info["current_context"], "prod");
+        assert_eq!(info["nodes"], json!(["10.0.0.1", "10.0.0.2"]));
     }
 
-    // Special handling for notifications that should return None
-    if method.starts_with("notifications/") {
-        return None; // Notifications should not have responses
+    #[test]
+    fn mounts_table_is_parsed() {
+        let out = "NODE       FILESYSTEM   SIZE(GB)   USED(GB)   AVAILABLE(GB)   PERCENT USED   MOUNTED ON\n\
+                   10.0.0.1   overlay      10.24      9.50       0.74            92.77%         /\n\
+                   10.0.0.1   /dev/sda6    1.02       0.01       1.01            1.00%          /var\n";
+        let mounts = parse_mounts_table(out).unwrap();
+        assert_eq!(mounts.len(), 2);
+        assert_eq!(mounts[0]["filesystem"], "overlay");
+        assert_eq!(mounts[0]["percent"], 92.77);
+        assert_eq!(mounts[1]["mountpoint"], "/var");
+        assert_eq!(mounts[1]["available"], 1.01);
+        assert!(parse_mounts_table("rpc error: unavailable").is_none());
     }
 
-    // Try system inspection methods
-    if let Some(result) = handle_system_inspection_methods(method, &params_map) {
-        return Some(result);
+    #[test]
+    fn proc_modules_are_parsed() {
+        let out = "nf_conntrack 172032 2 nf_nat,xt_conntrack, Live 0x0000000000000000\n\
+                   xt_conntrack 12288 1 - Live 0x0000000000000000\n";
+        let modules = parse_proc_modules(out).unwrap();
+        assert_eq!(modules.len(), 2);
+        assert_eq!(modules[0]["size"], 172032);
+        assert_eq!(modules[0]["used_by"], json!(["nf_nat", "xt_conntrack"]));
+        assert_eq!(modules[1]["used_by"], json!([]));
+        assert_eq!(modules[1]["state"], "Live");
+        assert!(parse_proc_modules("open /proc/modules: not found").is_none());
     }
 
-    // Try file operations methods
-    if let Some(result) = handle_file_operations_methods(method, &params_map) {
-        return Some(result);
+    #[test]
+    fn large_output_is_truncated() {
+        let text = "é".repeat(10);
+        let (head, total) = output::truncate(text.clone(), Some(5), output::Keep::Head);
+        assert_eq!(total, Some(20));
+        assert!(head.starts_with("éé\n... [truncated: showing first 4 of 20 bytes]"));
+        let (tail, _) = output::truncate(text.clone(), Some(5), output::Keep::Tail);
+        assert!(tail.ends_with("bytes]\néé"));
+        assert_eq!(
+            output::truncate(text.clone(), None, output::Keep::Head),
+            (text, None)
+        );
+
+        let mut result = json!({"content": "abcdef"});
+        output::limit_field(&mut result, "content", Some(3), output::Keep::Head);
+        assert_eq!(result["truncated"], true);
+        assert_eq!(result["total_bytes"], 6);
+        let unlimited = extract_params(Some(&json!({"max_bytes": 0})));
+        assert_eq!(output::max_output_bytes(&unlimited).unwrap(), None);
     }
 
-    // Try network operations methods
-    if let Some(result) = handle_network_operations_methods(method, &params_map) {
-        return Some(result);
+    #[tokio::test]
+    async fn initialize_negotiates_protocol_version() {
+        let initialize = |version: &str| json!({"protocolVersion": version, "capabilities": {}, "clientInfo": {"name": "test"}});
+        let runner = MockRunner::default();
+        let agreed =
+            |result: Option<Result<Value>>| result.unwrap().unwrap()["protocolVersion"].clone();
+
+        let older = handle_method("initialize", Some(&initialize("2024-11-05")), &runner).await;
+        assert_eq!(agreed(older), "2024-11-05");
+        let newer = handle_method("initialize", Some(&initialize("2099-01-01")), &runner).await;
+        assert_eq!(agreed(newer), SUPPORTED_PROTOCOL_VERSIONS[0]);
+        let too_old = handle_method("initialize", Some(&initialize("2024-01-01")), &runner)
+            .await
+            .unwrap();
+        assert!(too_old
+            .unwrap_err()
+            .to_string()
+            .contains("Unsupported protocol version"));
     }
 
-    // Try service and logging methods
-    if let Some(result) = handle_service_log_methods(method, &params_map) {
-        return Some(result);
+    #[test]
+    fn talosctl_error_data() {
+        let err = TalosctlError::new(
+            &[
+                "--talosconfig",
+                "/secret/talosconfig",
+                "--nodes",
+                "10.0.0.1",
+                "version",
+            ],
+            Some(1),
+            "x509: certificate has expired".to_string(),
+        );
+        let data = err.error_data();
+        assert_eq!(data["exit_code"], 1);
+        assert_eq!(data["category"], "auth");
+        assert_eq!(data["args"][1], "[REDACTED]");
+        assert_eq!(data["args"][4], "version");
     }
 
-    // Try storage and hardware methods
-    if let Some(result) = handle_storage_hardware_methods(method, &params_map) {
-        return Some(result);
+    #[test]
+    fn only_read_only_tools_retry_transient_failures() {
+        let err = TalosctlError::new(
+            &["version"],
+            Some(1),
+            "rpc error: code = Unavailable desc = connection refused".to_string(),
+        );
+        assert_eq!(err.category, Some("transient"));
+
+        // Outside a read-only call nothing is retried, whatever TALOSCTL_RETRIES says
+        assert_eq!(retry_policy().0, 0);
+        assert!(tools::is_read_only("get_version"));
+        assert!(!tools::is_read_only("reboot_node"));
     }
 
-    // Try core cluster methods
-    let result = handle_core_cluster_methods(method, &params_map);
-    if result.is_some() {
-        return result;
+    #[test]
+    fn endpoints_from_array_or_single_endpoint() {
+        let params = |v: Value| extract_params(Some(&v));
+        assert_eq!(
+            call_endpoints(&params(json!({"endpoints": ["10.0.0.1", "10.0.0.2"]}))).as_deref(),
+            Some("10.0.0.1,10.0.0.2")
+        );
+        assert_eq!(
+            call_endpoints(&params(json!({"endpoint": "cp.example.com:50000"}))).as_deref(),
+            Some("cp.example.com:50000")
+        );
+        assert_eq!(call_endpoints(&params(json!({"node": "10.0.0.5"}))), None);
+        assert_eq!(call_endpoints(&params(json!({"endpoints": []}))), None);
     }
 
-    // Try node management methods
-    if let Some(result) = handle_node_management_methods(method, &params_map) {
-        return Some(result);
+    #[test]
+    fn context_must_name_a_context() {
+        let params = |value: Value| extract_params(Some(&value));
+        assert_eq!(
+            call_context(&params(json!({"context": " staging "})))
+                .unwrap()
+                .as_deref(),
+            Some("staging")
+        );
+        assert_eq!(
+            call_context(&params(json!({"node": "10.0.0.5"}))).unwrap(),
+            None
+        );
+        assert!(call_context(&params(json!({"context": "  "}))).is_err());
+        assert!(call_context(&params(json!({"context": 3}))).is_err());
     }
 
-    // Try config/etcd methods
-    if let Some(result) = handle_config_etcd_methods(method, &params_map) {
-        return Some(result);
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn talosctl_slots_bound_concurrency() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static RUNNING: AtomicUsize = AtomicUsize::new(0);
+        static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+        let mut calls = JoinSet::new();
+        for _ in 0..100 {
+            calls.spawn(with_talosctl_slot(async {
+                let running = RUNNING.fetch_add(1, Ordering::SeqCst) + 1;
+                PEAK.fetch_max(running, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(2)).await;
+                RUNNING.fetch_sub(1, Ordering::SeqCst);
+            }));
+        }
+        while let Some(call) = calls.join_next().await {
+            call.unwrap();
+        }
+        let peak = PEAK.load(Ordering::SeqCst);
+        assert!(
+            peak > 1 && peak <= DEFAULT_TALOSCTL_MAX_CONCURRENCY,
+            "peak {}",
+            peak
+        );
     }
 
-    Some(Err(anyhow!("Unknown method: {}", method)))
-}
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn queued_talosctl_calls_get_their_full_timeout() {
+        let mut holders = JoinSet::new();
+        for _ in 0..DEFAULT_TALOSCTL_MAX_CONCURRENCY {
+            holders.spawn(with_talosctl_slot(tokio::time::sleep(
+                Duration::from_millis(300),
+            )));
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
 
-// Main async RPC loop over stdio (from grok.md specification).
-async fn rpc_loop() -> Result<()> {
-    let stdin = tokio::io::stdin();
-    let reader = BufReader::new(stdin);
-    let mut lines = reader.lines();
-    let mut stdout = tokio::io::stdout();
-
-    while let Some(line) = lines.next_line().await? {
-        let request: RpcRequest = serde_json::from_str(&line).context("Invalid JSON request")?;
-
-        let result = handle_method(&request.method, request.params.as_ref());
-        if let Some(method_result) = result {
-            let resp_json = match method_result {
-                Ok(res) => {
-                    let response = RpcSuccessResponse {
-                        jsonrpc: "2.0".to_string(),
-                        result: res,
-                        id: request.id,
-                    };
-                    serde_json::to_string(&response)?
-                }
-                Err(err) => {
-                    let response = RpcErrorResponse {
-                        jsonrpc: "2.0".to_string(),
-                        error: RpcError {
-                            code: -32600,
-                            message: err.to_string(),
-                            data: None,
-                        },
-                        id: request.id,
-                    };
-                    serde_json::to_string(&response)?
-                }
-            };
-            stdout.write_all((resp_json + "\n").as_bytes()).await?;
-            stdout.flush().await?;
+        // Queued for ~280ms, far longer than its timeout, but the call itself is quick
+        let queued = with_talosctl_slot_timeout(Duration::from_millis(150), async {
+            tokio::time::sleep(Duration::from_millis(10)).await
+        })
+        .await;
+        assert!(queued.is_ok());
+        while let Some(holder) = holders.join_next().await {
+            holder.unwrap();
         }
-        // If result is None, it's a notification - no response should be sent
     }
-    Ok(())
-}
 
-fn main() -> Result<()> {
-    let rt = Runtime::new()?;
-    rt.block_on(rpc_loop())?;
+    #[tokio::test]
+    async fn processes_across_nodes() {
+        let runner = MockRunner::with_output(
+            "NODE       PID   STATE   COMMAND\n\
+             10.0.0.2   1     S       init\n\
+             10.0.0.2   42    R       etcd",
+        );
+        let params_map = extract_params(Some(&json!({"nodes": ["10.0.0.1", "10.0.0.2"]})));
+        let result = dispatch_tool("get_processes", &params_map, &runner)
+            .await
+            .unwrap();
+        let mut calls = runner.calls();
+        calls.sort();
+        assert_eq!(
+            calls,
+            [
+                ["--nodes", "10.0.0.1", "processes", "--sort", "rss"],
+                ["--nodes", "10.0.0.2", "processes", "--sort", "rss"]
+            ]
+        );
+        let second = &result["results"]["10.0.0.2"];
+        assert_eq!(second["count"], 2);
+        assert_eq!(second["processes"][1]["pid"], 42);
+        assert_eq!(second["processes"][1]["command"], "etcd");
+    }
 
-    Ok(())
+    #[tokio::test]
+    async fn unreachable_node_leaves_the_others_partial_results() {
+        let runner = MockRunner {
+            output: "stats".to_string(),
+            fail_for: vec!["10.0.0.3".to_string()],
+            ..Default::default()
+        };
+        let nodes = json!({"nodes": ["10.0.0.1", "10.0.0.2", "10.0.0.3"]});
+        let result = dispatch_tool("stats", &extract_params(Some(&nodes)), &runner)
+            .await
+            .unwrap();
+        let results = result["results"].as_object().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(result["results"]["10.0.0.1"]["stats"], "stats");
+        assert!(result["errors"]["10.0.0.3"]
+            .as_str()
+            .unwrap()
+            .contains("connection refused"));
+
+        // Only when every node fails does the call fail
+        let runner = MockRunner {
+            fail_for: vec!["10.0.0.1".to_string(), "10.0.0.2".to_string()],
+            ..Default::default()
+        };
+        let nodes = json!({"nodes": ["10.0.0.1", "10.0.0.2"]});
+        assert!(
+            dispatch_tool("stats", &extract_params(Some(&nodes)), &runner)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn dmesg_requires_a_node() {
+        let params_map = extract_params(Some(&json!({"tail": 10})));
+        let err = dispatch_tool("dmesg", &params_map, &MockRunner::default())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Missing node or nodes param"));
+    }
+
+    #[tokio::test]
+    async fn node_values_are_validated() {
+        let prepare = |params: Value| async move {
+            nodes::prepare_node_params(&mut extract_params(Some(&params))).await
+        };
+        for node in [
+            json!(""),
+            json!("   "),
+            json!("10.0.0.1,10.0.0.2"),
+            json!(5),
+        ] {
+            let err = prepare(json!({ "node": node })).await.unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<RpcFailure>(),
+                Some(RpcFailure::InvalidParams(_))
+            ));
+        }
+        assert!(prepare(json!({"nodes": ["10.0.0.1", ""]})).await.is_err());
+
+        let mut params_map = extract_params(Some(&json!({"node": " 10.0.0.1 "})));
+        nodes::prepare_node_params(&mut params_map).await.unwrap();
+        assert_eq!(params_map["node"], "10.0.0.1");
+    }
+
+    #[tokio::test]
+    async fn node_addresses_are_normalized() {
+        let normalize = |node: &str| {
+            let mut params_map = extract_params(Some(&json!({ "node": node })));
+            async move {
+                nodes::prepare_node_params(&mut params_map)
+                    .await
+                    .map(|_| params_map["node"].clone())
+            }
+        };
+        for (node, expected) in [
+            ("10.0.0.1", "10.0.0.1"),
+            ("10.0.0.1:50000", "10.0.0.1"),
+            ("fd00::1", "fd00::1"),
+            ("fd00:0::1", "fd00::1"),
+            ("[fd00::1]", "fd00::1"),
+            ("[fd00::1]:50000", "fd00::1"),
+            ("CP-1.Example.com", "cp-1.example.com"),
+            ("worker-2:50000", "worker-2"),
+        ] {
+            assert_eq!(normalize(node).await.unwrap(), expected, "{}", node);
+        }
+        for node in [
+            "[10.0.0.1]",
+            "fd00::zz",
+            "[fd00::1",
+            "[fd00::1]50000",
+            "10.0.0.1:0",
+            "-bad.example.com",
+            "bad_host",
+        ] {
+            assert!(normalize(node).await.is_err(), "{}", node);
+        }
+    }
 }
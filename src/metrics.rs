@@ -0,0 +1,141 @@
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use crate::tools;
+
+// Upper bounds, in milliseconds, of the latency histogram buckets; slower calls land
+// in a final overflow bucket.
+const LATENCY_BUCKETS_MS: &[u64] = &[
+    10, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000, 60_000,
+];
+
+// Call counters for one tool. Updated with relaxed atomics, as each is independent.
+struct ToolStats {
+    calls: AtomicU64,
+    errors: AtomicU64,
+    total_ms: AtomicU64,
+    buckets: Vec<AtomicU64>,
+}
+
+impl ToolStats {
+    fn new() -> Self {
+        ToolStats {
+            calls: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            total_ms: AtomicU64::new(0),
+            buckets: (0..=LATENCY_BUCKETS_MS.len())
+                .map(|_| AtomicU64::new(0))
+                .collect(),
+        }
+    }
+}
+
+// Per-tool stats, allocated once for every known tool so recording never takes a lock.
+struct Registry {
+    started: Instant,
+    tools: HashMap<String, ToolStats>,
+}
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| Registry {
+        started: Instant::now(),
+        tools: tools::get_all_tool_schemas()["tools"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|tool| tool["name"].as_str())
+            .map(|name| (name.to_string(), ToolStats::new()))
+            .collect(),
+    })
+}
+
+// Start the uptime clock at server startup rather than at the first call.
+pub fn start() {
+    registry();
+}
+
+// Count a finished tool call. Calls to unknown tools aren't tracked.
+pub fn record(tool: &str, elapsed: Duration, ok: bool) {
+    let Some(stats) = registry().tools.get(tool) else {
+        return;
+    };
+    let ms = elapsed.as_millis() as u64;
+    let bucket = LATENCY_BUCKETS_MS
+        .iter()
+        .position(|bound| ms <= *bound)
+        .unwrap_or(LATENCY_BUCKETS_MS.len());
+    stats.calls.fetch_add(1, Ordering::Relaxed);
+    if !ok {
+        stats.errors.fetch_add(1, Ordering::Relaxed);
+    }
+    stats.total_ms.fetch_add(ms, Ordering::Relaxed);
+    stats.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+}
+
+// Upper bound of the bucket holding the `quantile` of calls, or None when it falls in
+// the overflow bucket.
+fn percentile(buckets: &[u64], calls: u64, quantile: f64) -> Option<u64> {
+    let rank = ((calls as f64) * quantile).ceil().max(1.0) as u64;
+    let mut seen = 0;
+    for (index, count) in buckets.iter().enumerate() {
+        seen += count;
+        if seen >= rank {
+            return LATENCY_BUCKETS_MS.get(index).copied();
+        }
+    }
+    None
+}
+
+// Counters, error rates and latency percentiles of every tool called so far, plus
+// totals across all tools.
+pub fn snapshot() -> Value {
+    let registry = registry();
+    let mut per_tool = Map::new();
+    let (mut total_calls, mut total_errors) = (0, 0);
+    let mut names: Vec<_> = registry.tools.keys().collect();
+    names.sort();
+    for name in names {
+        let stats = &registry.tools[name];
+        let calls = stats.calls.load(Ordering::Relaxed);
+        if calls == 0 {
+            continue;
+        }
+        let errors = stats.errors.load(Ordering::Relaxed);
+        let buckets: Vec<u64> = stats
+            .buckets
+            .iter()
+            .map(|b| b.load(Ordering::Relaxed))
+            .collect();
+        total_calls += calls;
+        total_errors += errors;
+        per_tool.insert(
+            name.clone(),
+            json!({
+                "calls": calls,
+                "errors": errors,
+                "error_rate": errors as f64 / calls as f64,
+                "mean_ms": stats.total_ms.load(Ordering::Relaxed) / calls,
+                "p50_ms": percentile(&buckets, calls, 0.5),
+                "p90_ms": percentile(&buckets, calls, 0.9),
+                "p99_ms": percentile(&buckets, calls, 0.99),
+                "latency_buckets": LATENCY_BUCKETS_MS
+                    .iter()
+                    .map(|bound| json!(bound))
+                    .chain([json!("+Inf")])
+                    .zip(&buckets)
+                    .map(|(le, count)| json!({"le_ms": le, "count": count}))
+                    .collect::<Vec<_>>()
+            }),
+        );
+    }
+    json!({
+        "uptime_secs": registry.started.elapsed().as_secs(),
+        "calls": total_calls,
+        "errors": total_errors,
+        "tools": per_tool
+    })
+}
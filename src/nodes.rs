@@ -0,0 +1,210 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::env;
+use std::net::IpAddr;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+// Params holding a single node address.
+const NODE_PARAMS: &[&str] = &["node", "target", "init_node"];
+
+// Params holding a list of node addresses.
+const NODE_LIST_PARAMS: &[&str] = &["control_planes", "worker_nodes", "nodes"];
+
+// Default node from TALOS_DEFAULT_NODE, used when a tool's node param is omitted.
+// Intended for single-node setups where exactly one node is always meant.
+pub fn default_node() -> Option<String> {
+    env::var("TALOS_DEFAULT_NODE")
+        .ok()
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+}
+
+// Target nodes for tools that can query several nodes, one talosctl call each: the
+// `nodes` array if given, otherwise the single `node` string.
+pub fn node_targets(params_map: &HashMap<String, Value>) -> Result<Vec<String>> {
+    let nodes: Vec<String> = params_map
+        .get("nodes")
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_str())
+                .filter(|n| !n.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    if !nodes.is_empty() {
+        return Ok(nodes);
+    }
+    match params_map.get("node").and_then(|v| v.as_str()) {
+        Some(node) if !node.is_empty() => Ok(vec![node.to_string()]),
+        _ => Err(invalid_params!("Missing node or nodes param")),
+    }
+}
+
+// Normalize and validate every node-valued param, then resolve hostnames if enabled.
+// Returns the hostname/IP pairs that were substituted by resolution.
+pub async fn prepare_node_params(params_map: &mut HashMap<String, Value>) -> Result<Vec<Value>> {
+    for value in node_param_values(params_map) {
+        match value.as_str() {
+            Some(node) => *value = json!(normalize_node(node)?),
+            None => {
+                return Err(invalid_params!(
+                    "Invalid node {}: expected an IP address or hostname string",
+                    value
+                ))
+            }
+        }
+    }
+    resolve_node_params(params_map).await
+}
+
+// Attach node hostname resolutions to a successful result for traceability.
+pub fn with_resolutions(result: Result<Value>, resolutions: Vec<Value>) -> Result<Value> {
+    result.map(|mut value| {
+        if !resolutions.is_empty() {
+            if let Some(obj) = value.as_object_mut() {
+                obj.insert("resolved_nodes".to_string(), json!(resolutions));
+            }
+        }
+        value
+    })
+}
+
+// All node address values in the params, across single and list params.
+fn node_param_values(params_map: &mut HashMap<String, Value>) -> Vec<&mut Value> {
+    params_map
+        .iter_mut()
+        .flat_map(|(key, value)| {
+            if NODE_PARAMS.contains(&key.as_str()) {
+                vec![value]
+            } else if NODE_LIST_PARAMS.contains(&key.as_str()) {
+                value
+                    .as_array_mut()
+                    .map(|values| values.iter_mut().collect())
+                    .unwrap_or_default()
+            } else {
+                Vec::new()
+            }
+        })
+        .collect()
+}
+
+// Normalize a node address for --nodes: strip brackets and any port, canonicalize
+// IP addresses (so IPv6 is always in compressed form) and validate hostnames.
+fn normalize_node(node: &str) -> Result<String> {
+    let trimmed = node.trim();
+    let invalid = |reason: &str| invalid_params!("Invalid node '{}': {}", node, reason);
+    if trimmed.is_empty() {
+        return Err(invalid("must not be empty"));
+    }
+    // talosctl would treat a comma-separated value as several nodes
+    if trimmed.contains(',') {
+        return Err(invalid("use the nodes array to target several nodes"));
+    }
+
+    let (host, bracketed) = if let Some(rest) = trimmed.strip_prefix('[') {
+        let (inner, after) = rest
+            .split_once(']')
+            .ok_or_else(|| invalid("missing closing ']'"))?;
+        if !after.is_empty() && !after.strip_prefix(':').is_some_and(is_port) {
+            return Err(invalid("expected [address] or [address]:port"));
+        }
+        (inner, true)
+    } else if trimmed.matches(':').count() == 1 {
+        // host:port; a bare IPv6 address always has more than one colon
+        let (host, port) = trimmed.split_once(':').unwrap_or((trimmed, ""));
+        if !is_port(port) {
+            return Err(invalid("port must be a number between 1 and 65535"));
+        }
+        (host, false)
+    } else {
+        (trimmed, false)
+    };
+
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(_)) if bracketed => Err(invalid("brackets are only valid around IPv6")),
+        Ok(ip) => Ok(ip.to_string()),
+        Err(_) if bracketed || host.contains(':') => Err(invalid("not a valid IPv6 address")),
+        Err(_) if is_hostname(host) => Ok(host.to_ascii_lowercase()),
+        Err(_) => Err(invalid("not a valid IP address or hostname")),
+    }
+}
+
+fn is_port(port: &str) -> bool {
+    port.parse::<u16>().is_ok_and(|p| p > 0)
+}
+
+// RFC 1123 hostname: dot-separated labels of letters, digits and inner hyphens.
+fn is_hostname(host: &str) -> bool {
+    !host.is_empty()
+        && host.len() <= 253
+        && host.trim_end_matches('.').split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+// How long resolved node addresses are cached (TALOS_RESOLVE_TTL seconds, default 300).
+fn resolve_ttl() -> Duration {
+    env::var("TALOS_RESOLVE_TTL")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(300))
+}
+
+// Resolve a node hostname to an IP, caching the result for the configured TTL. The
+// lookup runs off the async workers, so a slow DNS server only delays this call; the
+// cache lock is never held across it.
+async fn resolve_host(host: &str) -> Result<IpAddr> {
+    static CACHE: OnceLock<Mutex<HashMap<String, (IpAddr, Instant)>>> = OnceLock::new();
+    let cache = CACHE.get_or_init(Default::default);
+
+    if let Some((ip, resolved_at)) = cache.lock().unwrap().get(host) {
+        if resolved_at.elapsed() < resolve_ttl() {
+            return Ok(*ip);
+        }
+    }
+
+    let ip = tokio::net::lookup_host((host, 0))
+        .await
+        .map_err(|e| invalid_params!("Failed to resolve node hostname '{}': {}", host, e))?
+        .map(|addr| addr.ip())
+        .next()
+        .ok_or(invalid_params!(
+            "Node hostname '{}' resolved to no addresses",
+            host
+        ))?;
+    cache
+        .lock()
+        .unwrap()
+        .insert(host.to_string(), (ip, Instant::now()));
+    Ok(ip)
+}
+
+// When TALOS_RESOLVE_NODES=1, replace hostname node params with resolved IPs so talosctl
+// doesn't re-resolve on every call. Returns the hostname/IP pairs that were substituted.
+async fn resolve_node_params(params_map: &mut HashMap<String, Value>) -> Result<Vec<Value>> {
+    let mut resolutions = Vec::new();
+    if env::var("TALOS_RESOLVE_NODES").as_deref() != Ok("1") {
+        return Ok(resolutions);
+    }
+
+    for value in node_param_values(params_map) {
+        if let Some(host) = value.as_str() {
+            if !host.is_empty() && host.parse::<IpAddr>().is_err() {
+                let ip = resolve_host(host).await?;
+                resolutions.push(json!({"hostname": host, "ip": ip.to_string()}));
+                *value = json!(ip.to_string());
+            }
+        }
+    }
+
+    Ok(resolutions)
+}
@@ -0,0 +1,92 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::env;
+
+// Output size limit when neither max_bytes nor TALOS_MAX_OUTPUT_BYTES is set.
+const DEFAULT_MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+
+// Which end of an oversized output to keep.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Keep {
+    Head,
+    Tail,
+}
+
+// Byte limit for a tool's large text output: the call's `max_bytes`, then
+// TALOS_MAX_OUTPUT_BYTES, then the default. Zero means unlimited.
+pub fn max_output_bytes(params_map: &HashMap<String, Value>) -> Result<Option<usize>> {
+    let limit = match params_map.get("max_bytes") {
+        Some(value) => value.as_u64().ok_or_else(|| {
+            invalid_params!("Invalid max_bytes '{}': expected a byte count", value)
+        })? as usize,
+        None => env::var("TALOS_MAX_OUTPUT_BYTES")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(DEFAULT_MAX_OUTPUT_BYTES),
+    };
+    Ok(Some(limit).filter(|l| *l > 0))
+}
+
+// Which end of the output `read` keeps, from its `keep` param (defaults to the head).
+pub fn keep_param(params_map: &HashMap<String, Value>) -> Result<Keep> {
+    match params_map.get("keep").and_then(|v| v.as_str()) {
+        None | Some("head") => Ok(Keep::Head),
+        Some("tail") => Ok(Keep::Tail),
+        Some(other) => Err(invalid_params!(
+            "Invalid keep '{}': expected head or tail",
+            other
+        )),
+    }
+}
+
+// Cut text down to `max` bytes at a character boundary, marking where it was cut.
+// Returns the text and, when it was truncated, its original size.
+pub fn truncate(text: String, max: Option<usize>, keep: Keep) -> (String, Option<usize>) {
+    let total = text.len();
+    let Some(max) = max.filter(|max| total > *max) else {
+        return (text, None);
+    };
+
+    let truncated = match keep {
+        Keep::Head => {
+            let mut end = max;
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            format!(
+                "{}\n... [truncated: showing first {} of {} bytes]\n",
+                &text[..end],
+                end,
+                total
+            )
+        }
+        Keep::Tail => {
+            let mut start = total - max;
+            while !text.is_char_boundary(start) {
+                start += 1;
+            }
+            format!(
+                "... [truncated: showing last {} of {} bytes]\n{}",
+                total - start,
+                total,
+                &text[start..]
+            )
+        }
+    };
+    (truncated, Some(total))
+}
+
+// Truncate the string `field` of a tool result, flagging the result with `truncated`
+// and the field's `total_bytes` when it was cut.
+pub fn limit_field(result: &mut Value, field: &str, max: Option<usize>, keep: Keep) {
+    let Some(text) = result[field].as_str() else {
+        return;
+    };
+    let (text, total) = truncate(text.to_string(), max, keep);
+    if let Some(total) = total {
+        result[field] = json!(text);
+        result["truncated"] = json!(true);
+        result["total_bytes"] = json!(total);
+    }
+}
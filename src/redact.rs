@@ -0,0 +1,123 @@
+use regex::{Captures, Regex};
+use serde_json::{json, Value};
+use std::env;
+use std::sync::OnceLock;
+
+// Replacement for every redacted secret.
+const REDACTED: &str = "***REDACTED***";
+
+// Whether TALOS_REDACT is enabled.
+pub fn enabled() -> bool {
+    env::var("TALOS_REDACT").is_ok_and(|v| !v.is_empty() && v != "0")
+}
+
+// With TALOS_REDACT enabled, scrub secrets from a tool result and record how many
+// were replaced under `redactions`.
+pub fn apply(result: anyhow::Result<Value>) -> anyhow::Result<Value> {
+    if !enabled() {
+        return result;
+    }
+    result.map(|value| {
+        let mut count = 0;
+        let mut value = redact_value(None, value, &mut count);
+        if let Some(obj) = value.as_object_mut() {
+            obj.insert("redactions".to_string(), json!(count));
+        }
+        value
+    })
+}
+
+// Scrub secrets from a document whatever TALOS_REDACT says, for tools whose output
+// is secret by nature. Returns the document and how many values were replaced.
+pub fn redact_document(value: Value) -> (Value, usize) {
+    let mut count = 0;
+    let value = redact_value(None, value, &mut count);
+    (value, count)
+}
+
+struct Patterns {
+    pem: Regex,
+    secret_field: Regex,
+    secret_key: Regex,
+    bootstrap_token: Regex,
+    base64: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        pem: Regex::new(r"-----BEGIN [A-Z0-9 ]+-----[\s\S]*?-----END [A-Z0-9 ]+-----").unwrap(),
+        // `token: x`, `"client-key-data": "x"`, `password=x` and similar fields
+        secret_field: Regex::new(
+            r#"(?i)([\w.-]*(?:token|secret|password|passwd|key|crt|cert)[\w.-]*["']?\s*[:=]\s*["']?)([^\s"',|>]+)"#,
+        )
+        .unwrap(),
+        secret_key: Regex::new(r"(?i)token|secret|password|passwd|key|crt|cert").unwrap(),
+        // Kubernetes bootstrap tokens, e.g. abcdef.0123456789abcdef
+        bootstrap_token: Regex::new(r"\b[a-z0-9]{6}\.[a-z0-9]{16}\b").unwrap(),
+        base64: Regex::new(r"[A-Za-z0-9+/_-]{40,}={0,2}").unwrap(),
+    })
+}
+
+fn redact_value(key: Option<&str>, value: Value, count: &mut usize) -> Value {
+    match value {
+        Value::String(s) if key.is_some_and(|k| patterns().secret_key.is_match(k)) => {
+            if s.is_empty() {
+                Value::String(s)
+            } else {
+                *count += 1;
+                json!(REDACTED)
+            }
+        }
+        Value::String(s) => Value::String(redact_text(&s, count)),
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|v| redact_value(None, v, count))
+                .collect(),
+        ),
+        Value::Object(obj) => Value::Object(
+            obj.into_iter()
+                .map(|(k, v)| {
+                    let v = redact_value(Some(&k), v, count);
+                    (k, v)
+                })
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+// Replace PEM blocks, secret-looking fields, bootstrap tokens and long base64 runs.
+fn redact_text(text: &str, count: &mut usize) -> String {
+    let p = patterns();
+    let text = p.pem.replace_all(text, |_: &Captures| {
+        *count += 1;
+        REDACTED
+    });
+    let text = p.secret_field.replace_all(&text, |caps: &Captures| {
+        if caps[2] == *REDACTED {
+            return caps[0].to_string();
+        }
+        *count += 1;
+        format!("{}{}", &caps[1], REDACTED)
+    });
+    let text = p.bootstrap_token.replace_all(&text, |_: &Captures| {
+        *count += 1;
+        REDACTED
+    });
+    let text = p.base64.replace_all(&text, |caps: &Captures| {
+        let candidate = &caps[0];
+        // Hex digests and paths are single-case; encoded secrets mix cases and digits
+        let mixed = candidate.chars().any(|c| c.is_ascii_uppercase())
+            && candidate.chars().any(|c| c.is_ascii_lowercase())
+            && candidate.chars().any(|c| c.is_ascii_digit());
+        if mixed {
+            *count += 1;
+            REDACTED.to_string()
+        } else {
+            candidate.to_string()
+        }
+    });
+    text.into_owned()
+}
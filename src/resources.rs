@@ -0,0 +1,419 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::runner::CommandRunner;
+use crate::tools;
+
+// Read the optional namespace param for talosctl get, rejecting unknown namespaces
+// since a wrong one silently returns nothing.
+pub fn namespace_param(params_map: &HashMap<String, Value>) -> Result<Option<&str>> {
+    match params_map.get("namespace").and_then(|v| v.as_str()) {
+        Some(ns) if !tools::TALOS_NAMESPACES.contains(&ns) => Err(invalid_params!(
+            "Unknown Talos namespace '{}'; expected one of: {}",
+            ns,
+            tools::TALOS_NAMESPACES.join(", ")
+        )),
+        namespace => Ok(namespace),
+    }
+}
+
+// Append --namespace to talosctl get args when a namespace was requested.
+pub fn push_namespace<'a>(args: &mut Vec<&'a str>, namespace: Option<&'a str>) {
+    if let Some(ns) = namespace {
+        args.extend(&["--namespace", ns]);
+    }
+}
+
+// Run `talosctl get <resource_type>` for a typed tool, honoring namespace, output and
+// selector params. The raw output is returned under `result_key`.
+pub async fn get_typed_resource(
+    params_map: &HashMap<String, Value>,
+    resource_type: &str,
+    result_key: &str,
+    runner: &dyn CommandRunner,
+) -> Result<Value> {
+    get_resource_output(params_map, resource_type, None, result_key, runner).await
+}
+
+// Run `talosctl get` for any resource type the caller names, optionally a single
+// resource by id. Type and id are checked so they can't be taken for flags.
+pub async fn get_resource(
+    params_map: &HashMap<String, Value>,
+    runner: &dyn CommandRunner,
+) -> Result<Value> {
+    let resource = params_map
+        .get("resource")
+        .and_then(|v| v.as_str())
+        .ok_or(invalid_params!("Missing resource param"))?;
+    validate_get_arg("resource", resource)?;
+    let id = params_map.get("id").and_then(|v| v.as_str());
+    if let Some(id) = id {
+        validate_get_arg("id", id)?;
+    }
+
+    let mut result = get_resource_output(params_map, resource, id, "resources", runner).await?;
+    if let Some(obj) = result.as_object_mut() {
+        obj.insert("resource".to_string(), json!(resource));
+        obj.insert("id".to_string(), json!(id));
+    }
+    Ok(result)
+}
+
+// Reject a resource type or id that is empty, looks like a flag, or holds characters
+// no Talos resource name uses.
+fn validate_get_arg(param: &str, value: &str) -> Result<()> {
+    let allowed = |c: char| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '/' | ':');
+    if value.is_empty() || value.starts_with('-') || !value.chars().all(allowed) {
+        return Err(invalid_params!(
+            "Invalid {} '{}': expected a resource name such as 'members' or 'kubeletconfigs.kubernetes.talos.dev'",
+            param,
+            value
+        ));
+    }
+    Ok(())
+}
+
+async fn get_resource_output(
+    params_map: &HashMap<String, Value>,
+    resource_type: &str,
+    id: Option<&str>,
+    result_key: &str,
+    runner: &dyn CommandRunner,
+) -> Result<Value> {
+    let node = params_map
+        .get("node")
+        .and_then(|v| v.as_str())
+        .ok_or(invalid_params!("Missing node param"))?;
+    let namespace = namespace_param(params_map)?;
+    let selector_str = params_map.get("selector").and_then(|v| v.as_str());
+    let selector = selector_str.map(Selector::parse).transpose()?;
+
+    // Filtering needs structured output, so a selector forces JSON
+    let output_format = if selector.is_some() {
+        "json"
+    } else {
+        params_map
+            .get("output")
+            .and_then(|v| v.as_str())
+            .unwrap_or("table")
+    };
+
+    // talosctl takes the expression as part of the output mode: jsonpath=<expr>
+    let jsonpath = params_map.get("jsonpath").and_then(|v| v.as_str());
+    let output_arg = match (output_format, jsonpath) {
+        ("jsonpath", Some(expr)) if !expr.trim().is_empty() => format!("jsonpath={}", expr),
+        ("jsonpath", _) => {
+            return Err(invalid_params!(
+                "Missing jsonpath param: output jsonpath needs an expression such as '{{.spec}}'"
+            ))
+        }
+        (_, Some(_)) => return Err(invalid_params!("jsonpath only applies to output jsonpath")),
+        (format, None) => format.to_string(),
+    };
+
+    let mut args = vec!["--nodes", node, "get", resource_type];
+    if let Some(id) = id {
+        args.push(id);
+    }
+    push_namespace(&mut args, namespace);
+    args.extend(&["--output", &output_arg]);
+    let out = runner.run(&args).await?;
+
+    let mut result = Map::new();
+    result.insert("namespace".to_string(), json!(namespace));
+    result.insert("output_format".to_string(), json!(output_format));
+    if let Some(expr) = jsonpath {
+        result.insert("jsonpath".to_string(), json!(expr));
+    }
+    match selector {
+        Some(selector) => {
+            let resources = parse_resource_stream(&out)?;
+            let total = resources.len();
+            let matched: Vec<Value> = resources
+                .into_iter()
+                .filter(|r| selector.matches(r))
+                .collect();
+            result.insert("selector".to_string(), json!(selector_str));
+            result.insert("matched".to_string(), json!(matched.len()));
+            result.insert("total".to_string(), json!(total));
+            result.insert(result_key.to_string(), json!(matched));
+        }
+        None => {
+            let (output, parse_error) = embed_output(out, output_format);
+            result.insert(result_key.to_string(), output);
+            if let Some(e) = parse_error {
+                result.insert("parse_error".to_string(), json!(e));
+            }
+        }
+    }
+    Ok(Value::Object(result))
+}
+
+// Embed talosctl output in a tool result according to its --output format: json and
+// yaml become one structured entry per resource, while table and jsonpath output
+// stays text. Output that fails to parse is kept as text along with the error.
+pub fn embed_output(raw: String, format: &str) -> (Value, Option<String>) {
+    let parsed = match format {
+        "json" => parse_resource_stream(&raw),
+        "yaml" => parse_yaml_stream(&raw),
+        _ => return (json!(raw), None),
+    };
+    match parsed {
+        Ok(resources) => (json!(resources), None),
+        Err(e) => (json!(raw), Some(e.to_string())),
+    }
+}
+
+// Parse `talosctl get -o yaml` output, a stream of `---`-separated YAML documents.
+pub fn parse_yaml_stream(out: &str) -> Result<Vec<Value>> {
+    serde_yaml::Deserializer::from_str(out)
+        .map(Value::deserialize)
+        .filter(|doc| !matches!(doc, Ok(Value::Null)))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow!("Failed to parse talosctl YAML output: {}", e))
+}
+
+// List the resource types a node serves, from its resource definitions, so callers
+// can enumerate valid types before querying them.
+pub async fn list_resource_types(
+    params_map: &HashMap<String, Value>,
+    runner: &dyn CommandRunner,
+) -> Result<Value> {
+    let node = params_map
+        .get("node")
+        .and_then(|v| v.as_str())
+        .ok_or(invalid_params!("Missing node param"))?;
+    let out = runner
+        .run(&[
+            "--nodes",
+            node,
+            "get",
+            "resourcedefinitions",
+            "--output",
+            "json",
+        ])
+        .await?;
+
+    let mut types: Vec<Value> = parse_resource_stream(&out)?
+        .iter()
+        .map(|rd| {
+            let spec = &rd["spec"];
+            json!({
+                "name": spec["displayType"],
+                "type": spec["type"].as_str().or(rd["metadata"]["id"].as_str()),
+                "aliases": spec["aliases"].as_array().cloned().unwrap_or_default(),
+                "default_namespace": spec["defaultNamespace"]
+            })
+        })
+        .collect();
+    types.sort_by(|a, b| a["type"].as_str().cmp(&b["type"].as_str()));
+
+    Ok(json!({
+        "node": node,
+        "count": types.len(),
+        "resource_types": types
+    }))
+}
+
+// Fetch a resource from several nodes and report where their specs disagree, for
+// drift detection across a fleet. A node that can't be queried is reported but
+// doesn't fail the comparison.
+pub async fn compare_resource(
+    params_map: &HashMap<String, Value>,
+    runner: &dyn CommandRunner,
+) -> Result<Value> {
+    let resource = params_map
+        .get("resource")
+        .and_then(|v| v.as_str())
+        .ok_or(invalid_params!("Missing resource param"))?;
+    validate_get_arg("resource", resource)?;
+    let nodes: Vec<&str> = params_map
+        .get("nodes")
+        .and_then(|v| v.as_array())
+        .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default();
+    if nodes.len() < 2 {
+        return Err(invalid_params!("compare_resource needs at least two nodes"));
+    }
+    let id = params_map.get("id").and_then(|v| v.as_str());
+    if let Some(id) = id {
+        validate_get_arg("id", id)?;
+    }
+    let namespace = namespace_param(params_map)?;
+
+    // Flattened spec fields per node, keyed by resource id
+    let mut per_node = Map::new();
+    let mut fields: Vec<(&str, BTreeMap<String, FlatSpec>)> = Vec::new();
+    for node in &nodes {
+        let mut args = vec!["--nodes", *node, "get", resource];
+        if let Some(id) = id {
+            args.push(id);
+        }
+        push_namespace(&mut args, namespace);
+        args.extend(&["--output", "json"]);
+
+        match runner
+            .run(&args)
+            .await
+            .and_then(|out| parse_resource_stream(&out))
+        {
+            Ok(resources) => {
+                let mut by_id = BTreeMap::new();
+                for r in &resources {
+                    let mut flat = BTreeMap::new();
+                    flatten("", &r["spec"], &mut flat);
+                    by_id.insert(resource_id(r), flat);
+                }
+                per_node.insert(node.to_string(), json!({"resources": resources}));
+                fields.push((node, by_id));
+            }
+            Err(e) => {
+                per_node.insert(node.to_string(), json!({"error": e.to_string()}));
+            }
+        }
+    }
+
+    let ids: BTreeSet<&String> = fields.iter().flat_map(|(_, by_id)| by_id.keys()).collect();
+    let mut differences = Vec::new();
+    for rid in ids {
+        let paths: BTreeSet<&String> = fields
+            .iter()
+            .filter_map(|(_, by_id)| by_id.get(rid))
+            .flat_map(|flat| flat.keys())
+            .collect();
+        let missing_on: Vec<&str> = fields
+            .iter()
+            .filter(|(_, by_id)| !by_id.contains_key(rid))
+            .map(|(node, _)| *node)
+            .collect();
+        if !missing_on.is_empty() {
+            differences.push(json!({"id": rid, "missing_on": missing_on}));
+            continue;
+        }
+        for path in paths {
+            let values: Map<String, Value> = fields
+                .iter()
+                .map(|(node, by_id)| {
+                    let value = by_id[rid].get(path).cloned().unwrap_or(Value::Null);
+                    (node.to_string(), value)
+                })
+                .collect();
+            let mut distinct = values.values();
+            let first = distinct.next();
+            if distinct.any(|v| Some(v) != first) {
+                differences.push(json!({"id": rid, "path": path, "values": values}));
+            }
+        }
+    }
+
+    Ok(json!({
+        "resource": resource,
+        "id": id,
+        "namespace": namespace,
+        "nodes": nodes,
+        "consistent": differences.is_empty() && fields.len() == nodes.len(),
+        "difference_count": differences.len(),
+        "differences": differences,
+        "per_node": per_node
+    }))
+}
+
+// A resource spec flattened to dotted field paths.
+type FlatSpec = BTreeMap<String, Value>;
+
+// Identify a resource by namespace and id so same-named resources in different
+// namespaces aren't compared against each other.
+fn resource_id(resource: &Value) -> String {
+    let metadata = &resource["metadata"];
+    match (metadata["namespace"].as_str(), metadata["id"].as_str()) {
+        (Some(ns), Some(id)) => format!("{}/{}", ns, id),
+        (None, Some(id)) => id.to_string(),
+        _ => "unknown".to_string(),
+    }
+}
+
+// Flatten nested objects into dotted paths; arrays and scalars are compared whole.
+fn flatten(prefix: &str, value: &Value, out: &mut FlatSpec) {
+    match value {
+        Value::Object(obj) if !obj.is_empty() => {
+            for (key, child) in obj {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(&path, child, out);
+            }
+        }
+        other => {
+            out.insert(prefix.to_string(), other.clone());
+        }
+    }
+}
+
+// Parse `talosctl get -o json` output, a stream of pretty-printed JSON objects.
+pub fn parse_resource_stream(out: &str) -> Result<Vec<Value>> {
+    serde_json::Deserializer::from_str(out)
+        .into_iter::<Value>()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow!("Failed to parse talosctl JSON output: {}", e))
+}
+
+// A comma-separated list of `key=value` / `key!=value` terms, all of which must match.
+pub struct Selector {
+    terms: Vec<(String, bool, String)>,
+}
+
+impl Selector {
+    pub fn parse(selector: &str) -> Result<Self> {
+        let terms = selector
+            .split(',')
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(|term| {
+                let (key, equal, value) = if let Some((k, v)) = term.split_once("!=") {
+                    (k, false, v)
+                } else if let Some((k, v)) = term.split_once('=') {
+                    (k, true, v.strip_prefix('=').unwrap_or(v))
+                } else {
+                    return Err(invalid_params!(
+                        "Invalid selector term '{}': expected key=value or key!=value",
+                        term
+                    ));
+                };
+                Ok((key.trim().to_string(), equal, value.trim().to_string()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if terms.is_empty() {
+            return Err(invalid_params!(
+                "Selector must contain at least one key=value term"
+            ));
+        }
+        Ok(Selector { terms })
+    }
+
+    pub fn matches(&self, resource: &Value) -> bool {
+        self.terms.iter().all(|(key, equal, value)| {
+            let actual = lookup_field(resource, key).map(|v| match v {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            });
+            (actual.as_deref() == Some(value.as_str())) == *equal
+        })
+    }
+}
+
+// Find a selector key in a resource: spec fields first, then metadata and labels,
+// then a dotted path from the resource root.
+fn lookup_field<'a>(resource: &'a Value, key: &str) -> Option<&'a Value> {
+    let path = |root: &'a Value, key: &str| {
+        key.split('.')
+            .try_fold(root, |node, segment| node.get(segment))
+    };
+    path(&resource["spec"], key)
+        .or_else(|| path(&resource["metadata"], key))
+        .or_else(|| resource["metadata"]["labels"].get(key))
+        .or_else(|| path(resource, key))
+}
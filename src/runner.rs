@@ -0,0 +1,203 @@
+use anyhow::{anyhow, Result};
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::time::Duration;
+
+// Boxed future returned by CommandRunner methods, so the trait stays object safe.
+pub type RunFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+// Executes talosctl for the tool handlers. Handlers only build argument vectors and
+// shape results, so swapping the runner lets them be exercised without a cluster.
+pub trait CommandRunner: Send + Sync {
+    // Run to completion and return stdout.
+    fn run<'a>(&'a self, args: &'a [&'a str]) -> RunFuture<'a, String>;
+
+    // Run to completion and return stderr, where `talosctl health` reports.
+    fn run_with_stderr<'a>(&'a self, args: &'a [&'a str]) -> RunFuture<'a, String>;
+
+    // Run for at most `window`, returning stdout and whether the command finished.
+    fn run_bounded<'a>(
+        &'a self,
+        args: &'a [&'a str],
+        window: Duration,
+    ) -> RunFuture<'a, (String, bool)>;
+
+    // Stream stdout into `path`, returning the number of bytes written.
+    fn run_to_file<'a>(&'a self, args: &'a [&'a str], path: &'a Path) -> RunFuture<'a, u64>;
+}
+
+// Runs the real talosctl binary.
+pub struct TalosctlRunner;
+
+impl CommandRunner for TalosctlRunner {
+    fn run<'a>(&'a self, args: &'a [&'a str]) -> RunFuture<'a, String> {
+        Box::pin(crate::run_talosctl(args))
+    }
+
+    fn run_with_stderr<'a>(&'a self, args: &'a [&'a str]) -> RunFuture<'a, String> {
+        Box::pin(crate::run_talosctl_with_stderr(args))
+    }
+
+    fn run_bounded<'a>(
+        &'a self,
+        args: &'a [&'a str],
+        window: Duration,
+    ) -> RunFuture<'a, (String, bool)> {
+        Box::pin(crate::run_talosctl_bounded(args, window))
+    }
+
+    fn run_to_file<'a>(&'a self, args: &'a [&'a str], path: &'a Path) -> RunFuture<'a, u64> {
+        Box::pin(crate::run_talosctl_to_file(args, path))
+    }
+}
+
+// Stands in for talosctl when the startup preflight failed, answering every call
+// with the reason so each tool error says what to fix.
+pub struct UnavailableRunner {
+    pub reason: String,
+}
+
+impl UnavailableRunner {
+    fn fail<T>(&self) -> RunFuture<'_, T> {
+        Box::pin(async move {
+            Err(anyhow!(
+                "talosctl is unavailable: {}; fix this and restart the server",
+                self.reason
+            ))
+        })
+    }
+}
+
+impl CommandRunner for UnavailableRunner {
+    fn run<'a>(&'a self, _args: &'a [&'a str]) -> RunFuture<'a, String> {
+        self.fail()
+    }
+
+    fn run_with_stderr<'a>(&'a self, _args: &'a [&'a str]) -> RunFuture<'a, String> {
+        self.fail()
+    }
+
+    fn run_bounded<'a>(
+        &'a self,
+        _args: &'a [&'a str],
+        _window: Duration,
+    ) -> RunFuture<'a, (String, bool)> {
+        self.fail()
+    }
+
+    fn run_to_file<'a>(&'a self, _args: &'a [&'a str], _path: &'a Path) -> RunFuture<'a, u64> {
+        self.fail()
+    }
+}
+
+// Records the commands a call would run, for `explain`, without running anything. Every
+// command answers with empty output, so handlers carry on as if it had succeeded; the
+// call runs with `explaining()` set so safety pre-flights and local file I/O are skipped.
+#[derive(Default)]
+pub struct ExplainRunner {
+    commands: std::sync::Mutex<Vec<Vec<String>>>,
+}
+
+impl ExplainRunner {
+    // The recorded argument vectors, talosconfig paths redacted.
+    pub fn commands(&self) -> Vec<Vec<String>> {
+        self.commands.lock().unwrap().clone()
+    }
+
+    fn record(&self, args: &[&str]) {
+        self.commands
+            .lock()
+            .unwrap()
+            .push(crate::reported_args(args));
+    }
+}
+
+impl CommandRunner for ExplainRunner {
+    fn run<'a>(&'a self, args: &'a [&'a str]) -> RunFuture<'a, String> {
+        self.record(args);
+        Box::pin(async { Ok(String::new()) })
+    }
+
+    fn run_with_stderr<'a>(&'a self, args: &'a [&'a str]) -> RunFuture<'a, String> {
+        self.run(args)
+    }
+
+    fn run_bounded<'a>(
+        &'a self,
+        args: &'a [&'a str],
+        _window: Duration,
+    ) -> RunFuture<'a, (String, bool)> {
+        self.record(args);
+        Box::pin(async { Ok((String::new(), true)) })
+    }
+
+    fn run_to_file<'a>(&'a self, args: &'a [&'a str], _path: &'a Path) -> RunFuture<'a, u64> {
+        self.record(args);
+        Box::pin(async { Ok(0) })
+    }
+}
+
+// Records every argument vector and answers with canned output.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MockRunner {
+    pub calls: std::sync::Mutex<Vec<Vec<String>>>,
+    pub output: String,
+    // How long each call takes to answer, for exercising in-flight requests
+    pub delay: Duration,
+    // Calls with any of these arguments fail, as if the node were unreachable
+    pub fail_for: Vec<String>,
+}
+
+#[cfg(test)]
+impl MockRunner {
+    pub fn with_output(output: &str) -> Self {
+        MockRunner {
+            output: output.to_string(),
+            ..Default::default()
+        }
+    }
+
+    // The argument vectors of all calls so far.
+    pub fn calls(&self) -> Vec<Vec<String>> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn record(&self, args: &[&str]) {
+        let args = args.iter().map(|a| a.to_string()).collect();
+        self.calls.lock().unwrap().push(args);
+    }
+}
+
+#[cfg(test)]
+impl CommandRunner for MockRunner {
+    fn run<'a>(&'a self, args: &'a [&'a str]) -> RunFuture<'a, String> {
+        self.record(args);
+        Box::pin(async move {
+            tokio::time::sleep(self.delay).await;
+            if let Some(arg) = args.iter().find(|a| self.fail_for.iter().any(|f| f == *a)) {
+                return Err(anyhow!("{}: connection refused", arg));
+            }
+            Ok(self.output.clone())
+        })
+    }
+
+    fn run_with_stderr<'a>(&'a self, args: &'a [&'a str]) -> RunFuture<'a, String> {
+        self.run(args)
+    }
+
+    fn run_bounded<'a>(
+        &'a self,
+        args: &'a [&'a str],
+        _window: Duration,
+    ) -> RunFuture<'a, (String, bool)> {
+        self.record(args);
+        Box::pin(async move { Ok((self.output.clone(), true)) })
+    }
+
+    fn run_to_file<'a>(&'a self, args: &'a [&'a str], _path: &'a Path) -> RunFuture<'a, u64> {
+        self.record(args);
+        Box::pin(async move { Ok(self.output.len() as u64) })
+    }
+}
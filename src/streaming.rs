@@ -0,0 +1,630 @@
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{oneshot, Mutex};
+use tracing::Instrument;
+
+// Stdout shared between the RPC loop and background streaming tasks.
+pub type SharedStdout = Arc<Mutex<Box<dyn AsyncWrite + Send + Unpin>>>;
+
+// Cancellation senders for in-flight requests, keyed by request id.
+pub type InFlight = Arc<Mutex<HashMap<String, oneshot::Sender<()>>>>;
+
+// Default time a streaming tool runs for when no duration is given.
+const DEFAULT_STREAM_DURATION: &str = "60s";
+
+// How often a long-running call reports that it is still going.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+// Non-streaming tools slow enough to report progress while they run.
+const PROGRESS_TOOLS: &[&str] = &[
+    "get_health",
+    "upgrade_node",
+    "upgrade_k8s",
+    "capture_packets",
+    "wait_node_ready",
+    "get_support_bundle",
+    "image_pull",
+];
+
+// A streaming tool call extracted from a request.
+pub struct StreamingCall {
+    name: String,
+    params_map: HashMap<String, Value>,
+    // Whether the call came through tools/call and needs MCP content wrapping
+    wrap_content: bool,
+    progress_token: Value,
+}
+
+// Everything a streaming tool needs to emit notifications and observe cancellation.
+struct StreamContext {
+    stdout: SharedStdout,
+    progress_token: Value,
+    cancel: oneshot::Receiver<()>,
+}
+
+// Outcome of streaming a talosctl command.
+struct StreamSummary {
+    items: Vec<Value>,
+    stop_reason: &'static str,
+}
+
+// Tools that stream output and must run outside the synchronous dispatch path.
+pub fn is_streaming_tool(name: &str) -> bool {
+    matches!(name, "watch_events" | "watch_resource")
+}
+
+// Whether a call streams: a streaming tool, or get_logs or dmesg with follow set. An
+// `explain` call never streams, as it runs nothing.
+fn is_streaming_call(name: &str, arguments: Option<&Value>) -> bool {
+    let explain = arguments
+        .and_then(|a| a.get("explain"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    if explain {
+        return false;
+    }
+    is_streaming_tool(name)
+        || (matches!(name, "get_logs" | "dmesg")
+            && arguments
+                .and_then(|a| a.get("follow"))
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false))
+}
+
+// Write a single JSON-RPC message as one line on the shared stdout.
+pub async fn write_message(stdout: &SharedStdout, message: &str) -> Result<()> {
+    let mut out = stdout.lock().await;
+    out.write_all(format!("{}\n", message).as_bytes()).await?;
+    out.flush().await?;
+    Ok(())
+}
+
+// Detect a streaming tool invoked either directly or through tools/call.
+pub fn streaming_call(method: &str, params: Option<&Value>) -> Option<StreamingCall> {
+    let (name, arguments, wrap_content) = if method == "tools/call" {
+        let params = params?;
+        let name = params.get("name").and_then(|v| v.as_str())?;
+        (name, params.get("arguments"), true)
+    } else if crate::tools::direct_tool_calls() {
+        (method, params, false)
+    } else {
+        return None;
+    };
+
+    if !is_streaming_call(name, arguments) {
+        return None;
+    }
+
+    // Progress notifications are keyed by the client's token when one is supplied
+    let progress_token = params
+        .and_then(|p| p.get("_meta"))
+        .and_then(|m| m.get("progressToken"))
+        .cloned()
+        .unwrap_or(Value::Null);
+
+    Some(StreamingCall {
+        name: name.to_string(),
+        params_map: crate::extract_params(arguments),
+        wrap_content,
+        progress_token,
+    })
+}
+
+// Register a streaming call for cancellation and run it in the background.
+pub async fn spawn_streaming_call(
+    call: StreamingCall,
+    id: Option<Value>,
+    stdout: SharedStdout,
+    in_flight: InFlight,
+) {
+    let (cancel_tx, cancel_rx) = oneshot::channel();
+    let key = id.as_ref().map(|id| id.to_string());
+    if let Some(ref key) = key {
+        in_flight.lock().await.insert(key.clone(), cancel_tx);
+    }
+
+    let progress_token = if call.progress_token.is_null() {
+        id.clone().unwrap_or(Value::Null)
+    } else {
+        call.progress_token.clone()
+    };
+
+    let call = async move {
+        let ctx = StreamContext {
+            stdout: stdout.clone(),
+            progress_token,
+            cancel: cancel_rx,
+        };
+        let params = json!(call.params_map);
+        let span = crate::request_span(&call.name, id.as_ref(), Some(&params));
+        let started = std::time::Instant::now();
+        let result = run_streaming_tool(&call.name, &call.params_map, ctx)
+            .instrument(span.clone())
+            .await;
+        crate::log_request(&span, Some(&params), Some(&result), started.elapsed());
+
+        // A missing registration means notifications/cancelled already claimed it,
+        // and cancelled requests get no response
+        let cancelled = match key {
+            Some(ref key) => in_flight.lock().await.remove(key).is_none(),
+            None => false,
+        };
+        if cancelled {
+            return;
+        }
+
+        let result = if call.wrap_content {
+            result.map(|content| crate::tool_content(&content))
+        } else {
+            result
+        };
+
+        match crate::build_response(id, result) {
+            Ok(resp_json) => {
+                if let Err(e) = write_message(&stdout, &resp_json).await {
+                    tracing::error!(parent: &span, error = %e, "Failed to write streaming response");
+                }
+            }
+            Err(e) => {
+                tracing::error!(parent: &span, error = %e, "Failed to serialize streaming response")
+            }
+        }
+    };
+    tokio::spawn(call.in_current_span());
+}
+
+// Register a request so notifications/cancelled can abort it. Done before the request
+// is spawned, so a cancellation read right after it isn't missed.
+pub async fn register_cancel(
+    in_flight: &InFlight,
+    id: Option<&Value>,
+) -> Option<(String, oneshot::Receiver<()>)> {
+    let key = id?.to_string();
+    let (cancel_tx, cancel_rx) = oneshot::channel();
+    in_flight.lock().await.insert(key.clone(), cancel_tx);
+    Some((key, cancel_rx))
+}
+
+// Run a registered request's handler until it finishes or is cancelled. Cancelling
+// drops the handler, which kills any talosctl child it is waiting on (kill_on_drop),
+// and returns None: cancelled requests get no response.
+pub async fn run_cancellable<T>(
+    in_flight: &InFlight,
+    registration: Option<(String, oneshot::Receiver<()>)>,
+    handler: impl std::future::Future<Output = T>,
+) -> Option<T> {
+    let Some((key, cancel)) = registration else {
+        return Some(handler.await);
+    };
+    tokio::select! {
+        output = handler => {
+            in_flight.lock().await.remove(&key);
+            Some(output)
+        }
+        Ok(()) = cancel => {
+            tracing::info!(request = %key, "Request cancelled by the client");
+            None
+        }
+    }
+}
+
+// Abort the in-flight call named by a notifications/cancelled message.
+pub async fn cancel_request(in_flight: &InFlight, params: Option<&Value>) {
+    let Some(request_id) = params.and_then(|p| p.get("requestId")) else {
+        return;
+    };
+    if let Some(cancel) = in_flight.lock().await.remove(&request_id.to_string()) {
+        let _ = cancel.send(());
+    }
+}
+
+async fn run_streaming_tool(
+    name: &str,
+    params_map: &HashMap<String, Value>,
+    ctx: StreamContext,
+) -> Result<Value> {
+    let mut params_map = params_map.clone();
+    let result = match crate::nodes::prepare_node_params(&mut params_map).await {
+        Ok(resolutions) => {
+            let call = async {
+                match name {
+                    "watch_events" => watch_events(&params_map, ctx).await,
+                    "watch_resource" => watch_resource(&params_map, ctx).await,
+                    "get_logs" => follow_logs(&params_map, ctx).await,
+                    "dmesg" => follow_dmesg(&params_map, ctx).await,
+                    _ => Err(anyhow!("Unknown streaming tool: {}", name)),
+                }
+            };
+            let result = crate::with_call_options(name, &params_map, call).await;
+            crate::nodes::with_resolutions(result, resolutions)
+        }
+        Err(e) => Err(e),
+    };
+    crate::audit::record(name, &params_map, &result);
+    crate::redact::apply(result)
+}
+
+// Follow talosctl events, emitting each event as a progress notification.
+async fn watch_events(params_map: &HashMap<String, Value>, ctx: StreamContext) -> Result<Value> {
+    let node = params_map
+        .get("node")
+        .and_then(|v| v.as_str())
+        .ok_or(invalid_params!("Missing node param"))?;
+    let duration_str = params_map
+        .get("duration")
+        .and_then(|v| v.as_str())
+        .unwrap_or(DEFAULT_STREAM_DURATION);
+    let duration = crate::parse_duration(duration_str)?;
+
+    let mut header: Option<Vec<String>> = None;
+    let summary = stream_talosctl(&["--nodes", node, "events"], duration, ctx, |line| {
+        parse_event_line(&mut header, line)
+    })
+    .await?;
+
+    Ok(json!({
+        "node": node,
+        "duration": duration_str,
+        "stop_reason": summary.stop_reason,
+        "event_count": summary.items.len(),
+        "events": summary.items
+    }))
+}
+
+// Watch a Talos resource, emitting each created/updated/deleted change as a progress notification.
+async fn watch_resource(params_map: &HashMap<String, Value>, ctx: StreamContext) -> Result<Value> {
+    let node = params_map
+        .get("node")
+        .and_then(|v| v.as_str())
+        .ok_or(invalid_params!("Missing node param"))?;
+    let resource = params_map
+        .get("resource")
+        .and_then(|v| v.as_str())
+        .ok_or(invalid_params!("Missing resource param"))?;
+    let id = params_map.get("id").and_then(|v| v.as_str());
+    let namespace = crate::resources::namespace_param(params_map)?;
+    let duration_str = params_map
+        .get("duration")
+        .and_then(|v| v.as_str())
+        .unwrap_or(DEFAULT_STREAM_DURATION);
+    let duration = crate::parse_duration(duration_str)?;
+
+    let mut args = vec!["--nodes", node, "get", resource];
+    if let Some(id) = id {
+        args.push(id);
+    }
+    crate::resources::push_namespace(&mut args, namespace);
+    args.extend(&["--output", "json", "--watch"]);
+
+    // The JSON writer pretty-prints one object per event, closing with a bare "}"
+    let mut buffer = String::new();
+    let summary = stream_talosctl(&args, duration, ctx, |line| {
+        buffer.push_str(line);
+        buffer.push('\n');
+        if line != "}" {
+            return None;
+        }
+        let parsed = serde_json::from_str::<Value>(&buffer);
+        buffer.clear();
+        parsed.ok().and_then(resource_change)
+    })
+    .await?;
+
+    Ok(json!({
+        "node": node,
+        "resource": resource,
+        "id": id,
+        "namespace": namespace,
+        "duration": duration_str,
+        "stop_reason": summary.stop_reason,
+        "change_count": summary.items.len(),
+        "changes": summary.items
+    }))
+}
+
+// Follow a service's logs, emitting each new line as a progress notification until
+// the client cancels or max_duration elapses.
+async fn follow_logs(params_map: &HashMap<String, Value>, ctx: StreamContext) -> Result<Value> {
+    let node = params_map
+        .get("node")
+        .and_then(|v| v.as_str())
+        .ok_or(invalid_params!("Missing node param"))?;
+    let service = params_map
+        .get("service")
+        .and_then(|v| v.as_str())
+        .ok_or(invalid_params!("Missing service param"))?;
+    if params_map.contains_key("since") || params_map.contains_key("until") {
+        return Err(invalid_params!(
+            "since/until cannot be combined with follow"
+        ));
+    }
+    if params_map.contains_key("services") {
+        return Err(invalid_params!(
+            "services cannot be combined with follow; follow one service at a time"
+        ));
+    }
+    let tail = params_map
+        .get("tail")
+        .and_then(|v| v.as_i64())
+        .map(|t| t.to_string());
+    let kubernetes = params_map
+        .get("kubernetes")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let duration_str = params_map
+        .get("max_duration")
+        .and_then(|v| v.as_str())
+        .unwrap_or(DEFAULT_STREAM_DURATION);
+    let duration = crate::parse_duration(duration_str)?;
+
+    let mut args = vec!["--nodes", node, "logs", service, "--follow"];
+    if let Some(ref tail) = tail {
+        args.extend(&["--tail", tail]);
+    }
+    if kubernetes {
+        args.push("--kubernetes");
+    }
+
+    let summary = stream_talosctl(&args, duration, ctx, |line| {
+        (!line.trim().is_empty()).then(|| {
+            json!({
+                "timestamp": chrono::Utc::now().to_rfc3339(),
+                "message": line
+            })
+        })
+    })
+    .await?;
+
+    let lines: Vec<&str> = summary
+        .items
+        .iter()
+        .filter_map(|item| item["message"].as_str())
+        .collect();
+    Ok(json!({
+        "node": node,
+        "service": service,
+        "max_duration": duration_str,
+        "stop_reason": summary.stop_reason,
+        "line_count": lines.len(),
+        "logs": lines.join("\n")
+    }))
+}
+
+// Follow the kernel ring buffer, emitting each new message as a progress notification
+// until the client cancels or max_duration elapses. Messages already in the buffer are
+// skipped, so `tail` has nothing to apply to.
+async fn follow_dmesg(params_map: &HashMap<String, Value>, ctx: StreamContext) -> Result<Value> {
+    let targets = crate::nodes::node_targets(params_map)?;
+    if params_map.contains_key("tail") {
+        return Err(invalid_params!("tail cannot be combined with follow"));
+    }
+    let duration_str = params_map
+        .get("max_duration")
+        .and_then(|v| v.as_str())
+        .unwrap_or(DEFAULT_STREAM_DURATION);
+    let duration = crate::parse_duration(duration_str)?;
+
+    let node_list = targets.join(",");
+    let args = ["--nodes", node_list.as_str(), "dmesg", "--follow", "--tail"];
+    let summary = stream_talosctl(&args, duration, ctx, |line| {
+        (!line.trim().is_empty()).then(|| {
+            json!({
+                "timestamp": chrono::Utc::now().to_rfc3339(),
+                "message": line
+            })
+        })
+    })
+    .await?;
+
+    let lines: Vec<&str> = summary
+        .items
+        .iter()
+        .filter_map(|item| item["message"].as_str())
+        .collect();
+    Ok(json!({
+        "nodes": targets,
+        "max_duration": duration_str,
+        "stop_reason": summary.stop_reason,
+        "line_count": lines.len(),
+        "dmesg": lines.join("\n")
+    }))
+}
+
+// Convert a watched resource record into a {type, resource} change event.
+fn resource_change(mut record: Value) -> Option<Value> {
+    let event = record.as_object_mut()?.remove("event")?;
+    let change_type = match event.as_str()? {
+        "created" => "created",
+        "updated" => "updated",
+        "destroyed" => "deleted",
+        // Bootstrapped and other markers carry no resource change
+        _ => return None,
+    };
+    let id = record
+        .pointer("/metadata/id")
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown")
+        .to_string();
+
+    Some(json!({
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "type": change_type,
+        "message": format!("{} {}", change_type, id),
+        "resource": record
+    }))
+}
+
+// Run talosctl and forward each parsed output line until it exits, the
+// duration elapses or the call is cancelled.
+async fn stream_talosctl<F>(
+    args: &[&str],
+    duration: Duration,
+    ctx: StreamContext,
+    mut parse_line: F,
+) -> Result<StreamSummary>
+where
+    F: FnMut(&str) -> Option<Value>,
+{
+    let mut child = crate::talosctl_command()?
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to execute talosctl")?;
+    let child_stdout = child
+        .stdout
+        .take()
+        .ok_or(anyhow!("Failed to capture talosctl output"))?;
+    let mut lines = BufReader::new(child_stdout).lines();
+
+    let deadline = tokio::time::sleep(duration);
+    tokio::pin!(deadline);
+    let mut cancel = ctx.cancel;
+    let mut items = Vec::new();
+
+    let stop_reason = loop {
+        tokio::select! {
+            line = lines.next_line() => match line? {
+                Some(line) => {
+                    if let Some(item) = parse_line(&line) {
+                        send_progress(&ctx.stdout, &ctx.progress_token, items.len() + 1, &item).await?;
+                        items.push(item);
+                    }
+                }
+                None => break "exited",
+            },
+            _ = &mut deadline => break "duration",
+            _ = &mut cancel => break "cancelled",
+        }
+    };
+
+    if stop_reason == "exited" {
+        let status = child.wait().await.context("Failed to wait for talosctl")?;
+        if !status.success() {
+            let mut err = String::new();
+            if let Some(mut stderr) = child.stderr.take() {
+                stderr.read_to_string(&mut err).await.ok();
+            }
+            return Err(crate::TalosctlError::new(args, status.code(), err).into());
+        }
+    } else {
+        let _ = child.kill().await;
+    }
+
+    Ok(StreamSummary { items, stop_reason })
+}
+
+// The client's progress token for a call to one of PROGRESS_TOOLS, invoked directly
+// or through tools/call, if it asked for progress.
+pub fn progress_token(method: &str, params: Option<&Value>) -> Option<Value> {
+    let name = if method == "tools/call" {
+        params?.get("name")?.as_str()?
+    } else if crate::tools::direct_tool_calls() {
+        method
+    } else {
+        return None;
+    };
+    if !PROGRESS_TOOLS.contains(&name) {
+        return None;
+    }
+    params?.get("_meta")?.get("progressToken").cloned()
+}
+
+// Run a request's handler, emitting a progress notification with the elapsed time
+// every PROGRESS_INTERVAL until it finishes, so the client knows it is still running.
+pub async fn with_progress<T>(
+    stdout: &SharedStdout,
+    progress_token: Option<Value>,
+    handler: impl std::future::Future<Output = T>,
+) -> T {
+    let Some(progress_token) = progress_token else {
+        return handler.await;
+    };
+    let started = tokio::time::Instant::now();
+    let mut ticks = tokio::time::interval_at(started + PROGRESS_INTERVAL, PROGRESS_INTERVAL);
+    tokio::pin!(handler);
+    loop {
+        tokio::select! {
+            output = &mut handler => return output,
+            _ = ticks.tick() => {
+                let elapsed = started.elapsed().as_secs();
+                let notification = json!({
+                    "jsonrpc": "2.0",
+                    "method": "notifications/progress",
+                    "params": {
+                        "progressToken": progress_token,
+                        "progress": elapsed,
+                        "message": format!("Still running, {}s elapsed", elapsed)
+                    }
+                });
+                if let Err(e) = write_message(stdout, &notification.to_string()).await {
+                    tracing::error!(error = %e, "Failed to write progress notification");
+                }
+            }
+        }
+    }
+}
+
+// Emit an MCP progress notification carrying one streamed item.
+async fn send_progress(
+    stdout: &SharedStdout,
+    progress_token: &Value,
+    progress: usize,
+    item: &Value,
+) -> Result<()> {
+    let notification = json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/progress",
+        "params": {
+            "progressToken": progress_token,
+            "progress": progress,
+            "message": item
+                .get("message")
+                .and_then(|m| m.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| item.to_string()),
+            "data": item
+        }
+    });
+    write_message(stdout, &notification.to_string()).await
+}
+
+// Parse one line of `talosctl events` table output into {timestamp, type, message}.
+fn parse_event_line(header: &mut Option<Vec<String>>, line: &str) -> Option<Value> {
+    if line.trim().is_empty() {
+        return None;
+    }
+
+    let Some(columns) = header.as_ref() else {
+        *header = Some(line.split_whitespace().map(str::to_string).collect());
+        return None;
+    };
+
+    // MESSAGE is the last column and may contain spaces
+    let message_idx = columns
+        .iter()
+        .position(|c| c == "MESSAGE")
+        .unwrap_or(columns.len());
+    let (fields, message) = crate::split_columns(line, message_idx);
+    let column = |name: &str| {
+        columns
+            .iter()
+            .position(|c| c == name)
+            .and_then(|i| fields.get(i))
+            .copied()
+    };
+
+    Some(json!({
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "type": column("EVENT").unwrap_or("unknown"),
+        "message": message,
+        "node": column("NODE"),
+        "id": column("ID")
+    }))
+}
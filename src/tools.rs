@@ -1,4 +1,237 @@
 use serde_json::{json, Value};
+use std::env;
+use std::sync::OnceLock;
+
+/// Talos resource namespaces accepted by the `namespace` param of resource-backed tools
+pub const TALOS_NAMESPACES: &[&str] = &[
+    "cluster",
+    "config",
+    "controlplane",
+    "cri",
+    "files",
+    "hardware",
+    "k8s",
+    "kubespan",
+    "meta",
+    "network",
+    "network-config",
+    "perf",
+    "runtime",
+    "secrets",
+    "siderolink",
+    "v1alpha1",
+];
+
+/// Tools whose `timeout` param has its own meaning rather than limiting each talosctl command
+pub const TOOLS_WITH_OWN_TIMEOUT: &[&str] = &[
+    "get_health",
+    "service",
+    "wait_node_ready",
+    "apply_config",
+    "upgrade_node",
+];
+
+/// Tools that pass their own `--endpoints`, so a per-call `endpoint`/`endpoints` would conflict
+pub const TOOLS_WITH_OWN_ENDPOINTS: &[&str] = &["ping_node"];
+
+/// Modes accepted by `talosctl apply-config --mode`
+pub const APPLY_CONFIG_MODES: &[&str] = &["auto", "no-reboot", "reboot", "staged", "try"];
+
+/// Modes accepted by `talosctl reboot --mode`
+pub const REBOOT_MODES: &[&str] = &["default", "powercycle"];
+
+/// Methods accepted by `talosctl wipe disk --method`
+pub const WIPE_METHODS: &[&str] = &["fast", "zeroes"];
+
+/// Presets accepted by `talosctl cgroups --preset`
+pub const CGROUP_PRESETS: &[&str] = &["cpu", "cpuset", "io", "memory", "process", "psi", "swap"];
+
+/// Containerd namespaces accepted by `talosctl image --namespace`
+pub const IMAGE_NAMESPACES: &[&str] = &["cri", "system"];
+
+/// Prefix of the canonical names tools are advertised under
+pub const TOOL_NAME_PREFIX: &str = "talos_";
+
+/// Canonical name a tool is advertised under, from its handler name
+pub fn canonical_tool_name(name: &str) -> String {
+    format!("{}{}", TOOL_NAME_PREFIX, name)
+}
+
+/// Handler name of a tool called by its canonical name or by its legacy unprefixed alias
+pub fn resolve_tool_name(name: &str) -> &str {
+    name.strip_prefix(TOOL_NAME_PREFIX).unwrap_or(name)
+}
+
+/// Whether legacy direct-by-name tool calls are enabled with TALOS_DIRECT_TOOL_CALLS,
+/// for clients that predate tools being reachable only through `tools/call`
+pub fn direct_tool_calls() -> bool {
+    env::var("TALOS_DIRECT_TOOL_CALLS").is_ok_and(|v| !v.is_empty() && v != "0")
+}
+
+/// Handler names of all tools, each paired with whether it is annotated read-only
+fn tool_names() -> &'static [(String, bool)] {
+    static TOOLS: OnceLock<Vec<(String, bool)>> = OnceLock::new();
+    TOOLS.get_or_init(|| {
+        get_all_tool_schemas()["tools"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|tool| {
+                let read_only = tool["annotations"]["readOnlyHint"] == true;
+                tool["name"]
+                    .as_str()
+                    .map(|name| (name.to_string(), read_only))
+            })
+            .collect()
+    })
+}
+
+/// Whether a handler name belongs to a tool
+pub fn is_tool(name: &str) -> bool {
+    tool_names().iter().any(|(tool, _)| tool == name)
+}
+
+/// Whether a tool is annotated read-only, meaning its talosctl commands can safely be repeated
+pub fn is_read_only(name: &str) -> bool {
+    tool_names()
+        .iter()
+        .any(|(tool, read_only)| *read_only && tool == name)
+}
+
+/// Shared schema for the per-call `timeout` param every other non-streaming tool accepts
+pub fn command_timeout_property() -> Value {
+    json!({
+        "type": "string",
+        "description": "Maximum time each talosctl command may run, e.g. 30s or 5m (defaults to TALOSCTL_TIMEOUT, or 120s)"
+    })
+}
+
+/// Schema for the `endpoint` param added to every tool: the API endpoint talosctl
+/// connects through, which may differ from the node it targets
+pub fn endpoint_property() -> Value {
+    json!({
+        "type": "string",
+        "description": "Talos API endpoint to connect through (talosctl --endpoints), e.g. a control plane that can reach the target node; defaults to the talosconfig endpoints"
+    })
+}
+
+/// Schema for the `explain` param added to every tool
+pub fn explain_property() -> Value {
+    json!({
+        "type": "boolean",
+        "description": "Return the talosctl commands this call would run, talosconfig path redacted, without running them (defaults to false)",
+        "default": false
+    })
+}
+
+/// Schema for the `context` param added to every tool
+pub fn context_property() -> Value {
+    json!({
+        "type": "string",
+        "description": "talosconfig context to run against (talosctl --context), to target another cluster without switching the default context; defaults to the current context"
+    })
+}
+
+/// Schema for the `endpoints` param added to every tool
+pub fn endpoints_property() -> Value {
+    json!({
+        "type": "array",
+        "items": {"type": "string"},
+        "description": "Several Talos API endpoints to connect through, instead of endpoint"
+    })
+}
+
+/// Shared schema for the `namespace` param of tools backed by `talosctl get`
+fn namespace_property() -> Value {
+    json!({
+        "type": "string",
+        "description": "Resource namespace (default is to use default namespace per resource)",
+        "enum": TALOS_NAMESPACES
+    })
+}
+
+/// Shared schema for the `strict` param of etcd tools
+fn strict_property() -> Value {
+    json!({
+        "type": "boolean",
+        "description": "Refuse to run unless the node is a control plane (defaults to true; set false to skip the machine type check)",
+        "default": true
+    })
+}
+
+/// Shared schema for the `confirm` param of destructive tools
+fn confirm_property() -> Value {
+    json!({
+        "type": "boolean",
+        "description": "Must be true to proceed with this destructive operation"
+    })
+}
+
+/// Shared schema for the `dry_run` param of tools that change node state
+fn dry_run_property() -> Value {
+    json!({
+        "type": "boolean",
+        "description": "Only report what would change, without touching the node (defaults to false)",
+        "default": false
+    })
+}
+
+/// Shared schema for the `nodes` param of tools that can query several nodes at once
+fn nodes_property() -> Value {
+    json!({
+        "type": "array",
+        "items": {"type": "string"},
+        "description": "IP addresses or hostnames of several nodes to query concurrently, instead of node; results and errors are keyed by node, so an unreachable node doesn't fail the others"
+    })
+}
+
+/// Shared schema for the `max_bytes` param of tools that can return large text output
+fn max_bytes_property() -> Value {
+    json!({
+        "type": "integer",
+        "description": "Truncate the output beyond this many bytes, flagging the result with truncated and total_bytes (defaults to TALOS_MAX_OUTPUT_BYTES, or 1 MiB; 0 for no limit)",
+        "minimum": 0
+    })
+}
+
+/// Shared schema for the `selector` param of tools backed by `talosctl get`
+fn selector_property() -> Value {
+    json!({
+        "type": "string",
+        "description": "Comma-separated key=value or key!=value filters on resource fields (spec fields, metadata, labels, or dotted paths), e.g. 'transport=nvme'; forces JSON output and reports matched vs total"
+    })
+}
+
+/// Shared schema for the `jsonpath` param of tools backed by `talosctl get`
+fn jsonpath_property() -> Value {
+    json!({
+        "type": "string",
+        "description": "JSONPath expression to print when output is jsonpath, e.g. '{.spec.address}' (required for jsonpath output)"
+    })
+}
+
+/// Annotations for tools that only read cluster state, so clients may auto-approve them
+fn read_only_annotations() -> Value {
+    json!({
+        "readOnlyHint": true
+    })
+}
+
+/// Annotations for tools that change node or local state without destroying anything
+fn mutating_annotations() -> Value {
+    json!({
+        "readOnlyHint": false,
+        "destructiveHint": false
+    })
+}
+
+/// Annotations for tools that disrupt nodes, replace their configuration or drop data
+fn destructive_annotations() -> Value {
+    json!({
+        "readOnlyHint": false,
+        "destructiveHint": true
+    })
+}
 
 /// Get all tool schemas with descriptions and parameter definitions
 pub fn get_all_tool_schemas() -> Value {
@@ -9,7 +242,9 @@ pub fn get_all_tool_schemas() -> Value {
             get_stats_schema(),
             get_processes_schema(),
             get_memory_verbose_schema(),
+            get_cgroups_schema(),
             get_cpu_memory_usage_schema(),
+            get_dashboard_schema(),
 
             // File system operations
             get_list_schema(),
@@ -18,46 +253,89 @@ pub fn get_all_tool_schemas() -> Value {
             get_usage_schema(),
             get_mounts_schema(),
 
+            // Container images
+            get_image_list_schema(),
+            get_image_pull_schema(),
+
             // Network operations
             get_interfaces_schema(),
             get_routes_schema(),
             get_netstat_schema(),
             get_capture_packets_schema(),
             get_network_io_cgroups_schema(),
+            get_ping_node_schema(),
             get_list_network_interfaces_schema(),
 
             // Service and logging
             get_dmesg_schema(),
             get_service_schema(),
+            get_services_schema(),
             get_restart_schema(),
             get_logs_schema(),
+            get_apiserver_logs_schema(),
+            get_kubelet_logs_schema(),
             get_events_schema(),
+            get_watch_events_schema(),
 
             // Storage and hardware
             get_disks_schema(),
             get_list_disks_schema(),
+            get_kernel_modules_schema(),
+            get_wipe_disk_schema(),
+
+            // Resource inspection
+            get_resource_schema(),
+            get_list_resource_types_schema(),
+            get_compare_resource_schema(),
+            get_watch_resource_schema(),
+
+            // Controller runtime inspection
+            get_inspect_dependencies_schema(),
+
+            // Talosconfig context introspection
+            get_config_info_schema(),
+            get_config_contexts_schema(),
+
+            // Cluster generation
+            get_gen_config_schema(),
+            get_gen_secrets_schema(),
 
             // Core cluster management
             get_health_schema(),
             get_version_schema(),
+            get_version_report_schema(),
+            get_k8s_version_schema(),
+            get_members_schema(),
+            get_metrics_schema(),
             get_time_schema(),
+            get_collect_diagnostics_schema(),
+            get_support_bundle_schema(),
 
             // Node management
             get_reboot_node_schema(),
+            get_wait_node_ready_schema(),
             get_shutdown_node_schema(),
             get_reset_node_schema(),
             get_upgrade_node_schema(),
             get_upgrade_k8s_schema(),
+            get_rollback_node_schema(),
 
             // Configuration management
             get_apply_config_schema(),
+            get_machine_config_schema(),
+            get_patch_config_schema(),
             get_validate_config_schema(),
+            get_kubeconfig_schema(),
 
             // etcd management
             get_etcd_status_schema(),
             get_etcd_members_schema(),
             get_bootstrap_etcd_schema(),
-            get_defrag_etcd_schema()
+            get_defrag_etcd_schema(),
+            get_etcd_snapshot_schema(),
+            get_etcd_restore_schema(),
+            get_etcd_remove_member_schema(),
+            get_etcd_forfeit_leadership_schema()
         ]
     })
 }
@@ -67,6 +345,7 @@ fn get_containers_schema() -> Value {
     json!({
         "name": "containers",
         "description": "List running containers on a Talos node with their current status",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
@@ -74,13 +353,14 @@ fn get_containers_schema() -> Value {
                     "type": "string",
                     "description": "IP address or hostname of the Talos node to query"
                 },
+                "nodes": nodes_property(),
                 "kubernetes": {
                     "type": "boolean",
                     "description": "Use the k8s.io containerd namespace to list Kubernetes containers (defaults to false)",
                     "default": false
                 }
             },
-            "required": ["node"]
+            "required": []
         }
     })
 }
@@ -89,6 +369,7 @@ fn get_stats_schema() -> Value {
     json!({
         "name": "stats",
         "description": "Get resource usage statistics (CPU, memory) for containers on a Talos node",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
@@ -96,13 +377,14 @@ fn get_stats_schema() -> Value {
                     "type": "string",
                     "description": "IP address or hostname of the Talos node to query"
                 },
+                "nodes": nodes_property(),
                 "kubernetes": {
                     "type": "boolean",
                     "description": "Use the k8s.io containerd namespace to get Kubernetes containers stats (defaults to false)",
                     "default": false
                 }
             },
-            "required": ["node"]
+            "required": []
         }
     })
 }
@@ -111,12 +393,41 @@ fn get_memory_verbose_schema() -> Value {
     json!({
         "name": "memory_verbose",
         "description": "Get detailed memory usage information from a Talos node",
+        "annotations": read_only_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to query"
+                }
+            },
+            "required": ["node"]
+        }
+    })
+}
+
+fn get_cgroups_schema() -> Value {
+    json!({
+        "name": "get_cgroups",
+        "description": "Show a Talos node's cgroup tree with the resource stats of a preset (cpu, memory, io, ...), optionally scoped to one cgroup and its children",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
                 "node": {
                     "type": "string",
                     "description": "IP address or hostname of the Talos node to query"
+                },
+                "preset": {
+                    "type": "string",
+                    "description": "Set of cgroup stats to show (defaults to cpu)",
+                    "enum": CGROUP_PRESETS,
+                    "default": "cpu"
+                },
+                "id": {
+                    "type": "string",
+                    "description": "Only show the cgroup with this name (e.g. kubepods or besteffort) and its children"
                 }
             },
             "required": ["node"]
@@ -128,6 +439,7 @@ fn get_list_schema() -> Value {
     json!({
         "name": "list",
         "description": "List files and directories at a specified path on a Talos node",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
@@ -168,6 +480,17 @@ fn get_list_schema() -> Value {
                         "type": "string",
                         "enum": ["f", "d", "l", "L"]
                     }
+                },
+                "offset": {
+                    "type": "integer",
+                    "description": "Skip this many entries, to page through large listings (defaults to 0)",
+                    "minimum": 0,
+                    "default": 0
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "Return at most this many entries, with total and has_more reported; page with offset",
+                    "minimum": 1
                 }
             },
             "required": ["node"]
@@ -179,6 +502,7 @@ fn get_read_schema() -> Value {
     json!({
         "name": "read",
         "description": "Read the contents of a file on a Talos node",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
@@ -189,6 +513,23 @@ fn get_read_schema() -> Value {
                 "path": {
                     "type": "string",
                     "description": "Full path to the file to read"
+                },
+                "head_lines": {
+                    "type": "integer",
+                    "description": "Only return the first N lines of the file (not combinable with tail_lines)",
+                    "minimum": 1
+                },
+                "tail_lines": {
+                    "type": "integer",
+                    "description": "Only return the last N lines of the file, e.g. the latest entries of a log",
+                    "minimum": 1
+                },
+                "max_bytes": max_bytes_property(),
+                "keep": {
+                    "type": "string",
+                    "description": "Which end of a file larger than max_bytes to return (defaults to head)",
+                    "enum": ["head", "tail"],
+                    "default": "head"
                 }
             },
             "required": ["node", "path"]
@@ -200,6 +541,7 @@ fn get_interfaces_schema() -> Value {
     json!({
         "name": "interfaces",
         "description": "Get detailed network interface information including addresses and links",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
@@ -207,16 +549,15 @@ fn get_interfaces_schema() -> Value {
                     "type": "string",
                     "description": "IP address or hostname of the Talos node to query"
                 },
-                "namespace": {
-                    "type": "string",
-                    "description": "Resource namespace (default is to use default namespace per resource)"
-                },
+                "namespace": namespace_property(),
                 "output": {
                     "type": "string",
                     "description": "Output mode (default: table)",
                     "enum": ["json", "table", "yaml", "jsonpath"],
                     "default": "table"
-                }
+                },
+                "jsonpath": jsonpath_property(),
+                "selector": selector_property()
             },
             "required": ["node"]
         }
@@ -227,6 +568,7 @@ fn get_routes_schema() -> Value {
     json!({
         "name": "routes",
         "description": "Get network routing table information for a Talos node",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
@@ -234,16 +576,15 @@ fn get_routes_schema() -> Value {
                     "type": "string",
                     "description": "IP address or hostname of the Talos node to query"
                 },
-                "namespace": {
-                    "type": "string",
-                    "description": "Resource namespace (default is to use default namespace per resource)"
-                },
+                "namespace": namespace_property(),
                 "output": {
                     "type": "string",
                     "description": "Output mode (default: table)",
                     "enum": ["json", "table", "yaml", "jsonpath"],
                     "default": "table"
-                }
+                },
+                "jsonpath": jsonpath_property(),
+                "selector": selector_property()
             },
             "required": ["node"]
         }
@@ -255,15 +596,32 @@ fn get_dmesg_schema() -> Value {
     json!({
         "name": "dmesg",
         "description": "Get kernel ring buffer messages (system logs) from a Talos node",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
                 "node": {
                     "type": "string",
                     "description": "IP address or hostname of the Talos node to query"
-                }
+                },
+                "nodes": nodes_property(),
+                "tail": {
+                    "type": "integer",
+                    "description": "Only return the last N kernel messages (not combinable with follow)",
+                    "minimum": 1
+                },
+                "follow": {
+                    "type": "boolean",
+                    "description": "Stream new kernel messages as progress notifications until cancelled or max_duration elapses, skipping those already in the buffer (defaults to false)",
+                    "default": false
+                },
+                "max_duration": {
+                    "type": "string",
+                    "description": "How long to follow kernel messages before returning, e.g. 30s or 5m (defaults to 60s; only used with follow)"
+                },
+                "max_bytes": max_bytes_property()
             },
-            "required": ["node"]
+            "required": []
         }
     })
 }
@@ -272,6 +630,7 @@ fn get_service_schema() -> Value {
     json!({
         "name": "service",
         "description": "Manage services on a Talos node (get status, start, stop, restart)",
+        "annotations": mutating_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
@@ -288,6 +647,16 @@ fn get_service_schema() -> Value {
                     "description": "Action to perform on the service (defaults to 'status')",
                     "enum": ["status", "start", "stop", "restart"],
                     "default": "status"
+                },
+                "wait": {
+                    "type": "boolean",
+                    "description": "After a start or restart, poll the service status until it reports healthy or the timeout elapses (defaults to false)",
+                    "default": false
+                },
+                "timeout": {
+                    "type": "string",
+                    "description": "Maximum time to wait for the service to become healthy, e.g. '60s' or '5m' (defaults to 60s)",
+                    "default": "60s"
                 }
             },
             "required": ["node", "service"]
@@ -295,10 +664,29 @@ fn get_service_schema() -> Value {
     })
 }
 
+fn get_services_schema() -> Value {
+    json!({
+        "name": "services",
+        "description": "List all services on a Talos node with their state, health and last event",
+        "annotations": read_only_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to query"
+                }
+            },
+            "required": ["node"]
+        }
+    })
+}
+
 fn get_restart_schema() -> Value {
     json!({
         "name": "restart",
         "description": "Restart a specific service on a Talos node",
+        "annotations": mutating_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
@@ -320,7 +708,8 @@ fn get_restart_schema() -> Value {
 fn get_copy_schema() -> Value {
     json!({
         "name": "copy",
-        "description": "Copy files to/from a Talos node",
+        "description": "Copy files or directories from a Talos node, streamed to a local tar archive (Talos does not support copying onto a node)",
+        "annotations": mutating_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
@@ -328,16 +717,21 @@ fn get_copy_schema() -> Value {
                     "type": "string",
                     "description": "IP address or hostname of the Talos node"
                 },
+                "direction": {
+                    "type": "string",
+                    "description": "Transfer direction; only from_node is supported by the Talos API",
+                    "enum": ["from_node", "to_node"]
+                },
                 "source": {
                     "type": "string",
-                    "description": "Source file path (local or remote)"
+                    "description": "Absolute file or directory path on the node"
                 },
                 "destination": {
                     "type": "string",
-                    "description": "Destination file path (local or remote)"
+                    "description": "Local path of the tar archive to write; must be inside TALOS_WORK_DIR, where relative paths are resolved, and the absolute path is returned"
                 }
             },
-            "required": ["node", "source", "destination"]
+            "required": ["node", "direction", "source", "destination"]
         }
     })
 }
@@ -347,6 +741,7 @@ fn get_disks_schema() -> Value {
     json!({
         "name": "disks",
         "description": "Get detailed disk information from a Talos node",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
@@ -354,15 +749,60 @@ fn get_disks_schema() -> Value {
                     "type": "string",
                     "description": "IP address or hostname of the Talos node to query"
                 },
-                "namespace": {
-                    "type": "string",
-                    "description": "Resource namespace (default is to use default namespace per resource)"
-                },
+                "namespace": namespace_property(),
                 "output": {
                     "type": "string",
                     "description": "Output mode (default: table)",
                     "enum": ["json", "table", "yaml", "jsonpath"],
                     "default": "table"
+                },
+                "jsonpath": jsonpath_property(),
+                "selector": selector_property()
+            },
+            "required": ["node"]
+        }
+    })
+}
+
+fn get_wipe_disk_schema() -> Value {
+    json!({
+        "name": "wipe_disk",
+        "description": "Wipe a disk or partition on a Talos node, e.g. before replacing or reusing it; the system disk and disks in use are refused by Talos (DESTRUCTIVE OPERATION)",
+        "annotations": destructive_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node"
+                },
+                "device": {
+                    "type": "string",
+                    "description": "Block device to wipe, as a name or /dev path (e.g., /dev/sdb, nvme1n1)"
+                },
+                "method": {
+                    "type": "string",
+                    "description": "Wipe method: fast clears the partition table and signatures, zeroes overwrites the whole device (defaults to fast)",
+                    "enum": WIPE_METHODS
+                },
+                "confirm": confirm_property()
+            },
+            "required": ["node", "device", "confirm"]
+        }
+    })
+}
+
+fn get_kernel_modules_schema() -> Value {
+    json!({
+        "name": "get_kernel_modules",
+        "description": "List the kernel modules loaded on a Talos node (from /proc/modules) with their size, users and state, to diagnose missing drivers",
+        "annotations": read_only_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to query"
                 }
             },
             "required": ["node"]
@@ -377,15 +817,15 @@ fn get_disks_schema() -> Value {
 fn get_health_schema() -> Value {
     json!({
         "name": "get_health",
-        "description": "Check the health status of the Talos cluster",
+        "description": "Check the health status of the Talos cluster, with per-node check results",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
                 "control_planes": {
                     "type": "array",
-                    "description": "Array of IP addresses or hostnames of control plane nodes (defaults to [192.168.1.77])",
-                    "items": {"type": "string"},
-                    "default": ["192.168.1.77"]
+                    "description": "Array of IP addresses or hostnames of control plane nodes (defaults to [TALOS_DEFAULT_NODE]; required if that is unset)",
+                    "items": {"type": "string"}
                 },
                 "worker_nodes": {
                     "type": "array",
@@ -423,10 +863,15 @@ fn get_health_schema() -> Value {
 fn get_version_schema() -> Value {
     json!({
         "name": "get_version",
-        "description": "Get Talos client version information",
+        "description": "Get Talos client version information, and the node's server version when a node is given",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "Node to report the server (Talos) version of; omit for the client version only"
+                },
                 "short": {
                     "type": "boolean",
                     "description": "Print the short version (defaults to false)",
@@ -437,75 +882,58 @@ fn get_version_schema() -> Value {
     })
 }
 
-fn get_processes_schema() -> Value {
+fn get_version_report_schema() -> Value {
     json!({
-        "name": "get_processes",
-        "description": "List running processes on a Talos node",
+        "name": "version_report",
+        "description": "Compare Talos and Kubernetes versions across the cluster's nodes, flagging nodes behind the newest version (e.g. after a partial upgrade)",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
-                "node": {
-                    "type": "string",
-                    "description": "IP address or hostname of the Talos node to query"
+                "control_planes": {
+                    "type": "array",
+                    "description": "Array of IP addresses or hostnames of control plane nodes (defaults to [TALOS_DEFAULT_NODE] if no nodes are given)",
+                    "items": {"type": "string"}
                 },
-                "sort": {
-                    "type": "string",
-                    "description": "Column to sort output by (defaults to 'rss')",
-                    "enum": ["rss", "cpu"],
-                    "default": "rss"
+                "worker_nodes": {
+                    "type": "array",
+                    "description": "Array of IP addresses or hostnames of worker nodes",
+                    "items": {"type": "string"}
                 }
-            },
-            "required": ["node"]
+            }
         }
     })
 }
 
-fn get_logs_schema() -> Value {
+fn get_members_schema() -> Value {
     json!({
-        "name": "get_logs",
-        "description": "Get service logs from a Talos node",
+        "name": "get_members",
+        "description": "List the cluster members Talos discovery knows about, with hostname, addresses, machine type and OS, independent of Kubernetes (e.g. to find nodes missing from kubectl get nodes)",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
                 "node": {
                     "type": "string",
                     "description": "IP address or hostname of the Talos node to query"
-                },
-                "service": {
-                    "type": "string",
-                    "description": "Name of the service to get logs for (e.g., kubelet, etcd)"
-                },
-                "tail": {
-                    "type": "integer",
-                    "description": "Number of lines to show from the end of the logs (e.g., 100)",
-                    "minimum": 1
-                },
-                "kubernetes": {
-                    "type": "boolean",
-                    "description": "Use the k8s.io containerd namespace to access Kubernetes containers (defaults to false)",
-                    "default": false
                 }
             },
-            "required": ["node", "service"]
+            "required": ["node"]
         }
     })
 }
 
-fn get_usage_schema() -> Value {
+fn get_k8s_version_schema() -> Value {
     json!({
-        "name": "get_usage",
-        "description": "Get disk usage information for a path on a Talos node",
+        "name": "get_k8s_version",
+        "description": "Get the Kubernetes version a Talos node runs: its kubelet and, on control plane nodes, the API server, controller manager and scheduler, read from their Talos config resources (e.g. before choosing versions for upgrade_k8s)",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
                 "node": {
                     "type": "string",
-                    "description": "IP address or hostname of the Talos node to query"
-                },
-                "path": {
-                    "type": "string",
-                    "description": "Path to check disk usage for (defaults to root /)",
-                    "default": "/"
+                    "description": "IP address or hostname of the Talos node to query; use a control plane node to also get control plane versions"
                 }
             },
             "required": ["node"]
@@ -513,16 +941,169 @@ fn get_usage_schema() -> Value {
     })
 }
 
-fn get_mounts_schema() -> Value {
+fn get_metrics_schema() -> Value {
     json!({
-        "name": "get_mounts",
-        "description": "Get filesystem mount information from a Talos node",
+        "name": "get_metrics",
+        "description": "Get this server's tool call metrics since it started: calls, errors, error rate and latency percentiles per tool, e.g. to spot slow or failing tools",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
-            "properties": {
-                "node": {
-                    "type": "string",
+            "properties": {}
+        }
+    })
+}
+
+fn get_processes_schema() -> Value {
+    json!({
+        "name": "get_processes",
+        "description": "List running processes on a Talos node",
+        "annotations": read_only_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to query"
+                },
+                "nodes": nodes_property(),
+                "sort": {
+                    "type": "string",
+                    "description": "Column to sort output by (defaults to 'rss')",
+                    "enum": ["rss", "cpu"],
+                    "default": "rss"
+                }
+            },
+            "required": []
+        }
+    })
+}
+
+fn get_logs_schema() -> Value {
+    json!({
+        "name": "get_logs",
+        "description": "Get service logs from a Talos node, for one service or several fetched concurrently",
+        "annotations": read_only_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to query"
+                },
+                "service": {
+                    "type": "string",
+                    "description": "Name of the service to get logs for (e.g., kubelet, etcd)"
+                },
+                "services": {
+                    "type": "array",
+                    "description": "Several services to fetch logs for concurrently instead of service; results are keyed by service name (not combinable with follow)",
+                    "items": {"type": "string"},
+                    "minItems": 1
+                },
+                "tail": {
+                    "type": "integer",
+                    "description": "Number of lines to show from the end of the logs (e.g., 100); applied after since/until filtering",
+                    "minimum": 1
+                },
+                "since": {
+                    "type": "string",
+                    "description": "Only return log lines at or after this time, as RFC3339 (e.g., '2024-05-01T10:00:00Z') or a duration ago (e.g., '15m')"
+                },
+                "until": {
+                    "type": "string",
+                    "description": "Only return log lines at or before this time, as RFC3339 or a duration ago (e.g., '5m')"
+                },
+                "kubernetes": {
+                    "type": "boolean",
+                    "description": "Use the k8s.io containerd namespace to access Kubernetes containers (defaults to false)",
+                    "default": false
+                },
+                "follow": {
+                    "type": "boolean",
+                    "description": "Keep following the logs, sending each new line as a progress notification until cancelled or max_duration elapses (defaults to false; not combinable with since/until)",
+                    "default": false
+                },
+                "max_duration": {
+                    "type": "string",
+                    "description": "How long to follow logs before returning, e.g. 30s or 5m (defaults to 60s; only used with follow)"
+                },
+                "max_bytes": max_bytes_property()
+            },
+            "required": ["node"]
+        }
+    })
+}
+
+fn get_apiserver_logs_schema() -> Value {
+    json!({
+        "name": "get_apiserver_logs",
+        "description": "Get kube-apiserver logs from a Talos control plane node (presets the service name and Kubernetes namespace)",
+        "annotations": read_only_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos control plane node to query"
+                },
+                "tail": {
+                    "type": "integer",
+                    "description": "Number of lines to show from the end of the logs (e.g., 100)",
+                    "minimum": 1
+                },
+                "max_bytes": max_bytes_property()
+            },
+            "required": ["node"]
+        }
+    })
+}
+
+fn get_kubelet_logs_schema() -> Value {
+    json!({
+        "name": "get_kubelet_logs",
+        "description": "Get kubelet service logs from a Talos node (presets the service name)",
+        "annotations": read_only_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to query"
+                },
+                "tail": {
+                    "type": "integer",
+                    "description": "Number of lines to show from the end of the logs (e.g., 100)",
+                    "minimum": 1
+                },
+                "max_bytes": max_bytes_property()
+            },
+            "required": ["node"]
+        }
+    })
+}
+
+fn get_usage_schema() -> Value {
+    json!({
+        "name": "get_usage",
+        "description": "Get disk usage for a path on a Talos node, or for several paths at once with a summary of the largest consumers, e.g. to find what is filling ephemeral storage",
+        "annotations": read_only_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
                     "description": "IP address or hostname of the Talos node to query"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "Path to check disk usage for (defaults to root /)",
+                    "default": "/"
+                },
+                "paths": {
+                    "type": "array",
+                    "description": "Several paths to check concurrently instead of path; the response ranks the largest entries across all of them",
+                    "items": {"type": "string"},
+                    "minItems": 1
                 }
             },
             "required": ["node"]
@@ -530,72 +1111,599 @@ fn get_mounts_schema() -> Value {
     })
 }
 
-fn get_time_schema() -> Value {
+fn get_mounts_schema() -> Value {
+    json!({
+        "name": "get_mounts",
+        "description": "Get filesystem mounts on a Talos node with size, used and available space (GB) and percent used, e.g. to find nearly full filesystems",
+        "annotations": read_only_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to query"
+                }
+            },
+            "required": ["node"]
+        }
+    })
+}
+
+fn image_namespace_property() -> Value {
+    json!({
+        "type": "string",
+        "description": "Containerd namespace: cri for Kubernetes images (default) or system for Talos' own",
+        "enum": IMAGE_NAMESPACES
+    })
+}
+
+fn get_image_list_schema() -> Value {
+    json!({
+        "name": "image_list",
+        "description": "List the container images cached on a Talos node with their digest and size",
+        "annotations": read_only_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to query"
+                },
+                "namespace": image_namespace_property()
+            },
+            "required": ["node"]
+        }
+    })
+}
+
+fn get_image_pull_schema() -> Value {
+    json!({
+        "name": "image_pull",
+        "description": "Pull a container image into a Talos node's cache, e.g. to pre-warm it before pods are scheduled",
+        "annotations": mutating_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node"
+                },
+                "image": {
+                    "type": "string",
+                    "description": "Image reference to pull, e.g. registry.k8s.io/pause:3.10"
+                },
+                "namespace": image_namespace_property()
+            },
+            "required": ["node", "image"]
+        }
+    })
+}
+
+fn get_time_schema() -> Value {
+    json!({
+        "name": "get_time",
+        "description": "Get current time from a Talos node",
+        "annotations": read_only_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to query"
+                },
+                "check": {
+                    "type": "string",
+                    "description": "Check server time against specified NTP server (e.g., 'pool.ntp.org')"
+                }
+            },
+            "required": ["node"]
+        }
+    })
+}
+
+fn get_collect_diagnostics_schema() -> Value {
+    json!({
+        "name": "collect_diagnostics",
+        "description": "Run a battery of read-only checks (version, health, kubelet status, dmesg, events, disks, interfaces) against a Talos node and return each result with per-check success or error",
+        "annotations": read_only_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to diagnose"
+                }
+            },
+            "required": ["node"]
+        }
+    })
+}
+
+fn get_support_bundle_schema() -> Value {
+    json!({
+        "name": "get_support_bundle",
+        "description": "Collect a talosctl support bundle (a zip of logs, resources and diagnostics for filing issues) from cluster nodes into a local file; returns the path and size, not the archive",
+        "annotations": mutating_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "control_planes": {
+                    "type": "array",
+                    "description": "Array of IP addresses or hostnames of control plane nodes to collect from",
+                    "items": {"type": "string"}
+                },
+                "worker_nodes": {
+                    "type": "array",
+                    "description": "Array of IP addresses or hostnames of worker nodes to collect from",
+                    "items": {"type": "string"}
+                },
+                "node": {
+                    "type": "string",
+                    "description": "Single node to collect from when control_planes and worker_nodes are omitted"
+                },
+                "output": {
+                    "type": "string",
+                    "description": "Local file to write the bundle to; must be inside TALOS_WORK_DIR, where relative paths are placed (defaults to support-<timestamp>.zip there)"
+                }
+            },
+            "required": []
+        }
+    })
+}
+
+// Node management schemas
+fn get_reboot_node_schema() -> Value {
+    json!({
+        "name": "reboot_node",
+        "description": "Reboot a Talos node (DESTRUCTIVE OPERATION)",
+        "annotations": destructive_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to reboot"
+                },
+                "mode": {
+                    "type": "string",
+                    "description": "Reboot mode: default, or powercycle to skip the graceful kexec path and power cycle a wedged node (defaults to default)",
+                    "enum": REBOOT_MODES,
+                    "default": "default"
+                }
+            },
+            "required": ["node"]
+        }
+    })
+}
+
+fn get_wait_node_ready_schema() -> Value {
+    json!({
+        "name": "wait_node_ready",
+        "description": "Wait for a Talos node to come back online after a reboot or upgrade by polling its API until it responds",
+        "annotations": read_only_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to wait for"
+                },
+                "timeout": {
+                    "type": "string",
+                    "description": "Maximum time to wait, e.g. 90s or 10m (defaults to 5m)",
+                    "default": "5m"
+                },
+                "wait_for_down": {
+                    "type": "boolean",
+                    "description": "First wait for the node to stop responding, so a node that has not gone down yet is not reported ready (use right after reboot_node or upgrade_node)",
+                    "default": false
+                }
+            },
+            "required": ["node"]
+        }
+    })
+}
+
+fn get_shutdown_node_schema() -> Value {
+    json!({
+        "name": "shutdown_node",
+        "description": "Shutdown a Talos node (DESTRUCTIVE OPERATION)",
+        "annotations": destructive_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to shutdown"
+                }
+            },
+            "required": ["node"]
+        }
+    })
+}
+
+fn get_reset_node_schema() -> Value {
+    json!({
+        "name": "reset_node",
+        "description": "Reset a Talos node to factory defaults (DESTRUCTIVE OPERATION)",
+        "annotations": destructive_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to reset"
+                },
+                "reboot": {
+                    "type": "boolean",
+                    "description": "Reboot the node after resetting instead of powering it off (defaults to false)",
+                    "default": false
+                },
+                "maintenance": {
+                    "type": "boolean",
+                    "description": "Wipe only the STATE and EPHEMERAL partitions and reboot so the node returns to maintenance mode, ready for a new config (defaults to false; overrides reboot, not combinable with wipe)",
+                    "default": false
+                },
+                "graceful": {
+                    "type": "boolean",
+                    "description": "Cordon, drain and leave etcd before resetting; set false for a dead or unreachable node (defaults to true)",
+                    "default": true
+                },
+                "wipe": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "System partition labels to wipe (--system-labels-to-wipe), e.g. [\"STATE\", \"EPHEMERAL\"]; defaults to wiping all system partitions"
+                },
+                "wipe_user_disks": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "User disks to wipe as well (--user-disks-to-wipe), e.g. [\"/dev/sdb\"]"
+                },
+                "confirm": confirm_property()
+            },
+            "required": ["node", "confirm"]
+        }
+    })
+}
+
+// Configuration schemas
+fn get_apply_config_schema() -> Value {
+    json!({
+        "name": "apply_config",
+        "description": "Apply a configuration file to a Talos node",
+        "annotations": destructive_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to configure"
+                },
+                "file": {
+                    "type": "string",
+                    "description": "Path to the configuration file to apply, on the server's filesystem (use either file or content)"
+                },
+                "content": {
+                    "type": "string",
+                    "description": "Machine configuration YAML to apply, instead of a file path"
+                },
+                "mode": {
+                    "type": "string",
+                    "description": "How to apply the change: auto (default, reboots only if needed), no-reboot, reboot, staged (applied on next boot) or try (reverted unless confirmed within timeout)",
+                    "enum": APPLY_CONFIG_MODES
+                },
+                "timeout": {
+                    "type": "string",
+                    "description": "How long a try-mode change stays applied before it is reverted, e.g. 1m (talosctl default; only valid with mode try)"
+                },
+                "dry_run": dry_run_property()
+            },
+            "required": ["node"]
+        }
+    })
+}
+
+fn get_patch_config_schema() -> Value {
+    json!({
+        "name": "patch_config",
+        "description": "Apply a JSON patch to a node's machine config, e.g. to add a registry mirror, without resending the whole config",
+        "annotations": destructive_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to patch"
+                },
+                "patch": {
+                    "type": ["string", "object", "array"],
+                    "description": "JSON patch to apply: an RFC 6902 operation array (each op with path, plus value for add/replace/test or from for move/copy), or a merge patch object, given as JSON or a JSON string"
+                },
+                "mode": {
+                    "type": "string",
+                    "description": "How to apply the change: auto (default, reboots only if needed), no-reboot, reboot, staged (applied on next boot) or try",
+                    "enum": APPLY_CONFIG_MODES
+                },
+                "dry_run": dry_run_property(),
+                "confirm": confirm_property()
+            },
+            "required": ["node", "patch"]
+        }
+    })
+}
+
+fn get_machine_config_schema() -> Value {
+    json!({
+        "name": "get_machine_config",
+        "description": "Get a node's running machine config as YAML, with tokens, keys and other secrets redacted by default",
+        "annotations": read_only_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to query"
+                },
+                "redact_secrets": {
+                    "type": "boolean",
+                    "description": "Replace tokens, keys, certificates and other secrets with a placeholder (defaults to true)",
+                    "default": true
+                }
+            },
+            "required": ["node"]
+        }
+    })
+}
+
+fn get_validate_config_schema() -> Value {
+    json!({
+        "name": "validate_config",
+        "description": "Validate a Talos configuration file",
+        "annotations": read_only_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "config": {
+                    "type": "string",
+                    "description": "Path to the configuration file to validate"
+                },
+                "mode": {
+                    "type": "string",
+                    "description": "Validation mode (defaults to 'container')",
+                    "default": "container"
+                }
+            },
+            "required": ["config"]
+        }
+    })
+}
+
+fn get_kubeconfig_schema() -> Value {
+    json!({
+        "name": "get_kubeconfig",
+        "description": "Retrieve the cluster admin kubeconfig from a control plane node, returned as YAML or written to a local file. The kubeconfig contains credentials; handle it as a secret",
+        "annotations": mutating_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of a control plane node"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "Local file to write the kubeconfig to instead of returning it; must be inside TALOS_WORK_DIR, where relative paths are placed"
+                },
+                "merge": {
+                    "type": "boolean",
+                    "description": "Merge into an existing kubeconfig at path rather than replacing it (defaults to true; only used with path)",
+                    "default": true
+                },
+                "force": {
+                    "type": "boolean",
+                    "description": "Overwrite an existing file at path without prompting (defaults to false; only used with path)",
+                    "default": false
+                }
+            },
+            "required": ["node"]
+        }
+    })
+}
+
+// etcd management schemas
+fn get_etcd_status_schema() -> Value {
+    json!({
+        "name": "get_etcd_status",
+        "description": "Get etcd cluster status from a Talos node",
+        "annotations": read_only_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to query"
+                },
+                "strict": strict_property()
+            },
+            "required": ["node"]
+        }
+    })
+}
+
+fn get_etcd_members_schema() -> Value {
+    json!({
+        "name": "get_etcd_members",
+        "description": "Get etcd cluster member information from a Talos node",
+        "annotations": read_only_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to query"
+                },
+                "strict": strict_property()
+            },
+            "required": ["node"]
+        }
+    })
+}
+
+fn get_bootstrap_etcd_schema() -> Value {
+    json!({
+        "name": "bootstrap_etcd",
+        "description": "Bootstrap etcd cluster on a Talos node; refused if etcd is already bootstrapped unless forced",
+        "annotations": destructive_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to bootstrap"
+                },
+                "force": {
+                    "type": "boolean",
+                    "description": "Bootstrap even though etcd status shows the cluster is already bootstrapped, which can split etcd (defaults to false)",
+                    "default": false
+                },
+                "strict": strict_property()
+            },
+            "required": ["node"]
+        }
+    })
+}
+
+fn get_defrag_etcd_schema() -> Value {
+    json!({
+        "name": "defrag_etcd",
+        "description": "Defragment etcd database on a Talos node",
+        "annotations": destructive_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to defragment"
+                },
+                "strict": strict_property()
+            },
+            "required": ["node"]
+        }
+    })
+}
+
+fn get_etcd_snapshot_schema() -> Value {
+    json!({
+        "name": "etcd_snapshot",
+        "description": "Back up the etcd database of a control plane node to a local snapshot file",
+        "annotations": mutating_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the control plane node to snapshot"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "Local file to write the snapshot to; must be inside TALOS_WORK_DIR, where relative paths are placed (defaults to etcd-<node>-<timestamp>.snapshot there)"
+                },
+                "overwrite": {
+                    "type": "boolean",
+                    "description": "Replace the file if it already exists (defaults to false)",
+                    "default": false
+                },
+                "strict": strict_property()
+            },
+            "required": ["node"]
+        }
+    })
+}
+
+fn get_etcd_restore_schema() -> Value {
     json!({
-        "name": "get_time",
-        "description": "Get current time from a Talos node",
+        "name": "etcd_restore",
+        "description": "Recover etcd by bootstrapping a control plane node from a snapshot (DESTRUCTIVE OPERATION: discards current etcd state; requires confirm)",
+        "annotations": destructive_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
                 "node": {
                     "type": "string",
-                    "description": "IP address or hostname of the Talos node to query"
+                    "description": "IP address or hostname of the control plane node to bootstrap"
                 },
-                "check": {
+                "path": {
                     "type": "string",
-                    "description": "Check server time against specified NTP server (e.g., 'pool.ntp.org')"
-                }
+                    "description": "Local snapshot file to recover from; must be inside TALOS_WORK_DIR, where relative paths are resolved"
+                },
+                "confirm": confirm_property(),
+                "skip_hash_check": {
+                    "type": "boolean",
+                    "description": "Skip the snapshot integrity check, for snapshots copied from the etcd data directory (defaults to false)",
+                    "default": false
+                },
+                "strict": strict_property()
             },
-            "required": ["node"]
+            "required": ["node", "path", "confirm"]
         }
     })
 }
 
-// Node management schemas
-fn get_reboot_node_schema() -> Value {
+fn get_etcd_remove_member_schema() -> Value {
     json!({
-        "name": "reboot_node",
-        "description": "Reboot a Talos node (DESTRUCTIVE OPERATION)",
+        "name": "etcd_remove_member",
+        "description": "Remove a member from the etcd cluster, e.g. a failed control plane being replaced (DESTRUCTIVE OPERATION: requires confirm)",
+        "annotations": destructive_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
                 "node": {
                     "type": "string",
-                    "description": "IP address or hostname of the Talos node to reboot"
-                }
+                    "description": "IP address or hostname of a healthy control plane node to run the removal through"
+                },
+                "member_id": {
+                    "type": "string",
+                    "description": "ID of the etcd member to remove, as listed by get_etcd_members"
+                },
+                "confirm": confirm_property(),
+                "strict": strict_property()
             },
-            "required": ["node"]
+            "required": ["node", "member_id", "confirm"]
         }
     })
 }
 
-fn get_shutdown_node_schema() -> Value {
+fn get_etcd_forfeit_leadership_schema() -> Value {
     json!({
-        "name": "shutdown_node",
-        "description": "Shutdown a Talos node (DESTRUCTIVE OPERATION)",
+        "name": "etcd_forfeit_leadership",
+        "description": "Make a control plane node's etcd member give up leadership, e.g. before taking it down for maintenance",
+        "annotations": mutating_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
                 "node": {
                     "type": "string",
-                    "description": "IP address or hostname of the Talos node to shutdown"
-                }
+                    "description": "IP address or hostname of the control plane node whose etcd member should step down"
+                },
+                "strict": strict_property()
             },
             "required": ["node"]
         }
     })
 }
 
-fn get_reset_node_schema() -> Value {
+// Network monitoring schemas
+fn get_netstat_schema() -> Value {
     json!({
-        "name": "reset_node",
-        "description": "Reset a Talos node to factory defaults (DESTRUCTIVE OPERATION)",
+        "name": "get_netstat",
+        "description": "Get network connection statistics from a Talos node",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
                 "node": {
                     "type": "string",
-                    "description": "IP address or hostname of the Talos node to reset"
+                    "description": "IP address or hostname of the Talos node to query"
                 }
             },
             "required": ["node"]
@@ -603,78 +1711,110 @@ fn get_reset_node_schema() -> Value {
     })
 }
 
-// Configuration schemas
-fn get_apply_config_schema() -> Value {
+fn get_capture_packets_schema() -> Value {
     json!({
-        "name": "apply_config",
-        "description": "Apply a configuration file to a Talos node",
+        "name": "capture_packets",
+        "description": "Capture network packets on a Talos node interface as pcap, written to a local file or returned base64-encoded",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
                 "node": {
                     "type": "string",
-                    "description": "IP address or hostname of the Talos node to configure"
+                    "description": "IP address or hostname of the Talos node to capture from"
                 },
-                "file": {
+                "interface": {
+                    "type": "string",
+                    "description": "Network interface to capture from (defaults to eth0)",
+                    "default": "eth0"
+                },
+                "duration": {
+                    "type": "string",
+                    "description": "How long to capture for, which bounds the capture (defaults to 10s)",
+                    "default": "10s"
+                },
+                "filter": {
+                    "type": "string",
+                    "description": "BPF filter expression selecting the packets to capture, e.g. 'tcp port 6443'"
+                },
+                "output_file": {
                     "type": "string",
-                    "description": "Path to the configuration file to apply"
+                    "description": "Local file to write the pcap to, returning its path, size and packet count; must be inside TALOS_WORK_DIR, where relative paths are placed. Without it the capture is returned base64-encoded"
+                },
+                "max_bytes": {
+                    "type": "integer",
+                    "description": "Cap on an inline (base64) capture, cut at a packet boundary (defaults to 65536; 0 for no limit)",
+                    "minimum": 0
                 }
             },
-            "required": ["node", "file"]
+            "required": ["node"]
         }
     })
 }
 
-fn get_validate_config_schema() -> Value {
+fn get_network_io_cgroups_schema() -> Value {
     json!({
-        "name": "validate_config",
-        "description": "Validate a Talos configuration file",
+        "name": "get_network_io_cgroups",
+        "description": "Get network I/O cgroup statistics from a Talos node",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
-                "config": {
-                    "type": "string",
-                    "description": "Path to the configuration file to validate"
-                },
-                "mode": {
+                "node": {
                     "type": "string",
-                    "description": "Validation mode (defaults to 'container')",
-                    "default": "container"
+                    "description": "IP address or hostname of the Talos node to query"
                 }
             },
-            "required": ["config"]
+            "required": ["node"]
         }
     })
 }
 
-// etcd management schemas
-fn get_etcd_status_schema() -> Value {
+fn get_ping_node_schema() -> Value {
     json!({
-        "name": "get_etcd_status",
-        "description": "Get etcd cluster status from a Talos node",
+        "name": "ping_node",
+        "description": "Check node-to-node connectivity by reaching a target node's Talos API through a source node, reporting reachability and the elapsed time of the check, which includes talosctl startup and TLS setup so it is an upper bound on network latency rather than a round-trip time (distinct from the MCP protocol ping)",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
                 "node": {
                     "type": "string",
-                    "description": "IP address or hostname of the Talos node to query"
+                    "description": "IP address or hostname of the source Talos node to test from"
+                },
+                "target": {
+                    "type": "string",
+                    "description": "IP address or hostname of the target node to reach from the source node"
                 }
             },
-            "required": ["node"]
+            "required": ["node", "target"]
         }
     })
 }
 
-fn get_etcd_members_schema() -> Value {
+fn get_events_schema() -> Value {
     json!({
-        "name": "get_etcd_members",
-        "description": "Get etcd cluster member information from a Talos node",
+        "name": "get_events",
+        "description": "Get system events from a Talos node parsed into timestamp, type, actor and message, optionally filtered by type and time, e.g. to spot recent reboots or config applies (captures the event stream for a few seconds)",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
                 "node": {
                     "type": "string",
                     "description": "IP address or hostname of the Talos node to query"
+                },
+                "tail": {
+                    "type": "integer",
+                    "description": "Number of most recent events to return, counted after the type filter (use -1 for the full history; defaults to none)"
+                },
+                "type": {
+                    "type": "string",
+                    "description": "Only return events of this type, by full name or short name (e.g., ServiceStateEvent, runtime.SequenceEvent)"
+                },
+                "since": {
+                    "type": "string",
+                    "description": "Only return events from this long ago onwards (e.g., '15m') or since an RFC3339 time, passed to talosctl as --duration"
                 }
             },
             "required": ["node"]
@@ -682,16 +1822,22 @@ fn get_etcd_members_schema() -> Value {
     })
 }
 
-fn get_bootstrap_etcd_schema() -> Value {
+fn get_watch_events_schema() -> Value {
     json!({
-        "name": "bootstrap_etcd",
-        "description": "Bootstrap etcd cluster on a Talos node",
+        "name": "watch_events",
+        "description": "Stream live system events from a Talos node as progress notifications until cancelled or the duration elapses",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
                 "node": {
                     "type": "string",
-                    "description": "IP address or hostname of the Talos node to bootstrap"
+                    "description": "IP address or hostname of the Talos node to watch"
+                },
+                "duration": {
+                    "type": "string",
+                    "description": "How long to watch for events, e.g. '30s' or '5m' (defaults to 60s)",
+                    "default": "60s"
                 }
             },
             "required": ["node"]
@@ -699,28 +1845,47 @@ fn get_bootstrap_etcd_schema() -> Value {
     })
 }
 
-fn get_defrag_etcd_schema() -> Value {
+// Resource inspection schemas
+fn get_resource_schema() -> Value {
     json!({
-        "name": "defrag_etcd",
-        "description": "Defragment etcd database on a Talos node",
+        "name": "get_resource",
+        "description": "Get any Talos resource type by name (e.g. members, kubeletconfigs, certsans), optionally a single resource by id; use list_resource_types to discover types",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
                 "node": {
                     "type": "string",
-                    "description": "IP address or hostname of the Talos node to defragment"
-                }
+                    "description": "IP address or hostname of the Talos node to query"
+                },
+                "resource": {
+                    "type": "string",
+                    "description": "Resource type, alias or full type name (e.g. 'members', 'kubeletconfigs.kubernetes.talos.dev')"
+                },
+                "id": {
+                    "type": "string",
+                    "description": "Only get the resource with this id"
+                },
+                "namespace": namespace_property(),
+                "output": {
+                    "type": "string",
+                    "description": "Output mode (default: table)",
+                    "enum": ["json", "table", "yaml", "jsonpath"],
+                    "default": "table"
+                },
+                "jsonpath": jsonpath_property(),
+                "selector": selector_property()
             },
-            "required": ["node"]
+            "required": ["node", "resource"]
         }
     })
 }
 
-// Network monitoring schemas
-fn get_netstat_schema() -> Value {
+fn get_list_resource_types_schema() -> Value {
     json!({
-        "name": "get_netstat",
-        "description": "Get network connection statistics from a Talos node",
+        "name": "list_resource_types",
+        "description": "List the Talos resource types a node serves (type names, aliases and default namespaces) so valid types can be enumerated before querying them",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
@@ -734,43 +1899,83 @@ fn get_netstat_schema() -> Value {
     })
 }
 
-fn get_capture_packets_schema() -> Value {
+fn get_compare_resource_schema() -> Value {
     json!({
-        "name": "capture_packets",
-        "description": "Capture network packets on a Talos node interface",
+        "name": "compare_resource",
+        "description": "Fetch a Talos resource from several nodes and report per-node values plus the spec fields where they disagree, for configuration drift detection",
+        "annotations": read_only_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "resource": {
+                    "type": "string",
+                    "description": "Resource type to compare, e.g. machineconfig, kernelparamstatus or members"
+                },
+                "nodes": {
+                    "type": "array",
+                    "description": "IP addresses or hostnames of the nodes to compare (at least two)",
+                    "items": {"type": "string"}
+                },
+                "id": {
+                    "type": "string",
+                    "description": "Only compare the resource with this ID"
+                },
+                "namespace": namespace_property()
+            },
+            "required": ["resource", "nodes"]
+        }
+    })
+}
+
+fn get_watch_resource_schema() -> Value {
+    json!({
+        "name": "watch_resource",
+        "description": "Watch a Talos resource for changes, streaming each created/updated/deleted event with the resource body as progress notifications until cancelled or the duration elapses",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
                 "node": {
                     "type": "string",
-                    "description": "IP address or hostname of the Talos node to capture from"
+                    "description": "IP address or hostname of the Talos node to watch"
                 },
-                "interface": {
+                "resource": {
                     "type": "string",
-                    "description": "Network interface to capture from (defaults to eth0)",
-                    "default": "eth0"
+                    "description": "Resource type to watch (e.g., machineconfigstatus, members, addresses)"
+                },
+                "id": {
+                    "type": "string",
+                    "description": "Watch only the resource with this ID"
                 },
+                "namespace": namespace_property(),
                 "duration": {
                     "type": "string",
-                    "description": "Duration to capture packets (defaults to 10s)",
-                    "default": "10s"
+                    "description": "How long to watch for changes, e.g. '30s' or '5m' (defaults to 60s)",
+                    "default": "60s"
                 }
             },
-            "required": ["node"]
+            "required": ["node", "resource"]
         }
     })
 }
 
-fn get_network_io_cgroups_schema() -> Value {
+// Controller runtime inspection schemas
+fn get_inspect_dependencies_schema() -> Value {
     json!({
-        "name": "get_network_io_cgroups",
-        "description": "Get network I/O cgroup statistics from a Talos node",
+        "name": "inspect_dependencies",
+        "description": "Get the controller-runtime dependency graph of a Talos node as Graphviz DOT text, to debug why a controller isn't reconciling",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
                 "node": {
                     "type": "string",
                     "description": "IP address or hostname of the Talos node to query"
+                },
+                "with_resources": {
+                    "type": "boolean",
+                    "description": "Include individual resources in the graph, not just resource types (defaults to false)",
+                    "default": false
                 }
             },
             "required": ["node"]
@@ -778,19 +1983,95 @@ fn get_network_io_cgroups_schema() -> Value {
     })
 }
 
-fn get_events_schema() -> Value {
+// Talosconfig context schemas
+fn get_config_info_schema() -> Value {
     json!({
-        "name": "get_events",
-        "description": "Get system events from a Talos node",
+        "name": "config_info",
+        "description": "Show the active talosconfig context with its endpoints, nodes, roles and certificate expiry, to confirm which cluster tools will target",
+        "annotations": read_only_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {},
+            "required": []
+        }
+    })
+}
+
+fn get_config_contexts_schema() -> Value {
+    json!({
+        "name": "config_contexts",
+        "description": "List the contexts defined in the talosconfig with their endpoints and nodes, marking the current one",
+        "annotations": read_only_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {},
+            "required": []
+        }
+    })
+}
+
+// Cluster generation schemas
+fn get_gen_config_schema() -> Value {
+    json!({
+        "name": "gen_config",
+        "description": "Generate machine configs (controlplane.yaml, worker.yaml) and a talosconfig for a new cluster into a local directory. The files hold the cluster's secrets, so their paths are returned rather than their content by default",
+        "annotations": mutating_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
-                "node": {
+                "cluster_name": {
                     "type": "string",
-                    "description": "IP address or hostname of the Talos node to query"
+                    "description": "Name of the new cluster"
+                },
+                "endpoint": {
+                    "type": "string",
+                    "description": "Kubernetes API endpoint URL, e.g. https://10.0.0.1:6443"
+                },
+                "output_dir": {
+                    "type": "string",
+                    "description": "Directory to write the files to, which must not exist yet; must be inside TALOS_WORK_DIR, where relative paths are placed (defaults to <cluster_name>-config-<timestamp>)"
+                },
+                "with_secrets": {
+                    "type": "string",
+                    "description": "Secrets bundle from gen_secrets to build the configs from, instead of generating new secrets"
+                },
+                "kubernetes_version": {
+                    "type": "string",
+                    "description": "Kubernetes version to configure (talosctl default if omitted)"
+                },
+                "talos_version": {
+                    "type": "string",
+                    "description": "Talos version the configs should be compatible with, e.g. v1.8"
+                },
+                "install_disk": {
+                    "type": "string",
+                    "description": "Disk to install Talos to, e.g. /dev/sda"
+                },
+                "inline": {
+                    "type": "boolean",
+                    "description": "Return the generated files' content instead of their paths (defaults to false; the content includes secrets)",
+                    "default": false
                 }
             },
-            "required": ["node"]
+            "required": ["cluster_name", "endpoint"]
+        }
+    })
+}
+
+fn get_gen_secrets_schema() -> Value {
+    json!({
+        "name": "gen_secrets",
+        "description": "Generate a secrets bundle for a new cluster into a local file and return its path, for use with gen_config's with_secrets",
+        "annotations": mutating_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "output": {
+                    "type": "string",
+                    "description": "File to write the bundle to, which must not exist yet; must be inside TALOS_WORK_DIR, where relative paths are placed (defaults to secrets-<timestamp>.yaml)"
+                }
+            },
+            "required": []
         }
     })
 }
@@ -800,6 +2081,7 @@ fn get_upgrade_node_schema() -> Value {
     json!({
         "name": "upgrade_node",
         "description": "Upgrade a Talos node to a new image version",
+        "annotations": destructive_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
@@ -811,31 +2093,82 @@ fn get_upgrade_node_schema() -> Value {
                     "type": "string",
                     "description": "Container image to upgrade to (defaults to latest installer)",
                     "default": "ghcr.io/siderolabs/installer:latest"
-                }
+                },
+                "preserve": {
+                    "type": "boolean",
+                    "description": "Keep the EPHEMERAL partition's data across the upgrade (defaults to false)",
+                    "default": false
+                },
+                "stage": {
+                    "type": "boolean",
+                    "description": "Stage the upgrade to be performed on the next reboot instead of now (defaults to false)",
+                    "default": false
+                },
+                "reboot_mode": {
+                    "type": "string",
+                    "description": "How the node reboots into the new image: default, or powercycle to skip kexec (defaults to default)",
+                    "enum": REBOOT_MODES,
+                    "default": "default"
+                },
+                "wait": {
+                    "type": "boolean",
+                    "description": "Block until the upgrade finishes and the node is back, reporting talosctl's progress output (defaults to false)",
+                    "default": false
+                },
+                "timeout": {
+                    "type": "string",
+                    "description": "How long wait blocks for the upgrade, e.g. 30m (defaults to 30m; only used with wait)",
+                    "default": "30m"
+                },
+                "dry_run": dry_run_property()
             },
             "required": ["node"]
         }
     })
 }
 
+fn get_rollback_node_schema() -> Value {
+    json!({
+        "name": "rollback_node",
+        "description": "Roll a Talos node back to its previous installation and reboot it, e.g. after a failed upgrade (DESTRUCTIVE OPERATION)",
+        "annotations": destructive_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to roll back"
+                },
+                "confirm": confirm_property()
+            },
+            "required": ["node", "confirm"]
+        }
+    })
+}
+
 fn get_upgrade_k8s_schema() -> Value {
     json!({
         "name": "upgrade_k8s",
-        "description": "Upgrade Kubernetes cluster version",
+        "description": "Upgrade the cluster's Kubernetes control plane and kubelets to a new version, returning talosctl's per-component progress output",
+        "annotations": destructive_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "Control plane node to run the upgrade through (defaults to the talosconfig nodes)"
+                },
                 "from": {
                     "type": "string",
-                    "description": "Current Kubernetes version (defaults to 1.28.0)",
-                    "default": "1.28.0"
+                    "description": "Current Kubernetes version, e.g. 1.29.3 (detected from the cluster when omitted)"
                 },
                 "to": {
                     "type": "string",
-                    "description": "Target Kubernetes version (defaults to 1.29.0)",
-                    "default": "1.29.0"
-                }
-            }
+                    "description": "Target Kubernetes version, e.g. 1.30.1"
+                },
+                "dry_run": dry_run_property()
+            },
+            "required": ["to"]
         }
     })
 }
@@ -845,6 +2178,7 @@ fn get_list_disks_schema() -> Value {
     json!({
         "name": "list_disks",
         "description": "List disk devices on a Talos node",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
@@ -862,6 +2196,7 @@ fn get_list_network_interfaces_schema() -> Value {
     json!({
         "name": "list_network_interfaces",
         "description": "List network interfaces on a Talos node (legacy method)",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
@@ -879,6 +2214,25 @@ fn get_cpu_memory_usage_schema() -> Value {
     json!({
         "name": "get_cpu_memory_usage",
         "description": "Get CPU and memory usage statistics from a Talos node",
+        "annotations": read_only_annotations(),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "node": {
+                    "type": "string",
+                    "description": "IP address or hostname of the Talos node to query"
+                }
+            },
+            "required": ["node"]
+        }
+    })
+}
+
+fn get_dashboard_schema() -> Value {
+    json!({
+        "name": "dashboard",
+        "description": "One-shot overview of a Talos node: memory, CPU cgroups, processes and network connections in a single call",
+        "annotations": read_only_annotations(),
         "inputSchema": {
             "type": "object",
             "properties": {
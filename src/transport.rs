@@ -0,0 +1,222 @@
+use anyhow::{anyhow, Context, Result};
+use std::env;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinSet;
+use tracing::Instrument;
+
+use crate::runner::CommandRunner;
+use crate::streaming::SharedStdout;
+
+// How long a new connection has to send its auth token line.
+const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Pause after a failed accept, e.g. when out of file descriptors, before trying again.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+// Where to accept JSON-RPC connections instead of serving stdio.
+#[derive(Debug, PartialEq)]
+pub enum Listen {
+    Tcp(String),
+    Unix(PathBuf),
+}
+
+// The socket to listen on from TALOS_LISTEN: tcp://host:port or unix:///path. None
+// serves stdio. A socket hands cluster control to anyone who can connect, so it
+// requires TALOS_AUTH_TOKEN.
+pub fn listen_config() -> Result<Option<(Listen, String)>> {
+    let Some(address) = env::var("TALOS_LISTEN")
+        .ok()
+        .filter(|a| !a.trim().is_empty())
+    else {
+        return Ok(None);
+    };
+    let listen = parse_listen(address.trim())?;
+    let token = env::var("TALOS_AUTH_TOKEN")
+        .ok()
+        .filter(|t| !t.trim().is_empty())
+        .context("TALOS_LISTEN requires TALOS_AUTH_TOKEN to be set")?;
+    Ok(Some((listen, token.trim().to_string())))
+}
+
+fn parse_listen(address: &str) -> Result<Listen> {
+    if let Some(addr) = address.strip_prefix("tcp://") {
+        return Ok(Listen::Tcp(addr.to_string()));
+    }
+    if let Some(path) = address.strip_prefix("unix://") {
+        return Ok(Listen::Unix(PathBuf::from(path)));
+    }
+    Err(anyhow!(
+        "Invalid TALOS_LISTEN '{}': expected tcp://host:port or unix:///path",
+        address
+    ))
+}
+
+// Accept connections until `shutdown` resolves, serving each as its own JSON-RPC
+// session once it has sent the auth token as its first line. On shutdown, open
+// sessions stop reading and finish their in-flight requests.
+pub async fn listen(
+    listen: Listen,
+    token: String,
+    runner: Arc<dyn CommandRunner>,
+    shutdown: impl std::future::Future<Output = &'static str>,
+) -> Result<()> {
+    let (stop, stopped) = watch::channel(None);
+    let token = Arc::new(token);
+    let mut sessions = JoinSet::new();
+    tokio::pin!(shutdown);
+
+    match listen {
+        Listen::Tcp(addr) => {
+            let listener = TcpListener::bind(&addr)
+                .await
+                .with_context(|| format!("Failed to listen on {}", addr))?;
+            tracing::info!(address = %format_args!("tcp://{}", addr), "Listening for JSON-RPC connections");
+            loop {
+                tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, peer)) => {
+                            let session = session(stream, peer.to_string(), &token, &runner, &stopped);
+                            sessions.spawn(session);
+                        }
+                        Err(e) => accept_failed(e).await,
+                    },
+                    reason = &mut shutdown => {
+                        let _ = stop.send(Some(reason));
+                        break;
+                    }
+                }
+            }
+        }
+        #[cfg(unix)]
+        Listen::Unix(path) => {
+            let listener = bind_unix(&path)?;
+            tracing::info!(
+                address = %format_args!("unix://{}", path.display()),
+                "Listening for JSON-RPC connections"
+            );
+            loop {
+                tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => {
+                            let session = session(stream, path.display().to_string(), &token, &runner, &stopped);
+                            sessions.spawn(session);
+                        }
+                        Err(e) => accept_failed(e).await,
+                    },
+                    reason = &mut shutdown => {
+                        let _ = stop.send(Some(reason));
+                        break;
+                    }
+                }
+            }
+            let _ = std::fs::remove_file(&path);
+        }
+        #[cfg(not(unix))]
+        Listen::Unix(_) => return Err(anyhow!("Unix sockets are not supported on this platform")),
+    }
+
+    while sessions.join_next().await.is_some() {}
+    Ok(())
+}
+
+// A failed accept (too many open files, a connection aborted before it was accepted)
+// affects only that connection, so keep listening after a short pause.
+async fn accept_failed(e: std::io::Error) {
+    tracing::warn!(error = %e, "Failed to accept a connection");
+    tokio::time::sleep(ACCEPT_BACKOFF).await;
+}
+
+// Bind a Unix socket only the server user can connect to, replacing a stale socket
+// left by an earlier run.
+#[cfg(unix)]
+fn bind_unix(path: &std::path::Path) -> Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(anyhow!(
+                "{} exists and is not a socket; refusing to replace it",
+                path.display()
+            ));
+        }
+        std::fs::remove_file(path)
+            .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+    }
+    let listener = tokio::net::UnixListener::bind(path)
+        .with_context(|| format!("Failed to listen on {}", path.display()))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+        .with_context(|| format!("Failed to restrict {}", path.display()))?;
+    Ok(listener)
+}
+
+// Serve one connection: check its token line, then run the usual JSON-RPC session on it.
+fn session<S>(
+    stream: S,
+    peer: String,
+    token: &Arc<String>,
+    runner: &Arc<dyn CommandRunner>,
+    stopped: &watch::Receiver<Option<&'static str>>,
+) -> impl std::future::Future<Output = ()> + Send + 'static
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let token = token.clone();
+    let runner = runner.clone();
+    let mut stopped = stopped.clone();
+    let connection = tracing::info_span!("connection", peer = %peer);
+    async move {
+        let (read, mut write) = tokio::io::split(stream);
+        let mut input = BufReader::new(read);
+        if let Err(e) = authenticate(&mut input, &token).await {
+            tracing::warn!(error = %e, "Rejected connection");
+            let _ = write.write_all(format!("{}\n", e).as_bytes()).await;
+            return;
+        }
+        tracing::info!("Accepted connection");
+
+        let output: SharedStdout = Arc::new(Mutex::new(Box::new(write)));
+        let shutdown = async move {
+            match stopped.wait_for(Option::is_some).await {
+                Ok(reason) => reason.unwrap_or("shutdown"),
+                Err(_) => "shutdown",
+            }
+        };
+        if let Err(e) = crate::serve(input, output, runner, shutdown).await {
+            tracing::error!(error = %e, "Connection failed");
+        }
+        tracing::info!("Connection closed");
+    }
+    .instrument(connection)
+}
+
+// Read the connection's first line and check it against the token, comparing in
+// constant time so the token can't be guessed byte by byte. The read stops just past
+// the token's length (allowing for \r\n), so an unauthenticated peer can't make the
+// server buffer an unbounded line.
+async fn authenticate(
+    input: &mut (impl tokio::io::AsyncBufRead + Unpin),
+    token: &str,
+) -> Result<()> {
+    let mut line = String::new();
+    let mut first_line = input.take(token.len() as u64 + 2);
+    tokio::time::timeout(AUTH_TIMEOUT, first_line.read_line(&mut line))
+        .await
+        .map_err(|_| anyhow!("no auth token within {}s", AUTH_TIMEOUT.as_secs()))??;
+    let sent = line.trim().as_bytes();
+    let expected = token.as_bytes();
+    let matches = sent.len() == expected.len()
+        && sent
+            .iter()
+            .zip(expected)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0;
+    if !matches {
+        return Err(anyhow!("invalid auth token"));
+    }
+    Ok(())
+}
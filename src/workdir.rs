@@ -0,0 +1,184 @@
+use anyhow::{Context, Result};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::future::Future;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Directory generated artifacts are written to: TALOS_WORK_DIR, or a talos-mcp
+// directory under the system temp dir. Created if missing and returned absolute.
+pub fn work_dir() -> Result<PathBuf> {
+    let dir = env::var("TALOS_WORK_DIR")
+        .ok()
+        .filter(|d| !d.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| env::temp_dir().join("talos-mcp"));
+    if crate::explaining() {
+        return Ok(dir.canonicalize().unwrap_or(dir));
+    }
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create work directory {}", dir.display()))?;
+    dir.canonicalize()
+        .with_context(|| format!("Failed to resolve work directory {}", dir.display()))
+}
+
+// Absolute local path for an artifact, which always lies inside the work dir: a
+// relative path is resolved against it, and an absolute one must already point into
+// it. Nothing may climb out with `..`, so a caller can't overwrite files elsewhere on
+// the server host.
+pub fn artifact_path(path: &str) -> Result<PathBuf> {
+    let requested = Path::new(path);
+    let work = work_dir()?;
+    let outside = || {
+        invalid_params!(
+            "Artifact path '{}' must stay inside the work directory {}",
+            requested.display(),
+            work.display()
+        )
+    };
+    if requested
+        .components()
+        .any(|c| matches!(c, Component::ParentDir))
+    {
+        return Err(outside());
+    }
+    let relative = if requested.is_absolute() {
+        within(requested, &work).ok_or_else(outside)?
+    } else {
+        requested.to_path_buf()
+    };
+    if relative
+        .components()
+        .any(|c| matches!(c, Component::Prefix(_) | Component::RootDir))
+    {
+        return Err(outside());
+    }
+
+    let full = work.join(relative);
+    if crate::explaining() {
+        return Ok(full);
+    }
+    if let Some(parent) = full.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+    }
+    Ok(full)
+}
+
+// The part of an absolute path below `dir`, if any. The path's parent is resolved
+// when it exists, so a symlinked spelling of the work dir (/tmp vs /private/tmp) matches.
+fn within(path: &Path, dir: &Path) -> Option<PathBuf> {
+    let relative = match path.strip_prefix(dir) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => {
+            let parent = path.parent()?.canonicalize().ok()?;
+            parent.strip_prefix(dir).ok()?.join(path.file_name()?)
+        }
+    };
+    (!relative.as_os_str().is_empty()).then_some(relative)
+}
+
+// A private file in the work dir holding sensitive input for talosctl, removed when dropped.
+pub struct TempInput {
+    path: PathBuf,
+}
+
+impl TempInput {
+    // Write content to a new file only the server user can read.
+    pub fn write(name: &str, content: &str) -> Result<Self> {
+        let path = work_dir()?.join(format!(
+            ".{}-{}-{}",
+            name,
+            std::process::id(),
+            next_temp_id()
+        ));
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let input = TempInput { path };
+        if crate::explaining() {
+            return Ok(input);
+        }
+        options
+            .open(&input.path)
+            .and_then(|mut file| file.write_all(content.as_bytes()))
+            .with_context(|| format!("Failed to write {}", input.path.display()))?;
+        Ok(input)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempInput {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                tracing::error!(path = %self.path.display(), error = %e, "Failed to remove temp input");
+            }
+        }
+    }
+}
+
+// Distinguishes temp files of concurrent requests within this process.
+fn next_temp_id() -> u64 {
+    static NEXT_TEMP: AtomicU64 = AtomicU64::new(0);
+    NEXT_TEMP.fetch_add(1, Ordering::Relaxed)
+}
+
+// Run a step that writes an artifact, pointing it at a temporary sibling of `path`
+// that is renamed into place on success and removed on failure, so a failed step
+// never leaves partial output or clobbers an existing file.
+pub async fn produce<T, F, Fut>(path: &Path, step: F) -> Result<T>
+where
+    F: FnOnce(PathBuf) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    // Unique per call, as concurrent requests may produce the same artifact
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(format!(
+        ".partial-{}-{}",
+        std::process::id(),
+        next_temp_id()
+    ));
+    let temp = path.with_file_name(temp_name);
+    // An explained step writes nothing, so there is nothing to move or clean up
+    if crate::explaining() {
+        return step(temp).await;
+    }
+
+    let result = step(temp.clone()).await.and_then(|value| {
+        fs::rename(&temp, path)
+            .with_context(|| format!("Failed to move artifact into {}", path.display()))?;
+        Ok(value)
+    });
+    if result.is_err() {
+        let removed = if temp.is_dir() {
+            fs::remove_dir_all(&temp)
+        } else {
+            fs::remove_file(&temp)
+        };
+        if let Err(e) = removed {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                tracing::error!(path = %temp.display(), error = %e, "Failed to clean up partial artifact");
+            }
+        }
+    }
+    result
+}
+
+// Size in bytes of an artifact a step has produced; nothing is produced when explaining.
+pub fn artifact_size(path: &Path) -> Result<u64> {
+    if crate::explaining() {
+        return Ok(0);
+    }
+    fs::metadata(path)
+        .map(|metadata| metadata.len())
+        .with_context(|| format!("Failed to stat {}", path.display()))
+}